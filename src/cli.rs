@@ -1,6 +1,14 @@
 //! CLI definitions using clap derive
 
-use clap::{Parser, Subcommand};
+use crate::config;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// 🔎 Port Detective — What's running on this port?
 #[derive(Parser, Debug)]
@@ -15,21 +23,33 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
 
-    /// Port number to inspect (shorthand for `portdetective inspect <PORT>`)
-    #[arg(value_name = "PORT")]
+    /// Port number or name to inspect (shorthand for `portdetective inspect <PORT>`)
+    #[arg(value_name = "PORT", value_parser = parse_port)]
     pub port: Option<u16>,
 
-    /// Output as JSON
-    #[arg(long, short, global = true)]
+    /// Output format
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub format: Option<OutputFormat>,
+
+    /// Shorthand for `--format json`
+    #[arg(long, short, global = true, conflicts_with = "format")]
     pub json: bool,
 
     /// Only show TCP connections
-    #[arg(long, global = true, conflicts_with = "udp")]
+    #[arg(long, global = true, conflicts_with_all = ["udp", "any_protocol"])]
     pub tcp: bool,
 
     /// Only show UDP connections
-    #[arg(long, global = true, conflicts_with = "tcp")]
+    #[arg(long, global = true, conflicts_with_all = ["tcp", "any_protocol"])]
     pub udp: bool,
+
+    /// Show TCP and UDP, overriding a configured `protocol`
+    #[arg(long, global = true)]
+    pub any_protocol: bool,
+
+    /// When to color output
+    #[arg(long, global = true, value_name = "WHEN")]
+    pub color: Option<ColorMode>,
 }
 
 impl Cli {
@@ -42,6 +62,42 @@ impl Cli {
     }
 }
 
+impl Commands {
+    /// The name of the command if its only structured output is JSON. `inspect`,
+    /// `list`, `kill` and `sweep` render every `--format`.
+    pub fn json_only(&self) -> Option<&'static str> {
+        let name = match self {
+            Commands::Kill { apply: Some(_), .. } => "kill --apply",
+            Commands::Kill { plan: Some(_), .. } => "kill --plan",
+            Commands::Kill { and_hold: true, .. } => "kill --and-hold",
+            Commands::Inspect { .. }
+            | Commands::List
+            | Commands::Kill { .. }
+            | Commands::Sweep { .. } => {
+                return None;
+            }
+            Commands::Wait { .. } => "wait",
+            Commands::Trap { .. } => "trap",
+            Commands::Run { .. } => "run",
+            Commands::Hold { .. } => "hold",
+            Commands::Release { .. } => "release",
+            Commands::Lease { .. } => "lease",
+            Commands::Restart { .. } => "restart",
+            Commands::Monitor { .. } => "monitor",
+            Commands::Hooks { .. } => "hooks",
+            Commands::Audit { .. } => "audit",
+            Commands::Exposure => "exposure",
+            Commands::Config { .. } => "config",
+            Commands::Project { .. } => "project",
+            Commands::Status => "status",
+            Commands::Snapshot => "snapshot",
+            Commands::Diff { .. } => "diff",
+            Commands::Watch { .. } => "watch",
+        };
+        Some(name)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProtocolFilter {
     TcpOnly,
@@ -49,20 +105,101 @@ pub enum ProtocolFilter {
     Both,
 }
 
+/// How results are printed
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Colored, human-readable output
+    #[default]
+    Table,
+    /// Pretty-printed JSON
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    Csv,
+    Tsv,
+    /// A GitHub-flavored Markdown table
+    Markdown,
+    Yaml,
+}
+
+impl OutputFormat {
+    /// Anything but the human-readable table
+    pub fn is_structured(self) -> bool {
+        self != OutputFormat::Table
+    }
+
+    /// The name `--format` takes
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputFormat::Table => "table",
+            OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Tsv => "tsv",
+            OutputFormat::Markdown => "markdown",
+            OutputFormat::Yaml => "yaml",
+        }
+    }
+}
+
+/// When output is colored
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// When stdout is a terminal and `NO_COLOR` is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Port state `wait` blocks for
+#[derive(ValueEnum, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WaitUntil {
+    Listening,
+    Free,
+}
+
+/// How `run` resolves a busy port
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Kill the current owner
+    Kill,
+    /// Use the next free port instead
+    Next,
+    /// Give up
+    Abort,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Inspect what's running on a specific port
     #[command(visible_alias = "i")]
     Inspect {
-        /// Port number to inspect
+        /// Port number or name to inspect
+        #[arg(value_parser = parse_port)]
         port: u16,
     },
 
-    /// Kill the process running on a specific port
+    /// Kill every process listening on one or more ports
     #[command(visible_alias = "k")]
     Kill {
-        /// Port number to kill
-        port: u16,
+        /// Port number(s) to free
+        #[arg(
+            value_name = "PORT",
+            value_parser = parse_port,
+            required_unless_present_any = ["name", "user", "cwd_under", "older_than", "apply"]
+        )]
+        ports: Vec<u16>,
+
+        #[command(flatten)]
+        selector: Box<SelectorArgs>,
+
+        /// Kill every process the selector matches, not just a single one
+        #[arg(long)]
+        all: bool,
 
         /// Send SIGKILL instead of SIGTERM
         #[arg(long, short)]
@@ -71,11 +208,398 @@ pub enum Commands {
         /// Don't prompt for confirmation (for scripting)
         #[arg(long, short = 'y')]
         no_prompt: bool,
+
+        /// Allow killing PID 1, kernel threads, other users' and protected processes
+        #[arg(long = "i-know-what-im-doing")]
+        unguarded: bool,
+
+        /// Stop managed processes through systemd, Docker, pm2, ... instead of signaling them
+        #[arg(long, conflicts_with = "plan")]
+        via_manager: bool,
+
+        /// Wait up to TIMEOUT for established connections to close before signaling
+        #[arg(long, value_name = "TIMEOUT", value_parser = parse_duration, conflicts_with = "plan")]
+        drain: Option<Duration>,
+
+        /// Watch killed ports this long for a respawned owner (off by default)
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "0")]
+        respawn_window: Duration,
+
+        /// Record killed processes so `restart` can start them again
+        #[arg(long, conflicts_with = "plan")]
+        remember: bool,
+
+        /// Also record secret-looking environment variables (with --remember)
+        #[arg(long, requires = "remember")]
+        remember_secrets: bool,
+
+        /// Take the freed port right after the kill and hold it
+        #[arg(long, conflicts_with_all = ["plan", "name", "user", "cwd_under", "older_than"])]
+        and_hold: bool,
+
+        /// Release the port after this long (with --and-hold)
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "and_hold")]
+        hold_for: Option<Duration>,
+
+        /// Write the resolved targets to a plan file instead of killing them
+        #[arg(long, value_name = "FILE")]
+        plan: Option<PathBuf>,
+
+        /// Re-verify and execute a plan written by --plan
+        #[arg(
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["ports", "plan", "name", "user", "cwd_under", "older_than", "all", "force", "via_manager", "remember", "and_hold"]
+        )]
+        apply: Option<PathBuf>,
+    },
+
+    /// Block until a port is listening or free
+    #[command(visible_alias = "w")]
+    Wait {
+        /// Port number or name to wait on
+        #[arg(value_parser = parse_port)]
+        port: u16,
+
+        /// State to wait for
+        #[arg(long, value_enum, default_value = "listening")]
+        until: WaitUntil,
+
+        /// Give up after this long
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "30s")]
+        timeout: Duration,
+
+        /// How often to check the port
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "200ms")]
+        interval: Duration,
+
+        /// Only count owners whose name or command line matches this regex
+        #[arg(long, value_name = "REGEX")]
+        name: Option<Regex>,
+
+        /// Only count owners run by this user (`me` for yourself)
+        #[arg(long, value_name = "USER")]
+        user: Option<String>,
+    },
+
+    /// Watch a port and catch every process that binds it, however briefly
+    Trap {
+        /// Port number or name to watch
+        #[arg(value_parser = parse_port)]
+        port: u16,
+
+        /// How long to watch
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "60s")]
+        duration: Duration,
+
+        /// How often to poll the socket table
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "20ms")]
+        interval: Duration,
+
+        /// Also append every sighting to this file as JSON lines
+        #[arg(long, value_name = "FILE", value_parser = parse_path)]
+        log: Option<PathBuf>,
+    },
+
+    /// Free a port if needed, then run a command with PORT set
+    Run {
+        /// Port the command wants
+        #[arg(long, value_parser = parse_port)]
+        port: u16,
+
+        /// Resolve a busy port without asking
+        #[arg(long, value_enum, value_name = "ACTION")]
+        on_conflict: Option<OnConflict>,
+
+        /// Command to run, after `--`
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+
+    /// Find and kill your orphaned, detached, idle or old listeners
+    Sweep {
+        /// Count a listener as idle after this long without connections
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "4h")]
+        idle: Duration,
+
+        /// Count a listener as stale once it has run this long
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "7d")]
+        older_than: Duration,
+
+        /// Only show what would be swept
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Send SIGKILL instead of SIGTERM
+        #[arg(long, short)]
+        force: bool,
+
+        /// Skip confirmation prompt
+        #[arg(long = "no-prompt", short = 'y')]
+        no_prompt: bool,
+    },
+
+    /// Bind ports so nothing else can take them, until a signal or timeout
+    Hold {
+        /// Port number(s) or names to hold
+        #[arg(value_name = "PORT", value_parser = parse_port, required = true)]
+        ports: Vec<u16>,
+
+        /// Address to bind
+        #[arg(long, default_value = "0.0.0.0")]
+        addr: IpAddr,
+
+        /// Release the ports after this long
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        timeout: Option<Duration>,
+    },
+
+    /// End a hold started by `hold` or `kill --and-hold` in another shell
+    Release {
+        /// Held port number(s) or names
+        #[arg(value_name = "PORT", value_parser = parse_port, required = true)]
+        ports: Vec<u16>,
+    },
+
+    /// Lease free ports that no other caller on this machine will get
+    #[command(args_conflicts_with_subcommands = true)]
+    Lease {
+        #[command(subcommand)]
+        action: Option<LeaseAction>,
+
+        #[command(flatten)]
+        request: LeaseArgs,
+    },
+
+    /// Start a process killed with `kill --remember` again
+    Restart {
+        /// Port it listened on, or its remembered ID; lists remembered processes when omitted
+        #[arg(value_name = "PORT|ID", value_parser = parse_port_or_id)]
+        target: Option<u32>,
     },
 
     /// List all listening ports
     #[command(visible_alias = "l", visible_alias = "ls")]
     List,
+
+    /// Stream port open, close and owner change events as JSON lines
+    Monitor {
+        /// How often to take a snapshot
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "1s")]
+        interval: Duration,
+
+        /// Report ports already open at start as `opened`
+        #[arg(long)]
+        existing: bool,
+    },
+
+    /// Run configured hooks when ports open, close or change owner
+    Hooks {
+        /// Hooks file (default: ~/.config/portdetective/hooks.toml)
+        #[arg(long, value_name = "FILE", value_parser = parse_path)]
+        config: Option<PathBuf>,
+
+        /// How often to take a snapshot
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "1s")]
+        interval: Duration,
+    },
+
+    /// Check the listening ports against the policy in `.portdetective.toml`
+    Audit {
+        /// Policy file (default: ./.portdetective.toml)
+        #[arg(long, value_name = "FILE", value_parser = parse_path)]
+        policy: Option<PathBuf>,
+    },
+
+    /// List listeners reachable from the network, riskiest first
+    Exposure,
+
+    /// Show the effective configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Check the ports a repo's files declare against the ports already in use
+    Project {
+        /// Directory in the repo to scan (default: the current directory)
+        #[arg(value_name = "DIR", value_parser = parse_path)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Show whether each named port in `.portdetective.toml` is up, and who holds it
+    Status,
+
+    /// Print the listening ports as a JSON snapshot for a later `diff`
+    Snapshot,
+
+    /// Compare a snapshot with another one or with the live port list
+    Diff {
+        /// Snapshot taken earlier
+        before: PathBuf,
+
+        /// Snapshot to compare with, or `live` (the default)
+        after: Option<PathBuf>,
+    },
+
+    /// Keep the port list on screen, refreshing it on an interval
+    Watch {
+        /// How often to refresh
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "2s")]
+        interval: Duration,
+    },
+}
+
+/// Options for handing out a lease
+#[derive(Args, Debug, Clone)]
+pub struct LeaseArgs {
+    /// Ports to lease from, e.g. 20000-21000
+    #[arg(long, value_name = "START-END", value_parser = parse_port_range, default_value = "20000-30000")]
+    pub range: RangeInclusive<u16>,
+
+    /// How long the lease lasts
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, default_value = "10m")]
+    pub ttl: Duration,
+
+    /// Who holds the lease, e.g. a CI job ID (defaults to your user name)
+    #[arg(long)]
+    pub owner: Option<String>,
+
+    /// Number of ports to lease
+    #[arg(long, default_value_t = 1)]
+    pub count: usize,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Print every setting's value and where it came from
+    Show,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LeaseAction {
+    /// Give leased ports back
+    #[command(group(clap::ArgGroup::new("which").required(true).args(["ports", "owner"])))]
+    Release {
+        /// Leased port number(s) or names
+        #[arg(value_name = "PORT", value_parser = parse_port)]
+        ports: Vec<u16>,
+
+        /// Release every lease of this owner
+        #[arg(long)]
+        owner: Option<String>,
+    },
+
+    /// Show the leases currently handed out
+    List,
+}
+
+/// Criteria for picking processes from the port list
+#[derive(Args, Debug, Clone, Default)]
+pub struct SelectorArgs {
+    /// Only processes whose name or command line matches this regex
+    #[arg(long, value_name = "REGEX")]
+    pub name: Option<Regex>,
+
+    /// Only processes owned by this user (`me` for the current user)
+    #[arg(long, value_name = "USER")]
+    pub user: Option<String>,
+
+    /// Only processes whose working directory is under this path
+    #[arg(long, value_name = "DIR", value_parser = parse_path)]
+    pub cwd_under: Option<PathBuf>,
+
+    /// Only processes started longer ago than this (e.g. 30m, 4h, 2d)
+    #[arg(long, value_name = "AGE", value_parser = parse_duration)]
+    pub older_than: Option<Duration>,
+}
+
+/// Parse a duration such as `200ms`, `30s`, `10m`, `4h` or `2d`.
+///
+/// A bare number is taken as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", s))?;
+
+    let millis = match unit {
+        "ms" => 1,
+        "" | "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => {
+            return Err(format!(
+                "invalid duration unit `{}` (use ms, s, m, h or d)",
+                unit
+            ));
+        }
+    };
+    Ok(Duration::from_millis(value.saturating_mul(millis)))
+}
+
+/// Parse a port number, or a name declared under `[ports]` in the config
+pub fn parse_port(s: &str) -> Result<u16, String> {
+    resolve_port(s, config::port_names())
+}
+
+fn resolve_port(s: &str, names: &BTreeMap<String, u16>) -> Result<u16, String> {
+    if s.starts_with(|c: char| c.is_ascii_digit()) {
+        return s.parse().map_err(|_| format!("invalid port `{}`", s));
+    }
+    names.get(s).copied().ok_or_else(|| {
+        if names.is_empty() {
+            format!("`{}` is not a port number", s)
+        } else {
+            let known: Vec<&str> = names.keys().map(String::as_str).collect();
+            format!(
+                "`{}` is not a port number or a named port ({})",
+                s,
+                known.join(", ")
+            )
+        }
+    })
+}
+
+/// Parse a remembered ID, a port number or a named port
+pub fn parse_port_or_id(s: &str) -> Result<u32, String> {
+    match s.parse() {
+        Ok(id) => Ok(id),
+        Err(_) => parse_port(s).map(u32::from),
+    }
+}
+
+/// Parse an inclusive port range like `20000-21000`
+pub fn parse_port_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = s
+        .split_once('-')
+        .ok_or_else(|| format!("invalid port range `{}` (use START-END)", s))?;
+    let start: u16 = start
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid port `{}`", start))?;
+    let end: u16 = end
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid port `{}`", end))?;
+    if start == 0 || start > end {
+        return Err(format!("invalid port range `{}`", s));
+    }
+    Ok(start..=end)
+}
+
+/// Parse a path, expanding a leading `~` to the home directory
+pub fn parse_path(s: &str) -> Result<PathBuf, String> {
+    match s.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = std::env::var_os("HOME").ok_or("cannot expand `~`: HOME is not set")?;
+            Ok(PathBuf::from(home).join(rest.trim_start_matches('/')))
+        }
+        _ => Ok(PathBuf::from(s)),
+    }
 }
 
 #[cfg(test)]
@@ -90,6 +614,22 @@ mod tests {
         assert!(cli.command.is_none());
     }
 
+    #[test]
+    fn test_resolve_port() {
+        let names = BTreeMap::from([("api".to_string(), 3000), ("web".to_string(), 5173)]);
+        assert_eq!(resolve_port("8080", &names), Ok(8080));
+        assert_eq!(resolve_port("api", &names), Ok(3000));
+        assert_eq!(
+            resolve_port("db", &names).unwrap_err(),
+            "`db` is not a port number or a named port (api, web)"
+        );
+        assert!(resolve_port("70000", &names).is_err());
+        assert_eq!(
+            resolve_port("api", &BTreeMap::new()).unwrap_err(),
+            "`api` is not a port number"
+        );
+    }
+
     #[test]
     fn test_inspect_subcommand() {
         let cli = Cli::parse_from(["portdetective", "inspect", "8080"]);
@@ -113,11 +653,14 @@ mod tests {
         let cli = Cli::parse_from(["portdetective", "kill", "3000"]);
         match cli.command {
             Some(Commands::Kill {
-                port,
+                ports,
                 force,
                 no_prompt,
+                all,
+                ..
             }) => {
-                assert_eq!(port, 3000);
+                assert_eq!(ports, vec![3000]);
+                assert!(!all);
                 assert!(!force);
                 assert!(!no_prompt);
             }
@@ -143,6 +686,597 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_kill_multiple_ports() {
+        let cli = Cli::parse_from(["portdetective", "kill", "3000", "5173", "-y"]);
+        match cli.command {
+            Some(Commands::Kill {
+                ports, no_prompt, ..
+            }) => {
+                assert_eq!(ports, vec![3000, 5173]);
+                assert!(no_prompt);
+            }
+            _ => panic!("Expected Kill command"),
+        }
+    }
+
+    #[test]
+    fn test_kill_requires_port() {
+        let result = Cli::try_parse_from(["portdetective", "kill"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_kill_guardrail_override() {
+        let cli = Cli::parse_from(["portdetective", "kill", "22", "--i-know-what-im-doing"]);
+        match cli.command {
+            Some(Commands::Kill { unguarded, .. }) => assert!(unguarded),
+            _ => panic!("Expected Kill command"),
+        }
+    }
+
+    #[test]
+    fn test_kill_via_manager() {
+        let cli = Cli::parse_from(["portdetective", "kill", "80", "--via-manager"]);
+        match cli.command {
+            Some(Commands::Kill { via_manager, .. }) => assert!(via_manager),
+            _ => panic!("Expected Kill command"),
+        }
+    }
+
+    #[test]
+    fn test_kill_drain() {
+        let cli = Cli::parse_from(["portdetective", "kill", "8080", "--drain", "30s"]);
+        match cli.command {
+            Some(Commands::Kill { drain, .. }) => {
+                assert_eq!(drain, Some(Duration::from_secs(30)));
+            }
+            _ => panic!("Expected Kill command"),
+        }
+
+        let result = Cli::try_parse_from(["portdetective", "kill", "8080", "--drain", "soon"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_kill_respawn_window() {
+        let cli = Cli::parse_from(["portdetective", "kill", "3000"]);
+        match cli.command {
+            Some(Commands::Kill { respawn_window, .. }) => {
+                assert_eq!(respawn_window, Duration::ZERO);
+            }
+            _ => panic!("Expected Kill command"),
+        }
+
+        let cli = Cli::parse_from(["portdetective", "kill", "3000", "--respawn-window", "2s"]);
+        match cli.command {
+            Some(Commands::Kill { respawn_window, .. }) => {
+                assert_eq!(respawn_window, Duration::from_secs(2));
+            }
+            _ => panic!("Expected Kill command"),
+        }
+    }
+
+    #[test]
+    fn test_kill_remember() {
+        let cli = Cli::parse_from(["portdetective", "kill", "3000", "--remember"]);
+        match cli.command {
+            Some(Commands::Kill {
+                remember,
+                remember_secrets,
+                ..
+            }) => {
+                assert!(remember);
+                assert!(!remember_secrets);
+            }
+            _ => panic!("Expected Kill command"),
+        }
+
+        let result = Cli::try_parse_from(["portdetective", "kill", "3000", "--remember-secrets"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_wait_defaults() {
+        let cli = Cli::parse_from(["portdetective", "wait", "5432"]);
+        match cli.command {
+            Some(Commands::Wait {
+                port,
+                until,
+                timeout,
+                interval,
+                name,
+                ..
+            }) => {
+                assert_eq!(port, 5432);
+                assert_eq!(until, WaitUntil::Listening);
+                assert_eq!(timeout, Duration::from_secs(30));
+                assert_eq!(interval, Duration::from_millis(200));
+                assert!(name.is_none());
+            }
+            _ => panic!("Expected Wait command"),
+        }
+    }
+
+    #[test]
+    fn test_wait_options() {
+        let cli = Cli::parse_from([
+            "portdetective",
+            "wait",
+            "5432",
+            "--until",
+            "free",
+            "--timeout",
+            "5s",
+            "--name",
+            "postgres",
+            "--user",
+            "me",
+        ]);
+        match cli.command {
+            Some(Commands::Wait {
+                until,
+                timeout,
+                name,
+                user,
+                ..
+            }) => {
+                assert_eq!(until, WaitUntil::Free);
+                assert_eq!(timeout, Duration::from_secs(5));
+                assert_eq!(name.unwrap().as_str(), "postgres");
+                assert_eq!(user.as_deref(), Some("me"));
+            }
+            _ => panic!("Expected Wait command"),
+        }
+
+        let result = Cli::try_parse_from(["portdetective", "wait", "5432", "--until", "up"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_monitor_defaults() {
+        let cli = Cli::parse_from(["portdetective", "monitor"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Monitor { interval, existing: false }) if interval == Duration::from_secs(1)
+        ));
+
+        let cli = Cli::parse_from(["portdetective", "monitor", "--existing"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Monitor { existing: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_hooks_command() {
+        let cli = Cli::parse_from(["portdetective", "hooks"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Hooks { config: None, interval }) if interval == Duration::from_secs(1)
+        ));
+
+        let cli = Cli::parse_from(["portdetective", "hooks", "--config", "/tmp/hooks.toml"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Hooks { config: Some(path), .. }) if path == std::path::Path::new("/tmp/hooks.toml")
+        ));
+    }
+
+    #[test]
+    fn test_audit_policy() {
+        let cli = Cli::parse_from(["portdetective", "audit"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Audit { policy: None })
+        ));
+
+        let cli = Cli::parse_from(["portdetective", "audit", "--policy", "ci/ports.toml"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Audit { policy: Some(path) }) if path == std::path::Path::new("ci/ports.toml")
+        ));
+    }
+
+    #[test]
+    fn test_project_dir() {
+        let cli = Cli::parse_from(["portdetective", "project"]);
+        assert!(matches!(cli.command, Some(Commands::Project { dir: None })));
+
+        let cli = Cli::parse_from(["portdetective", "project", "services/api"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Project { dir: Some(dir) }) if dir == std::path::Path::new("services/api")
+        ));
+    }
+
+    #[test]
+    fn test_config_show() {
+        let cli = Cli::parse_from(["portdetective", "config", "show"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Config {
+                action: ConfigAction::Show
+            })
+        ));
+        assert!(Cli::try_parse_from(["portdetective", "config"]).is_err());
+    }
+
+    #[test]
+    fn test_color_flag() {
+        let cli = Cli::parse_from(["portdetective", "list", "--color", "never"]);
+        assert_eq!(cli.color, Some(ColorMode::Never));
+        assert!(Cli::try_parse_from(["portdetective", "--color", "sometimes", "list"]).is_err());
+    }
+
+    #[test]
+    fn test_diff_arguments() {
+        let cli = Cli::parse_from(["portdetective", "diff", "before.json"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Diff { after: None, .. })
+        ));
+
+        let cli = Cli::parse_from(["portdetective", "diff", "before.json", "after.json"]);
+        match cli.command {
+            Some(Commands::Diff { before, after }) => {
+                assert_eq!(before, PathBuf::from("before.json"));
+                assert_eq!(after, Some(PathBuf::from("after.json")));
+            }
+            _ => panic!("Expected Diff command"),
+        }
+    }
+
+    #[test]
+    fn test_watch_interval() {
+        let cli = Cli::parse_from(["portdetective", "watch"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Watch { interval }) if interval == Duration::from_secs(2)
+        ));
+
+        let cli = Cli::parse_from(["portdetective", "watch", "--interval", "500ms"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Watch { interval }) if interval == Duration::from_millis(500)
+        ));
+    }
+
+    #[test]
+    fn test_trap_defaults() {
+        let cli = Cli::parse_from(["portdetective", "trap", "8080"]);
+        match cli.command {
+            Some(Commands::Trap {
+                port,
+                duration,
+                interval,
+                log,
+            }) => {
+                assert_eq!(port, 8080);
+                assert_eq!(duration, Duration::from_secs(60));
+                assert_eq!(interval, Duration::from_millis(20));
+                assert!(log.is_none());
+            }
+            _ => panic!("Expected Trap command"),
+        }
+
+        let cli = Cli::parse_from([
+            "portdetective",
+            "trap",
+            "8080",
+            "--duration",
+            "10m",
+            "--log",
+            "/tmp/trap.jsonl",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Trap { duration, log: Some(_), .. }) if duration == Duration::from_secs(600)
+        ));
+    }
+
+    #[test]
+    fn test_run_command() {
+        let cli = Cli::parse_from([
+            "portdetective",
+            "run",
+            "--port",
+            "3000",
+            "--",
+            "npm",
+            "run",
+            "dev",
+        ]);
+        match cli.command {
+            Some(Commands::Run {
+                port,
+                on_conflict,
+                command,
+            }) => {
+                assert_eq!(port, 3000);
+                assert_eq!(on_conflict, None);
+                assert_eq!(command, vec!["npm", "run", "dev"]);
+            }
+            _ => panic!("Expected Run command"),
+        }
+
+        let cli = Cli::parse_from([
+            "portdetective",
+            "run",
+            "--port",
+            "3000",
+            "--on-conflict",
+            "next",
+            "--",
+            "vite",
+            "--port",
+            "3000",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Run {
+                on_conflict: Some(OnConflict::Next),
+                ..
+            })
+        ));
+
+        assert!(Cli::try_parse_from(["portdetective", "run", "--port", "3000"]).is_err());
+    }
+
+    #[test]
+    fn test_sweep_defaults() {
+        let cli = Cli::parse_from(["portdetective", "sweep"]);
+        match cli.command {
+            Some(Commands::Sweep {
+                idle,
+                older_than,
+                dry_run,
+                no_prompt,
+                ..
+            }) => {
+                assert_eq!(idle, Duration::from_secs(4 * 3600));
+                assert_eq!(older_than, Duration::from_secs(7 * 86_400));
+                assert!(!dry_run);
+                assert!(!no_prompt);
+            }
+            _ => panic!("Expected Sweep command"),
+        }
+
+        let cli = Cli::parse_from(["portdetective", "sweep", "--idle", "30m", "--dry-run"]);
+        match cli.command {
+            Some(Commands::Sweep { idle, dry_run, .. }) => {
+                assert_eq!(idle, Duration::from_secs(1800));
+                assert!(dry_run);
+            }
+            _ => panic!("Expected Sweep command"),
+        }
+    }
+
+    #[test]
+    fn test_hold_and_release() {
+        let cli = Cli::parse_from(["portdetective", "hold", "3000", "--tcp", "--timeout", "1m"]);
+        assert!(cli.tcp);
+        match cli.command {
+            Some(Commands::Hold {
+                ports,
+                addr,
+                timeout,
+            }) => {
+                assert_eq!(ports, vec![3000]);
+                assert_eq!(addr.to_string(), "0.0.0.0");
+                assert_eq!(timeout, Some(Duration::from_secs(60)));
+            }
+            _ => panic!("Expected Hold command"),
+        }
+
+        let cli = Cli::parse_from(["portdetective", "release", "3000", "3001"]);
+        assert!(
+            matches!(cli.command, Some(Commands::Release { ports }) if ports == vec![3000, 3001])
+        );
+
+        assert!(Cli::try_parse_from(["portdetective", "hold"]).is_err());
+        assert!(Cli::try_parse_from(["portdetective", "hold", "3000", "--addr", "nope"]).is_err());
+    }
+
+    #[test]
+    fn test_kill_and_hold() {
+        let cli = Cli::parse_from([
+            "portdetective",
+            "kill",
+            "3000",
+            "--and-hold",
+            "--hold-for",
+            "30s",
+        ]);
+        match cli.command {
+            Some(Commands::Kill {
+                and_hold, hold_for, ..
+            }) => {
+                assert!(and_hold);
+                assert_eq!(hold_for, Some(Duration::from_secs(30)));
+            }
+            _ => panic!("Expected Kill command"),
+        }
+
+        let result = Cli::try_parse_from(["portdetective", "kill", "3000", "--hold-for", "30s"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_lease_request() {
+        let cli = Cli::parse_from([
+            "portdetective",
+            "lease",
+            "--range",
+            "20000-21000",
+            "--ttl",
+            "5m",
+            "--owner",
+            "job-7",
+        ]);
+        match cli.command {
+            Some(Commands::Lease {
+                action: None,
+                request,
+            }) => {
+                assert_eq!(request.range, 20000..=21000);
+                assert_eq!(request.ttl, Duration::from_secs(300));
+                assert_eq!(request.owner.as_deref(), Some("job-7"));
+                assert_eq!(request.count, 1);
+            }
+            _ => panic!("Expected Lease command"),
+        }
+    }
+
+    #[test]
+    fn test_lease_release_and_list() {
+        let cli = Cli::parse_from(["portdetective", "lease", "release", "20001"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Lease {
+                action: Some(LeaseAction::Release { ports, owner: None }),
+                ..
+            }) if ports == vec![20001]
+        ));
+
+        let cli = Cli::parse_from(["portdetective", "lease", "list"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Lease {
+                action: Some(LeaseAction::List),
+                ..
+            })
+        ));
+
+        assert!(Cli::try_parse_from(["portdetective", "lease", "release"]).is_err());
+        assert!(Cli::try_parse_from(["portdetective", "lease", "--ttl", "1m", "list"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_port_range() {
+        assert_eq!(parse_port_range("20000-21000"), Ok(20000..=21000));
+        assert_eq!(parse_port_range("8080-8080"), Ok(8080..=8080));
+        assert!(parse_port_range("21000-20000").is_err());
+        assert!(parse_port_range("0-10").is_err());
+        assert!(parse_port_range("20000").is_err());
+        assert!(parse_port_range("1-70000").is_err());
+    }
+
+    #[test]
+    fn test_restart_command() {
+        let cli = Cli::parse_from(["portdetective", "restart", "3000"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Restart { target: Some(3000) })
+        ));
+
+        let cli = Cli::parse_from(["portdetective", "restart"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Restart { target: None })
+        ));
+    }
+
+    #[test]
+    fn test_kill_plan_and_apply() {
+        let cli = Cli::parse_from(["portdetective", "kill", "3000", "--plan", "plan.json"]);
+        match cli.command {
+            Some(Commands::Kill { plan, apply, .. }) => {
+                assert_eq!(plan, Some(PathBuf::from("plan.json")));
+                assert_eq!(apply, None);
+            }
+            _ => panic!("Expected Kill command"),
+        }
+
+        let cli = Cli::parse_from(["portdetective", "kill", "--apply", "plan.json", "-y"]);
+        match cli.command {
+            Some(Commands::Kill { ports, apply, .. }) => {
+                assert!(ports.is_empty());
+                assert_eq!(apply, Some(PathBuf::from("plan.json")));
+            }
+            _ => panic!("Expected Kill command"),
+        }
+    }
+
+    #[test]
+    fn test_kill_apply_conflicts_with_ports() {
+        let result = Cli::try_parse_from(["portdetective", "kill", "3000", "--apply", "plan.json"]);
+        assert!(result.is_err());
+
+        let result = Cli::try_parse_from([
+            "portdetective",
+            "kill",
+            "--plan",
+            "a.json",
+            "--apply",
+            "b.json",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_kill_by_selector() {
+        let cli = Cli::parse_from([
+            "portdetective",
+            "kill",
+            "--name",
+            "vite|jupyter",
+            "--user",
+            "me",
+            "--cwd-under",
+            "/home/dev/projects",
+            "--older-than",
+            "4h",
+            "--all",
+        ]);
+        match cli.command {
+            Some(Commands::Kill {
+                ports,
+                selector,
+                all,
+                ..
+            }) => {
+                assert!(ports.is_empty());
+                assert!(selector.name.unwrap().is_match("vite"));
+                assert_eq!(selector.user.as_deref(), Some("me"));
+                assert_eq!(
+                    selector.cwd_under,
+                    Some(PathBuf::from("/home/dev/projects"))
+                );
+                assert_eq!(selector.older_than, Some(Duration::from_secs(4 * 3600)));
+                assert!(all);
+            }
+            _ => panic!("Expected Kill command"),
+        }
+    }
+
+    #[test]
+    fn test_kill_rejects_invalid_regex() {
+        let result = Cli::try_parse_from(["portdetective", "kill", "--name", "("]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("200ms"), Ok(Duration::from_millis(200)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(2 * 86_400)));
+        assert!(parse_duration("4x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn test_parse_path_expands_home() {
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        assert_eq!(parse_path("~/projects"), Ok(home.join("projects")));
+        assert_eq!(parse_path("~"), Ok(home));
+        assert_eq!(parse_path("/srv"), Ok(PathBuf::from("/srv")));
+        assert_eq!(parse_path("~other/x"), Ok(PathBuf::from("~other/x")));
+    }
+
     #[test]
     fn test_list_subcommand() {
         let cli = Cli::parse_from(["portdetective", "list"]);
@@ -166,6 +1300,20 @@ mod tests {
         assert!(cli2.json);
     }
 
+    #[test]
+    fn test_format_flag() {
+        let cli = Cli::parse_from(["portdetective", "list", "--format", "csv"]);
+        assert_eq!(cli.format, Some(OutputFormat::Csv));
+
+        let cli = Cli::parse_from(["portdetective", "--format", "markdown", "3000"]);
+        assert_eq!(cli.format, Some(OutputFormat::Markdown));
+
+        assert!(Cli::try_parse_from(["portdetective", "list", "--format", "xml"]).is_err());
+        assert!(
+            Cli::try_parse_from(["portdetective", "list", "--format", "csv", "--json"]).is_err()
+        );
+    }
+
     #[test]
     fn test_tcp_only_filter() {
         let cli = Cli::parse_from(["portdetective", "--tcp", "3000"]);
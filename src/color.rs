@@ -0,0 +1,114 @@
+//! Terminal styling that can be switched off by configuration
+
+use owo_colors::Style;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turn styling on or off for everything printed afterwards
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A value printed with a style while styling is enabled, and as-is otherwise.
+///
+/// Width and alignment apply to the value, so `{:<8}` pads the text rather
+/// than the escape codes.
+pub struct Painted<T> {
+    value: T,
+    style: Style,
+    /// Whether styling was on when the value was painted
+    enabled: bool,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.enabled {
+            fmt::Display::fmt(&owo_colors::OwoColorize::style(&self.value, self.style), f)
+        } else {
+            fmt::Display::fmt(&self.value, f)
+        }
+    }
+}
+
+/// The subset of `OwoColorize` used for output, honoring `set_enabled`
+pub trait Colorize: fmt::Display {
+    fn paint(&self, style: Style) -> Painted<&Self> {
+        Painted {
+            value: self,
+            style,
+            enabled: ENABLED.load(Ordering::Relaxed),
+        }
+    }
+
+    fn red(&self) -> Painted<&Self> {
+        self.paint(Style::new().red())
+    }
+
+    fn green(&self) -> Painted<&Self> {
+        self.paint(Style::new().green())
+    }
+
+    fn yellow(&self) -> Painted<&Self> {
+        self.paint(Style::new().yellow())
+    }
+
+    fn blue(&self) -> Painted<&Self> {
+        self.paint(Style::new().blue())
+    }
+
+    fn magenta(&self) -> Painted<&Self> {
+        self.paint(Style::new().magenta())
+    }
+
+    fn cyan(&self) -> Painted<&Self> {
+        self.paint(Style::new().cyan())
+    }
+
+    fn bold(&self) -> Painted<&Self> {
+        self.paint(Style::new().bold())
+    }
+
+    fn dimmed(&self) -> Painted<&Self> {
+        self.paint(Style::new().dimmed())
+    }
+
+    fn italic(&self) -> Painted<&Self> {
+        self.paint(Style::new().italic())
+    }
+
+    fn underline(&self) -> Painted<&Self> {
+        self.paint(Style::new().underline())
+    }
+
+    fn reversed(&self) -> Painted<&Self> {
+        self.paint(Style::new().reversed())
+    }
+}
+
+impl<T: fmt::Display + ?Sized> Colorize for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Built directly rather than through `set_enabled`, which other tests
+    // running in parallel would see
+    fn painted(enabled: bool) -> Painted<&'static str> {
+        Painted {
+            value: "ok",
+            style: Style::new().green().bold(),
+            enabled,
+        }
+    }
+
+    #[test]
+    fn test_styling_toggles() {
+        let styled = format!("{:<5}|", painted(true));
+        assert!(styled.contains("\u{1b}["));
+        assert!(styled.contains("ok   "));
+
+        assert_eq!(format!("{:<5}|", painted(false)), "ok   |");
+    }
+}
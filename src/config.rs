@@ -0,0 +1,707 @@
+//! Settings layered from config files, the environment and flags
+
+use crate::cli::{Cli, ColorMode, Commands, OutputFormat};
+use crate::error::{PortDetectiveError, Result};
+use crate::model::Protocol;
+use crate::state;
+use clap::CommandFactory;
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Name of the project file, looked for in the current directory and its parents
+pub const PROJECT_FILE: &str = ".portdetective.toml";
+
+/// A column of the port list
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Port,
+    Proto,
+    Pid,
+    Process,
+    User,
+    Address,
+    Command,
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Column::Port => write!(f, "port"),
+            Column::Proto => write!(f, "proto"),
+            Column::Pid => write!(f, "pid"),
+            Column::Process => write!(f, "process"),
+            Column::User => write!(f, "user"),
+            Column::Address => write!(f, "address"),
+            Column::Command => write!(f, "command"),
+        }
+    }
+}
+
+/// Columns shown when none are configured
+pub const DEFAULT_COLUMNS: &[Column] = &[
+    Column::Port,
+    Column::Proto,
+    Column::Pid,
+    Column::Process,
+    Column::User,
+    Column::Command,
+];
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(&'static str),
+    Flag(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(var) => write!(f, "${}", var),
+            Source::Flag(flag) => write!(f, "{}", flag),
+        }
+    }
+}
+
+/// One config file's worth of settings; anything left out keeps the value
+/// from the layer below
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Layer {
+    format: Option<OutputFormat>,
+    protocol: Option<Protocol>,
+    protected: Option<Vec<String>>,
+    columns: Option<Vec<Column>>,
+    color: Option<ColorMode>,
+    #[serde(default)]
+    alias: BTreeMap<String, String>,
+    #[serde(default)]
+    ports: BTreeMap<String, u16>,
+    /// `audit` policy, read by the policy module
+    #[serde(default, rename = "listener")]
+    _listener: Option<toml::Value>,
+}
+
+fn parse(text: &str) -> std::result::Result<Layer, String> {
+    toml::from_str(text).map_err(|e| e.message().to_string())
+}
+
+/// Effective settings, with the source of each
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Default output format
+    pub format: OutputFormat,
+    /// Default protocol filter
+    pub protocol: Protocol,
+    /// Process names refused by kill, on top of the built-in ones
+    pub protected: Vec<String>,
+    /// Columns of the port list
+    pub columns: Vec<Column>,
+    pub color: ColorMode,
+    /// Command aliases, expanded before the command line is parsed
+    pub aliases: BTreeMap<String, String>,
+    /// Named ports, accepted wherever a port number is
+    pub ports: BTreeMap<String, u16>,
+    /// Config files read, lowest precedence first
+    pub files: Vec<PathBuf>,
+    sources: BTreeMap<String, Source>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            format: OutputFormat::Table,
+            protocol: Protocol::Both,
+            protected: Vec::new(),
+            columns: DEFAULT_COLUMNS.to_vec(),
+            color: ColorMode::Auto,
+            aliases: BTreeMap::new(),
+            ports: BTreeMap::new(),
+            files: Vec::new(),
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+/// Location of the user config file
+pub fn user_path() -> PathBuf {
+    state::config_dir().join("config.toml")
+}
+
+/// The nearest project file in the current directory or one of its parents
+pub fn project_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Read the user config, the project config and `PORTDETECTIVE_*` variables,
+/// each overriding the one before.
+///
+/// A file or variable that cannot be used is skipped and its error returned
+/// next to the settings, so a broken config never stops a command from running.
+pub fn load() -> (Settings, Vec<PortDetectiveError>) {
+    let mut settings = Settings::default();
+    let mut errors = Vec::new();
+    for path in [Some(user_path()), project_path()].into_iter().flatten() {
+        if path.is_file()
+            && let Err(e) = settings.merge_file(&path)
+        {
+            errors.push(e);
+        }
+    }
+    errors.extend(settings.merge_env(|var| std::env::var(var).ok()));
+    (settings, errors)
+}
+
+/// Names are told apart from port numbers by their first character
+fn valid_port_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic())
+}
+
+static CURRENT: OnceLock<Settings> = OnceLock::new();
+static PORT_NAMES: OnceLock<BTreeMap<String, u16>> = OnceLock::new();
+
+/// Make named ports resolvable while the command line is parsed; the first
+/// call wins
+pub fn init_port_names(ports: BTreeMap<String, u16>) {
+    let _ = PORT_NAMES.set(ports);
+}
+
+/// Named ports passed to `init_port_names`, or none before that
+pub fn port_names() -> &'static BTreeMap<String, u16> {
+    PORT_NAMES.get_or_init(BTreeMap::new)
+}
+
+/// Make the settings available through `current`; the first call wins
+pub fn init(settings: Settings) -> &'static Settings {
+    CURRENT.get_or_init(|| settings)
+}
+
+/// The settings passed to `init`, or the defaults before that
+pub fn current() -> &'static Settings {
+    CURRENT.get_or_init(Settings::default)
+}
+
+fn env_value<T: for<'de> Deserialize<'de>>(var: &str, value: &str) -> Result<T> {
+    T::deserialize(value.trim().into_deserializer()).map_err(|e: serde::de::value::Error| {
+        PortDetectiveError::Env {
+            var: var.to_string(),
+            reason: e.to_string(),
+        }
+    })
+}
+
+fn env_list<T: for<'de> Deserialize<'de>>(var: &str, value: &str) -> Result<Vec<T>> {
+    value
+        .split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| env_value(var, item))
+        .collect()
+}
+
+/// The value, or `None` with the error pushed onto `errors`
+fn keep<T>(result: Result<T>, errors: &mut Vec<PortDetectiveError>) -> Option<T> {
+    result.map_err(|e| errors.push(e)).ok()
+}
+
+impl Settings {
+    fn set_source(&mut self, key: &str, source: &Source) {
+        self.sources.insert(key.to_string(), source.clone());
+    }
+
+    fn merge(&mut self, layer: Layer, source: Source) {
+        if let Some(format) = layer.format {
+            self.format = format;
+            self.set_source("format", &source);
+        }
+        if let Some(protocol) = layer.protocol {
+            self.protocol = protocol;
+            self.set_source("protocol", &source);
+        }
+        if let Some(protected) = layer.protected {
+            self.protected = protected;
+            self.set_source("protected", &source);
+        }
+        if let Some(columns) = layer.columns {
+            self.columns = columns;
+            self.set_source("columns", &source);
+        }
+        if let Some(color) = layer.color {
+            self.color = color;
+            self.set_source("color", &source);
+        }
+        for (name, expansion) in layer.alias {
+            self.set_source(&format!("alias.{}", name), &source);
+            self.aliases.insert(name, expansion);
+        }
+        for (name, port) in layer.ports {
+            self.set_source(&format!("ports.{}", name), &source);
+            self.ports.insert(name, port);
+        }
+    }
+
+    fn merge_file(&mut self, path: &Path) -> Result<()> {
+        let config_error = |reason: String| PortDetectiveError::Config {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let text = fs::read_to_string(path).map_err(|e| config_error(e.to_string()))?;
+        let layer = parse(&text).map_err(config_error)?;
+        if layer.columns.as_ref().is_some_and(Vec::is_empty) {
+            return Err(config_error("columns must not be empty".to_string()));
+        }
+        if let Some(name) = layer.ports.keys().find(|name| !valid_port_name(name)) {
+            return Err(config_error(format!(
+                "port name `{}` must start with a letter",
+                name
+            )));
+        }
+        self.merge(layer, Source::File(path.to_path_buf()));
+        self.files.push(path.to_path_buf());
+        Ok(())
+    }
+
+    /// Apply the `PORTDETECTIVE_*` variables `lookup` finds, skipping and
+    /// returning the errors of those that cannot be parsed
+    fn merge_env(&mut self, lookup: impl Fn(&str) -> Option<String>) -> Vec<PortDetectiveError> {
+        let get = |var| lookup(var).filter(|v| !v.trim().is_empty());
+        let mut errors = Vec::new();
+
+        if let Some(v) = get("PORTDETECTIVE_FORMAT")
+            && let Some(format) = keep(env_value("PORTDETECTIVE_FORMAT", &v), &mut errors)
+        {
+            self.format = format;
+            self.set_source("format", &Source::Env("PORTDETECTIVE_FORMAT"));
+        }
+        if let Some(v) = get("PORTDETECTIVE_PROTOCOL")
+            && let Some(protocol) = keep(env_value("PORTDETECTIVE_PROTOCOL", &v), &mut errors)
+        {
+            self.protocol = protocol;
+            self.set_source("protocol", &Source::Env("PORTDETECTIVE_PROTOCOL"));
+        }
+        if let Some(v) = get("PORTDETECTIVE_PROTECTED")
+            && let Some(protected) = keep(env_list("PORTDETECTIVE_PROTECTED", &v), &mut errors)
+        {
+            self.protected = protected;
+            self.set_source("protected", &Source::Env("PORTDETECTIVE_PROTECTED"));
+        }
+        if let Some(v) = get("PORTDETECTIVE_COLUMNS")
+            && let Some(columns) = keep(env_list("PORTDETECTIVE_COLUMNS", &v), &mut errors)
+        {
+            self.columns = columns;
+            self.set_source("columns", &Source::Env("PORTDETECTIVE_COLUMNS"));
+        }
+        if let Some(v) = get("PORTDETECTIVE_COLOR")
+            && let Some(color) = keep(env_value("PORTDETECTIVE_COLOR", &v), &mut errors)
+        {
+            self.color = color;
+            self.set_source("color", &Source::Env("PORTDETECTIVE_COLOR"));
+        }
+        errors
+    }
+
+    /// Merge with the parsed command line: flags win over the settings, and
+    /// settings fill in flags that were not given
+    pub fn with_flags(mut self, cli: &mut Cli) -> Self {
+        if let Some(format) = cli.format {
+            self.format = format;
+            self.set_source("format", &Source::Flag("--format"));
+        } else if cli.json {
+            self.format = OutputFormat::Json;
+            self.set_source("format", &Source::Flag("--json"));
+        }
+        cli.format = Some(self.format);
+        cli.json = self.format == OutputFormat::Json;
+
+        if cli.tcp || cli.udp || cli.any_protocol {
+            let (protocol, flag) = if cli.tcp {
+                (Protocol::Tcp, "--tcp")
+            } else if cli.udp {
+                (Protocol::Udp, "--udp")
+            } else {
+                (Protocol::Both, "--any-protocol")
+            };
+            self.protocol = protocol;
+            self.set_source("protocol", &Source::Flag(flag));
+        } else {
+            cli.tcp = self.protocol == Protocol::Tcp;
+            cli.udp = self.protocol == Protocol::Udp;
+        }
+
+        if let Some(color) = cli.color {
+            self.color = color;
+            self.set_source("color", &Source::Flag("--color"));
+        }
+        self
+    }
+
+    /// The format `cli`'s command prints in. Commands that only print tables
+    /// and JSON refuse any other `--format`, but fall back to JSON when the
+    /// other format is only a default from a config file or the environment.
+    pub fn format_for(&self, cli: &Cli) -> Result<OutputFormat> {
+        let Some(command) = cli.command.as_ref().and_then(Commands::json_only) else {
+            return Ok(self.format);
+        };
+        match self.format {
+            OutputFormat::Table | OutputFormat::Json => Ok(self.format),
+            format if self.source("format") == &Source::Flag("--format") => {
+                Err(PortDetectiveError::UnsupportedFormat {
+                    command,
+                    format: format.name(),
+                })
+            }
+            _ => Ok(OutputFormat::Json),
+        }
+    }
+
+    /// Whether output should be colored
+    pub fn color_enabled(&self) -> bool {
+        match self.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        }
+    }
+
+    /// The name declared for a port, if any
+    pub fn port_name(&self, port: u16) -> Option<&str> {
+        self.ports
+            .iter()
+            .find(|(_, p)| **p == port)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Where a setting came from
+    pub fn source(&self, key: &str) -> &Source {
+        self.sources.get(key).unwrap_or(&Source::Default)
+    }
+
+    /// Every setting as `(key, value, source)`, for `config show`
+    pub fn entries(&self) -> Vec<(String, String, &Source)> {
+        let list = |items: Vec<String>| {
+            if items.is_empty() {
+                "(none)".to_string()
+            } else {
+                items.join(", ")
+            }
+        };
+        let color = match self.color {
+            ColorMode::Auto => "auto",
+            ColorMode::Always => "always",
+            ColorMode::Never => "never",
+        };
+
+        let mut entries = vec![
+            ("format".to_string(), self.format.name().to_string()),
+            ("protocol".to_string(), self.protocol.to_string()),
+            ("protected".to_string(), list(self.protected.clone())),
+            (
+                "columns".to_string(),
+                list(self.columns.iter().map(Column::to_string).collect()),
+            ),
+            ("color".to_string(), color.to_string()),
+        ];
+        for (name, expansion) in &self.aliases {
+            entries.push((format!("alias.{}", name), expansion.clone()));
+        }
+        for (name, port) in &self.ports {
+            entries.push((format!("ports.{}", name), port.to_string()));
+        }
+        entries
+            .into_iter()
+            .map(|(key, value)| {
+                let source = self.source(&key);
+                (key, value, source)
+            })
+            .collect()
+    }
+}
+
+/// Replace a leading alias with its expansion.
+///
+/// Only the first word that is neither a flag nor a flag's value is looked
+/// up, once, and built-in subcommands cannot be shadowed.
+pub fn expand_alias(args: Vec<String>, aliases: &BTreeMap<String, String>) -> Vec<String> {
+    let command = Cli::command();
+    let takes_value: Vec<String> = command
+        .get_arguments()
+        .filter(|arg| arg.get_action().takes_values())
+        .filter_map(|arg| arg.get_long())
+        .map(|long| format!("--{}", long))
+        .collect();
+
+    let mut i = 1;
+    while i < args.len() {
+        if takes_value.contains(&args[i]) {
+            i += 2;
+        } else if args[i].starts_with('-') {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    let Some(expansion) = args.get(i).and_then(|word| aliases.get(word)) else {
+        return args;
+    };
+    if command.find_subcommand(&args[i]).is_some() {
+        return args;
+    }
+
+    let mut expanded = args[..i].to_vec();
+    expanded.extend(expansion.split_whitespace().map(str::to_string));
+    expanded.extend(args[i + 1..].iter().cloned());
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn file(name: &str) -> Source {
+        Source::File(PathBuf::from(name))
+    }
+
+    #[test]
+    fn test_parse_layer() {
+        let layer = parse(
+            r#"
+            format = "json"
+            protocol = "tcp"
+            protected = ["postgres", "nginx"]
+            columns = ["port", "process", "address"]
+            color = "never"
+
+            [alias]
+            k9 = "kill --force --no-prompt"
+
+            [ports]
+            api = 3000
+            web = 5173
+
+            [[listener]]
+            port = 22
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layer.format, Some(OutputFormat::Json));
+        assert_eq!(layer.protocol, Some(Protocol::Tcp));
+        assert_eq!(layer.protected.unwrap(), vec!["postgres", "nginx"]);
+        assert_eq!(
+            layer.columns.unwrap(),
+            vec![Column::Port, Column::Process, Column::Address]
+        );
+        assert_eq!(layer.color, Some(ColorMode::Never));
+        assert_eq!(layer.alias["k9"], "kill --force --no-prompt");
+        assert_eq!(layer.ports["api"], 3000);
+    }
+
+    #[test]
+    fn test_port_names() {
+        assert!(valid_port_name("api"));
+        assert!(!valid_port_name("3000"));
+        assert!(parse("[ports]\napi = 70000\n").is_err());
+
+        let mut settings = Settings::default();
+        settings.merge(
+            parse("[ports]\napi = 3000\nweb = 5173\n").unwrap(),
+            file("project.toml"),
+        );
+        assert_eq!(settings.port_name(5173), Some("web"));
+        assert_eq!(settings.port_name(8080), None);
+        assert_eq!(settings.source("ports.api"), &file("project.toml"));
+    }
+
+    #[test]
+    fn test_parse_rejects_typos() {
+        assert!(parse("colour = \"never\"\n").is_err());
+        assert!(parse("format = \"xml\"\n").is_err());
+        assert!(parse("columns = [\"port\", \"cmd\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let mut settings = Settings::default();
+        settings.merge(
+            parse("format = \"json\"\ncolor = \"never\"\n[alias]\na = \"list\"\nb = \"list\"\n")
+                .unwrap(),
+            file("user.toml"),
+        );
+        settings.merge(
+            parse("color = \"always\"\n[alias]\nb = \"kill\"\n").unwrap(),
+            file("project.toml"),
+        );
+
+        assert_eq!(settings.format, OutputFormat::Json);
+        assert_eq!(settings.source("format"), &file("user.toml"));
+        assert_eq!(settings.color, ColorMode::Always);
+        assert_eq!(settings.source("color"), &file("project.toml"));
+        assert_eq!(settings.aliases["a"], "list");
+        assert_eq!(settings.aliases["b"], "kill");
+        assert_eq!(settings.source("alias.b"), &file("project.toml"));
+        assert_eq!(settings.source("protocol"), &Source::Default);
+    }
+
+    #[test]
+    fn test_env_overrides_files() {
+        let mut settings = Settings::default();
+        settings.merge(parse("protocol = \"tcp\"\n").unwrap(), file("user.toml"));
+        let errors = settings.merge_env(|var| match var {
+            "PORTDETECTIVE_PROTOCOL" => Some("udp".to_string()),
+            "PORTDETECTIVE_COLUMNS" => Some("port, pid,command".to_string()),
+            "PORTDETECTIVE_COLOR" => Some(String::new()),
+            _ => None,
+        });
+        assert!(errors.is_empty());
+
+        assert_eq!(settings.protocol, Protocol::Udp);
+        assert_eq!(
+            settings.source("protocol"),
+            &Source::Env("PORTDETECTIVE_PROTOCOL")
+        );
+        assert_eq!(
+            settings.columns,
+            vec![Column::Port, Column::Pid, Column::Command]
+        );
+        assert_eq!(settings.source("color"), &Source::Default);
+    }
+
+    #[test]
+    fn test_bad_env_value_is_skipped() {
+        let mut settings = Settings::default();
+        let errors = settings.merge_env(|var| match var {
+            "PORTDETECTIVE_FORMAT" => Some("xml".to_string()),
+            "PORTDETECTIVE_PROTOCOL" => Some("udp".to_string()),
+            _ => None,
+        });
+        assert!(matches!(
+            errors.as_slice(),
+            [PortDetectiveError::Env { var, .. }] if var == "PORTDETECTIVE_FORMAT"
+        ));
+        assert_eq!(settings.format, OutputFormat::Table);
+        assert_eq!(settings.protocol, Protocol::Udp);
+    }
+
+    #[test]
+    fn test_default_format_falls_back_for_json_only_commands() {
+        let mut settings = Settings::default();
+        settings.merge_env(|var| (var == "PORTDETECTIVE_FORMAT").then(|| "csv".to_string()));
+        let format_for = |args: &[&str]| {
+            let mut cli = Cli::parse_from(args);
+            let merged = settings.clone().with_flags(&mut cli);
+            merged.format_for(&cli).map_err(|e| e.to_string())
+        };
+
+        assert_eq!(
+            format_for(&["portdetective", "status"]),
+            Ok(OutputFormat::Json)
+        );
+        assert_eq!(
+            format_for(&["portdetective", "config", "show"]),
+            Ok(OutputFormat::Json)
+        );
+        assert_eq!(
+            format_for(&["portdetective", "list"]),
+            Ok(OutputFormat::Csv)
+        );
+        assert_eq!(
+            format_for(&["portdetective", "status", "--format", "table"]),
+            Ok(OutputFormat::Table)
+        );
+        // Only an explicit flag is refused
+        assert_eq!(
+            format_for(&["portdetective", "--format", "csv", "status"]),
+            Err("`status` cannot print csv; use --format table or --format json".to_string())
+        );
+    }
+
+    #[test]
+    fn test_flags_win_and_settings_fill_in() {
+        let settings = Settings {
+            format: OutputFormat::Json,
+            protocol: Protocol::Tcp,
+            ..Settings::default()
+        };
+
+        let mut cli = Cli::parse_from(["portdetective", "list"]);
+        let merged = settings.clone().with_flags(&mut cli);
+        assert!(cli.json);
+
+        // Only JSON turns on the commands' JSON output
+        let mut cli = Cli::parse_from(["portdetective", "--format", "csv", "list"]);
+        settings.clone().with_flags(&mut cli);
+        assert!(!cli.json);
+        assert!(cli.tcp && !cli.udp);
+        assert_eq!(merged.source("protocol"), &Source::Default);
+
+        let mut cli = Cli::parse_from(["portdetective", "--udp", "--color", "never", "list"]);
+        let merged = settings.with_flags(&mut cli);
+        assert!(cli.udp && !cli.tcp);
+        assert_eq!(merged.protocol, Protocol::Udp);
+        assert_eq!(merged.source("protocol"), &Source::Flag("--udp"));
+        assert!(!merged.color_enabled());
+
+        // A configured protocol can be widened back to both
+        let settings = Settings {
+            protocol: Protocol::Tcp,
+            ..Settings::default()
+        };
+        let mut cli = Cli::parse_from(["portdetective", "--any-protocol", "list"]);
+        let merged = settings.with_flags(&mut cli);
+        assert!(!cli.tcp && !cli.udp);
+        assert_eq!(merged.protocol, Protocol::Both);
+        assert_eq!(merged.source("protocol"), &Source::Flag("--any-protocol"));
+        assert!(Cli::try_parse_from(["portdetective", "--any-protocol", "--tcp", "list"]).is_err());
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases = BTreeMap::from([
+            ("k9".to_string(), "kill --force".to_string()),
+            ("list".to_string(), "kill".to_string()),
+        ]);
+
+        assert_eq!(
+            expand_alias(args("portdetective --json k9 3000"), &aliases),
+            args("portdetective --json kill --force 3000")
+        );
+        // Values of global flags are not mistaken for the alias
+        assert_eq!(
+            expand_alias(args("portdetective --color never k9 3000"), &aliases),
+            args("portdetective --color never kill --force 3000")
+        );
+        assert_eq!(
+            expand_alias(args("portdetective --format=csv k9 3000"), &aliases),
+            args("portdetective --format=csv kill --force 3000")
+        );
+        // Built-in subcommands and port shorthands are left alone
+        assert_eq!(
+            expand_alias(args("portdetective list"), &aliases),
+            args("portdetective list")
+        );
+        assert_eq!(
+            expand_alias(args("portdetective 3000"), &aliases),
+            args("portdetective 3000")
+        );
+    }
+}
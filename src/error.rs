@@ -1,5 +1,6 @@
 //! Error types for Port Detective
 
+use std::path::PathBuf;
 use thiserror::Error;
 
 /// All errors that can occur in Port Detective
@@ -27,6 +28,81 @@ pub enum PortDetectiveError {
 
     #[error("Operation cancelled by user")]
     Cancelled,
+
+    #[error("Selector matches {0} processes; pass --all to kill every one of them")]
+    AmbiguousSelector(usize),
+
+    #[error("Refusing to kill PID {pid}: {reason} (pass --i-know-what-im-doing to override)")]
+    Protected { pid: u32, reason: String },
+
+    #[error("PID {pid} now belongs to a different process ({reason}); refusing to signal it")]
+    ProcessChanged { pid: u32, reason: String },
+
+    #[error("Kill plan {}: {reason}", path.display())]
+    Plan { path: PathBuf, reason: String },
+
+    #[error("Stop command `{command}` failed: {reason}")]
+    StopFailed { command: String, reason: String },
+
+    #[error("State file {}: {reason}", path.display())]
+    State { path: PathBuf, reason: String },
+
+    #[error("Nothing remembered for port or ID {0} (kill it with --remember first)")]
+    NotRemembered(u32),
+
+    #[error("Could not restart {name}: {reason}")]
+    RestartFailed { name: String, reason: String },
+
+    #[error("Could not hold port {port}: {reason}")]
+    Hold { port: u16, reason: String },
+
+    #[error("Port {0} is not held by portdetective")]
+    NotHeld(u16),
+
+    #[error("No free, unleased port in {start}-{end}")]
+    NoFreePort { start: u16, end: u16 },
+
+    #[error("No lease matches {0}")]
+    NotLeased(String),
+
+    #[error("Invalid lease TTL: {0}")]
+    InvalidTtl(String),
+
+    #[error("Could not run `{command}`: {reason}")]
+    RunFailed { command: String, reason: String },
+
+    #[error("Port {0} is still in use after the kill")]
+    StillInUse(u16),
+
+    #[error("Could not write log {}: {reason}", path.display())]
+    Log { path: PathBuf, reason: String },
+
+    #[error("Could not scan {}: {reason}", path.display())]
+    Project { path: PathBuf, reason: String },
+
+    #[error("Snapshot {}: {reason}", path.display())]
+    Snapshot { path: PathBuf, reason: String },
+
+    #[error("Config file {}: {reason}", path.display())]
+    Config { path: PathBuf, reason: String },
+
+    #[error("No named ports; declare them under [ports] in .portdetective.toml")]
+    NoNamedPorts,
+
+    #[error("`{command}` cannot print {format}; use --format table or --format json")]
+    UnsupportedFormat {
+        command: &'static str,
+        format: &'static str,
+    },
+
+    #[error("Environment variable {var}: {reason}")]
+    Env { var: String, reason: String },
+
+    #[error("Terminal error: {0}")]
+    Terminal(String),
+
+    #[error("Cannot ask for confirmation: stdin is not a terminal (pass -y to skip the prompt)")]
+    NotInteractive,
 }
 
 pub type Result<T> = std::result::Result<T, PortDetectiveError>;
@@ -86,6 +162,84 @@ mod tests {
         assert_eq!(err.to_string(), "Operation cancelled by user");
     }
 
+    #[test]
+    fn test_ambiguous_selector_message() {
+        let err = PortDetectiveError::AmbiguousSelector(3);
+        assert_eq!(
+            err.to_string(),
+            "Selector matches 3 processes; pass --all to kill every one of them"
+        );
+    }
+
+    #[test]
+    fn test_protected_message() {
+        let err = PortDetectiveError::Protected {
+            pid: 1,
+            reason: "PID 1 is the init process".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Refusing to kill PID 1: PID 1 is the init process (pass --i-know-what-im-doing to override)"
+        );
+    }
+
+    #[test]
+    fn test_not_interactive_message() {
+        let err = PortDetectiveError::NotInteractive;
+        assert!(err.to_string().contains("stdin is not a terminal"));
+    }
+
+    #[test]
+    fn test_process_changed_message() {
+        let err = PortDetectiveError::ProcessChanged {
+            pid: 4242,
+            reason: "executable changed from /usr/bin/node to /usr/bin/python3".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "PID 4242 now belongs to a different process (executable changed from /usr/bin/node to /usr/bin/python3); refusing to signal it"
+        );
+    }
+
+    #[test]
+    fn test_invalid_ttl_message() {
+        let err = PortDetectiveError::InvalidTtl("must be longer than zero".to_string());
+        assert_eq!(
+            err.to_string(),
+            "Invalid lease TTL: must be longer than zero"
+        );
+    }
+
+    #[test]
+    fn test_still_in_use_message() {
+        let err = PortDetectiveError::StillInUse(3000);
+        assert_eq!(err.to_string(), "Port 3000 is still in use after the kill");
+    }
+
+    #[test]
+    fn test_plan_message() {
+        let err = PortDetectiveError::Plan {
+            path: PathBuf::from("plan.json"),
+            reason: "created on host a, this is b".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Kill plan plan.json: created on host a, this is b"
+        );
+    }
+
+    #[test]
+    fn test_stop_failed_message() {
+        let err = PortDetectiveError::StopFailed {
+            command: "systemctl stop nginx.service".to_string(),
+            reason: "Access denied".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Stop command `systemctl stop nginx.service` failed: Access denied"
+        );
+    }
+
     #[test]
     fn test_result_type_alias() {
         fn returns_ok() -> Result<u32> {
@@ -99,4 +253,168 @@ mod tests {
         assert!(returns_ok().is_ok());
         assert!(returns_err().is_err());
     }
+
+    #[test]
+    fn test_state_message() {
+        let err = PortDetectiveError::State {
+            path: PathBuf::from("/tmp/remembered.json"),
+            reason: "expected value".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "State file /tmp/remembered.json: expected value"
+        );
+    }
+
+    #[test]
+    fn test_not_remembered_message() {
+        let err = PortDetectiveError::NotRemembered(3000);
+        assert_eq!(
+            err.to_string(),
+            "Nothing remembered for port or ID 3000 (kill it with --remember first)"
+        );
+    }
+
+    #[test]
+    fn test_restart_failed_message() {
+        let err = PortDetectiveError::RestartFailed {
+            name: "node".to_string(),
+            reason: "port 3000 is in use".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Could not restart node: port 3000 is in use"
+        );
+    }
+
+    #[test]
+    fn test_hold_message() {
+        let err = PortDetectiveError::Hold {
+            port: 3000,
+            reason: "Address already in use".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Could not hold port 3000: Address already in use"
+        );
+    }
+
+    #[test]
+    fn test_not_held_message() {
+        let err = PortDetectiveError::NotHeld(3000);
+        assert_eq!(err.to_string(), "Port 3000 is not held by portdetective");
+    }
+
+    #[test]
+    fn test_no_free_port_message() {
+        let err = PortDetectiveError::NoFreePort {
+            start: 20000,
+            end: 21000,
+        };
+        assert_eq!(err.to_string(), "No free, unleased port in 20000-21000");
+    }
+
+    #[test]
+    fn test_not_leased_message() {
+        let err = PortDetectiveError::NotLeased("owner job-7".to_string());
+        assert_eq!(err.to_string(), "No lease matches owner job-7");
+    }
+
+    #[test]
+    fn test_run_failed_message() {
+        let err = PortDetectiveError::RunFailed {
+            command: "npm run dev".to_string(),
+            reason: "No such file or directory".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Could not run `npm run dev`: No such file or directory"
+        );
+    }
+
+    #[test]
+    fn test_log_message() {
+        let err = PortDetectiveError::Log {
+            path: PathBuf::from("/tmp/trap.jsonl"),
+            reason: "Permission denied".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Could not write log /tmp/trap.jsonl: Permission denied"
+        );
+    }
+
+    #[test]
+    fn test_terminal_message() {
+        let err = PortDetectiveError::Terminal("not a tty".to_string());
+        assert_eq!(err.to_string(), "Terminal error: not a tty");
+    }
+
+    #[test]
+    fn test_config_message() {
+        let err = PortDetectiveError::Config {
+            path: PathBuf::from("/home/dev/.config/portdetective/hooks.toml"),
+            reason: "no [[hook]] entries".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Config file /home/dev/.config/portdetective/hooks.toml: no [[hook]] entries"
+        );
+    }
+
+    #[test]
+    fn test_snapshot_message() {
+        let err = PortDetectiveError::Snapshot {
+            path: PathBuf::from("before.json"),
+            reason: "expected value at line 1 column 1".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Snapshot before.json: expected value at line 1 column 1"
+        );
+    }
+
+    #[test]
+    fn test_env_message() {
+        let err = PortDetectiveError::Env {
+            var: "PORTDETECTIVE_COLOR".to_string(),
+            reason: "unknown variant `sometimes`".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Environment variable PORTDETECTIVE_COLOR: unknown variant `sometimes`"
+        );
+    }
+
+    #[test]
+    fn test_no_named_ports_message() {
+        assert_eq!(
+            PortDetectiveError::NoNamedPorts.to_string(),
+            "No named ports; declare them under [ports] in .portdetective.toml"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_format_message() {
+        let err = PortDetectiveError::UnsupportedFormat {
+            command: "exposure",
+            format: "csv",
+        };
+        assert_eq!(
+            err.to_string(),
+            "`exposure` cannot print csv; use --format table or --format json"
+        );
+    }
+
+    #[test]
+    fn test_project_message() {
+        let err = PortDetectiveError::Project {
+            path: PathBuf::from("/srv/app"),
+            reason: "Permission denied (os error 13)".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Could not scan /srv/app: Permission denied (os error 13)"
+        );
+    }
 }
@@ -0,0 +1,442 @@
+//! Which listeners can be reached from outside this host, and how risky that is
+
+use crate::listing::Listener;
+use crate::model::Protocol;
+use crate::net::InterfaceAddr;
+use serde::Serialize;
+use std::net::IpAddr;
+
+/// How bad it is for a listener to be reachable from the network
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Risk {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+impl std::fmt::Display for Risk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Risk::Critical => write!(f, "critical"),
+            Risk::High => write!(f, "high"),
+            Risk::Medium => write!(f, "medium"),
+            Risk::Low => write!(f, "low"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Category {
+    /// Databases and caches, often without authentication by default
+    Datastore,
+    /// Debuggers and profilers that allow running code or reading memory
+    Debug,
+}
+
+/// A well-known service recognised by process name or command-line argument
+struct Service {
+    name: &'static str,
+    category: Category,
+    /// Its usual ports; on their own only a guess for owners that cannot be inspected
+    ports: &'static [u16],
+    processes: &'static [&'static str],
+    /// Argument that identifies it inside a generic runtime (java, node, a Go
+    /// binary); the port it names, or else one of `ports`, must be the listener's
+    marker: Option<&'static str>,
+    hint: &'static str,
+}
+
+const SERVICES: &[Service] = &[
+    Service {
+        name: "Redis",
+        category: Category::Datastore,
+        ports: &[6379],
+        processes: &["redis-server"],
+        marker: None,
+        hint: "Set `bind 127.0.0.1` in redis.conf and require a password",
+    },
+    Service {
+        name: "PostgreSQL",
+        category: Category::Datastore,
+        ports: &[5432],
+        processes: &["postgres", "postmaster"],
+        marker: None,
+        hint: "Set `listen_addresses = 'localhost'` in postgresql.conf",
+    },
+    Service {
+        name: "MongoDB",
+        category: Category::Datastore,
+        ports: &[27017, 27018, 27019],
+        processes: &["mongod", "mongos"],
+        marker: None,
+        hint: "Set `net.bindIp: 127.0.0.1` in mongod.conf and enable authorization",
+    },
+    Service {
+        name: "Elasticsearch",
+        category: Category::Datastore,
+        ports: &[9200, 9300],
+        processes: &[],
+        marker: Some("org.elasticsearch"),
+        hint: "Set `network.host: 127.0.0.1` in elasticsearch.yml and enable security",
+    },
+    Service {
+        name: "MySQL",
+        category: Category::Datastore,
+        ports: &[3306, 33060],
+        processes: &["mysqld", "mariadbd"],
+        marker: None,
+        hint: "Set `bind-address = 127.0.0.1` in my.cnf",
+    },
+    Service {
+        name: "Memcached",
+        category: Category::Datastore,
+        ports: &[11211],
+        processes: &["memcached"],
+        marker: None,
+        hint: "Start memcached with `-l 127.0.0.1`",
+    },
+    Service {
+        name: "Node inspector",
+        category: Category::Debug,
+        ports: &[9229],
+        processes: &[],
+        marker: Some("--inspect"),
+        hint: "Pass `--inspect` without a host (it defaults to 127.0.0.1) and tunnel over SSH",
+    },
+    Service {
+        name: "JDWP",
+        category: Category::Debug,
+        ports: &[5005],
+        processes: &[],
+        marker: Some("jdwp"),
+        hint: "Use `address=127.0.0.1:<port>` in `-agentlib:jdwp` and tunnel over SSH",
+    },
+    Service {
+        name: "pprof",
+        category: Category::Debug,
+        ports: &[6060],
+        processes: &[],
+        marker: Some("pprof"),
+        hint: "Serve net/http/pprof on a separate localhost-only listener",
+    },
+];
+
+const WILDCARD_HINT: &str = "Bind to 127.0.0.1 if only this host needs it, or firewall the port";
+const ADDRESS_HINT: &str = "Firewall the port if it should not be reachable on this network";
+
+impl Service {
+    fn matches(&self, listener: &Listener) -> bool {
+        let process = &listener.process;
+        if process.is_unknown() {
+            return self.ports.contains(&listener.port);
+        }
+        if self.processes.contains(&process.name.as_str()) {
+            return true;
+        }
+        let Some(marker) = self.marker else {
+            return false;
+        };
+        process
+            .command
+            .iter()
+            .filter(|arg| arg.contains(marker))
+            .any(|arg| match argument_port(arg) {
+                Some(port) => port == listener.port,
+                None => self.ports.contains(&listener.port),
+            })
+    }
+}
+
+/// Port named by an argument like `--inspect=0.0.0.0:9229`, `-pprof-addr=:6060`
+/// or `-agentlib:jdwp=...,address=*:5005,...`
+fn argument_port(arg: &str) -> Option<u16> {
+    let value = match arg.find("address=") {
+        Some(i) => arg[i + "address=".len()..].split(',').next()?,
+        None => arg.split_once('=')?.1,
+    };
+    value.rsplit(':').next()?.parse().ok()
+}
+
+/// A listener reachable from outside loopback
+#[derive(Debug, Clone, Serialize)]
+pub struct Exposure {
+    pub risk: Risk,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub address: String,
+    /// Non-loopback interface addresses the listener answers on
+    pub reachable: Vec<InterfaceAddr>,
+    /// Missing when the owner cannot be inspected; process and user are then `unknown`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub process: String,
+    pub user: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<&'static str>,
+    pub hint: &'static str,
+}
+
+/// Loopback or link-local: not reachable beyond this host or its link
+fn is_local(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => v4.is_loopback() || v4.is_link_local(),
+        IpAddr::V6(v6) => v6.is_loopback() || (v6.segments()[0] & 0xffc0) == 0xfe80,
+    }
+}
+
+/// Interface addresses a socket bound to `bound` accepts connections on,
+/// leaving out loopback and link-local ones.
+///
+/// `::` is taken to accept IPv4 too, the Linux default.
+pub fn reachable(bound: IpAddr, interfaces: &[InterfaceAddr]) -> Vec<InterfaceAddr> {
+    if is_local(bound) {
+        return Vec::new();
+    }
+    let routable = interfaces.iter().filter(|i| !is_local(i.address));
+    if bound.is_unspecified() {
+        routable
+            .filter(|i| bound.is_ipv6() || i.address.is_ipv4())
+            .cloned()
+            .collect()
+    } else {
+        let mut on = routable
+            .filter(|i| i.address == bound)
+            .cloned()
+            .collect::<Vec<_>>();
+        // Bound with IP_FREEBIND or on an address since removed; still not local
+        if on.is_empty() {
+            on.push(InterfaceAddr {
+                interface: String::new(),
+                address: bound,
+            });
+        }
+        on
+    }
+}
+
+/// Exposed listeners, riskiest first.
+///
+/// Datastores on a wildcard address rank highest, then debug ports and
+/// datastores on a specific address, then everything else on a wildcard.
+pub fn assess(listeners: &[Listener], interfaces: &[InterfaceAddr]) -> Vec<Exposure> {
+    let mut exposures: Vec<Exposure> = listeners
+        .iter()
+        .filter_map(|listener| {
+            let bound: IpAddr = listener.local_addr.parse().ok()?;
+            let reachable = reachable(bound, interfaces);
+            if reachable.is_empty() {
+                return None;
+            }
+
+            let service = SERVICES.iter().find(|s| s.matches(listener));
+            let wildcard = bound.is_unspecified();
+            let risk = match (service.map(|s| s.category), wildcard) {
+                (Some(Category::Datastore), true) => Risk::Critical,
+                (Some(_), _) => Risk::High,
+                (None, true) => Risk::Medium,
+                (None, false) => Risk::Low,
+            };
+            let hint = match service {
+                Some(service) => service.hint,
+                None if wildcard => WILDCARD_HINT,
+                None => ADDRESS_HINT,
+            };
+
+            Some(Exposure {
+                risk,
+                port: listener.port,
+                protocol: listener.protocol,
+                address: listener.local_addr.clone(),
+                reachable,
+                pid: (!listener.process.is_unknown()).then_some(listener.process.pid),
+                process: listener.process.name.clone(),
+                user: listener.process.user.clone(),
+                service: service.map(|s| s.name),
+                hint,
+            })
+        })
+        .collect();
+
+    exposures.sort_by(|a, b| {
+        (a.risk, a.port, a.protocol, &a.address).cmp(&(b.risk, b.port, b.protocol, &b.address))
+    });
+    exposures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ProcessInfo;
+
+    fn interfaces() -> Vec<InterfaceAddr> {
+        [
+            ("lo", "127.0.0.1"),
+            ("lo", "::1"),
+            ("eth0", "10.0.0.5"),
+            ("eth0", "fe80::1"),
+        ]
+        .iter()
+        .map(|(interface, address)| InterfaceAddr {
+            interface: interface.to_string(),
+            address: address.parse().unwrap(),
+        })
+        .collect()
+    }
+
+    fn listener(port: u16, address: &str, name: &str, command: &[&str]) -> Listener {
+        Listener {
+            port,
+            protocol: Protocol::Tcp,
+            local_addr: address.to_string(),
+            process: ProcessInfo {
+                pid: u32::from(port),
+                name: name.to_string(),
+                user: "dev".to_string(),
+                uid: None,
+                command: command.iter().map(|s| s.to_string()).collect(),
+                cwd: None,
+                exe: None,
+                parent_pid: None,
+                parent_name: None,
+                started: None,
+                manager: None,
+                protocol: Protocol::Tcp,
+            },
+        }
+    }
+
+    #[test]
+    fn test_reachable() {
+        let interfaces = interfaces();
+        assert!(reachable("127.0.0.1".parse().unwrap(), &interfaces).is_empty());
+        assert!(reachable("::1".parse().unwrap(), &interfaces).is_empty());
+
+        let v4 = reachable("0.0.0.0".parse().unwrap(), &interfaces);
+        assert_eq!(v4.len(), 1);
+        assert_eq!(v4[0].interface, "eth0");
+        // Link-local fe80::1 does not count
+        assert_eq!(reachable("::".parse().unwrap(), &interfaces).len(), 1);
+        assert!(reachable("fe80::1".parse().unwrap(), &interfaces).is_empty());
+        assert!(reachable("169.254.1.1".parse().unwrap(), &interfaces).is_empty());
+
+        let specific = reachable("10.0.0.5".parse().unwrap(), &interfaces);
+        assert_eq!(specific, vec![interfaces[2].clone()]);
+    }
+
+    #[test]
+    fn test_loopback_only_host_has_no_exposure() {
+        let loopback: Vec<InterfaceAddr> = interfaces().into_iter().take(2).collect();
+        let listeners = vec![listener(6379, "0.0.0.0", "redis-server", &[])];
+        assert!(assess(&listeners, &loopback).is_empty());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let listeners = vec![
+            listener(3000, "0.0.0.0", "node", &["node", "server.js"]),
+            listener(8080, "10.0.0.5", "java", &["java", "-jar", "app.jar"]),
+            listener(9229, "0.0.0.0", "node", &["node", "--inspect=0.0.0.0:9229"]),
+            listener(5432, "127.0.0.1", "postgres", &[]),
+            listener(6379, "0.0.0.0", "redis-server", &[]),
+            listener(5433, "10.0.0.5", "postgres", &[]),
+        ];
+
+        let exposures = assess(&listeners, &interfaces());
+        let ranked: Vec<(Risk, u16, Option<&str>)> = exposures
+            .iter()
+            .map(|e| (e.risk, e.port, e.service))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                (Risk::Critical, 6379, Some("Redis")),
+                (Risk::High, 5433, Some("PostgreSQL")),
+                (Risk::High, 9229, Some("Node inspector")),
+                (Risk::Medium, 3000, None),
+                (Risk::Low, 8080, None),
+            ]
+        );
+        assert_eq!(exposures[3].hint, WILDCARD_HINT);
+    }
+
+    #[test]
+    fn test_unknown_owner_is_still_reported() {
+        let hidden = Listener {
+            process: ProcessInfo::unknown(Protocol::Tcp),
+            ..listener(6379, "0.0.0.0", "", &[])
+        };
+        let exposures = assess(&[hidden], &interfaces());
+        assert_eq!(exposures.len(), 1);
+        assert_eq!(exposures[0].risk, Risk::Critical);
+        assert_eq!(exposures[0].pid, None);
+        assert_eq!(exposures[0].process, "unknown");
+    }
+
+    #[test]
+    fn test_service_needs_a_name_not_just_a_port() {
+        let listeners = vec![
+            // Usual Redis port, but not Redis
+            listener(6379, "0.0.0.0", "node", &["node", "server.js"]),
+            // Mentions 6379 without being Redis
+            listener(
+                3000,
+                "0.0.0.0",
+                "node",
+                &["node", "app.js", "--redis=:6379"],
+            ),
+            // The app port of a process that also has the inspector open
+            listener(3000, "0.0.0.0", "node", &["node", "--inspect", "app.js"]),
+            listener(9229, "0.0.0.0", "node", &["node", "--inspect", "app.js"]),
+            listener(
+                9300,
+                "0.0.0.0",
+                "java",
+                &["java", "org.elasticsearch.bootstrap.Elasticsearch"],
+            ),
+        ];
+
+        let services: Vec<(u16, Option<&str>)> = assess(&listeners, &interfaces())
+            .iter()
+            .map(|e| (e.port, e.service))
+            .collect();
+        assert_eq!(
+            services,
+            vec![
+                (9300, Some("Elasticsearch")),
+                (9229, Some("Node inspector")),
+                (3000, None),
+                (3000, None),
+                (6379, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_argument_port() {
+        assert_eq!(argument_port("--inspect=0.0.0.0:9230"), Some(9230));
+        assert_eq!(argument_port("--inspect"), None);
+        assert_eq!(argument_port("-pprof-addr=:6060"), Some(6060));
+        assert_eq!(
+            argument_port("-agentlib:jdwp=transport=dt_socket,address=*:5005,server=y"),
+            Some(5005)
+        );
+    }
+
+    #[test]
+    fn test_debug_flag_on_unusual_port() {
+        let jdwp = listener(
+            8000,
+            "0.0.0.0",
+            "java",
+            &[
+                "java",
+                "-agentlib:jdwp=transport=dt_socket,server=y,address=*:8000",
+            ],
+        );
+        let exposures = assess(&[jdwp], &interfaces());
+        assert_eq!(exposures[0].service, Some("JDWP"));
+        assert_eq!(exposures[0].risk, Risk::High);
+    }
+}
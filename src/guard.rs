@@ -0,0 +1,207 @@
+//! Safety guardrails checked before any process is signaled
+
+use crate::model::KillTarget;
+use crate::proc;
+
+/// Process names that are refused by default
+pub const DEFAULT_PROTECTED_NAMES: &[&str] = &[
+    "sshd",
+    "systemd",
+    "init",
+    "launchd",
+    "dockerd",
+    "containerd",
+];
+
+/// Ports whose owners are refused by default
+pub const DEFAULT_PROTECTED_PORTS: &[u16] = &[22, 53];
+
+/// Rules deciding which processes may be signaled without an explicit override
+#[derive(Debug, Clone)]
+pub struct Guardrails {
+    pub protected_names: Vec<String>,
+    pub protected_ports: Vec<u16>,
+    /// UID running portdetective; processes of anyone else are refused
+    pub current_uid: u32,
+}
+
+impl Guardrails {
+    /// Default protected set for the given user
+    pub fn new(current_uid: u32) -> Self {
+        Self {
+            protected_names: DEFAULT_PROTECTED_NAMES
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            protected_ports: DEFAULT_PROTECTED_PORTS.to_vec(),
+            current_uid,
+        }
+    }
+
+    /// Why the target must not be signaled, or `None` if it is fair game
+    pub fn check(&self, target: &KillTarget) -> Option<String> {
+        let info = &target.process;
+
+        if info.pid == 1 {
+            return Some("PID 1 is the init process".to_string());
+        }
+        if proc::is_kernel_thread(info.pid) {
+            return Some("it is a kernel thread".to_string());
+        }
+        if self.protected_names.iter().any(|n| n == &info.name) {
+            return Some(format!("{} is a protected process", info.name));
+        }
+        if let Some(port) = target
+            .ports
+            .iter()
+            .find(|p| self.protected_ports.contains(p))
+        {
+            return Some(format!("port {} is protected", port));
+        }
+        // Names are no good here: every UID without a passwd entry is "unknown"
+        if info.uid != Some(self.current_uid) {
+            return Some(format!("it belongs to another user ({})", info.user));
+        }
+
+        None
+    }
+
+    /// Split targets into those allowed and those refused, with the reason
+    pub fn partition(
+        &self,
+        targets: Vec<KillTarget>,
+    ) -> (Vec<KillTarget>, Vec<(KillTarget, String)>) {
+        let mut allowed = Vec::new();
+        let mut refused = Vec::new();
+
+        for target in targets {
+            match self.check(&target) {
+                Some(reason) => refused.push((target, reason)),
+                None => allowed.push(target),
+            }
+        }
+
+        (allowed, refused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ProcessInfo, Protocol};
+
+    const DEV: u32 = 1000;
+    const ROOT: u32 = 0;
+
+    fn target(pid: u32, name: &str, (user, uid): (&str, u32), port: u16) -> KillTarget {
+        KillTarget {
+            ports: vec![port],
+            process: ProcessInfo {
+                pid,
+                name: name.to_string(),
+                user: user.to_string(),
+                uid: Some(uid),
+                command: vec![name.to_string()],
+                cwd: None,
+                exe: None,
+                parent_pid: Some(1),
+                parent_name: None,
+                started: None,
+                manager: None,
+                protocol: Protocol::Tcp,
+            },
+        }
+    }
+
+    fn own_pid() -> u32 {
+        std::process::id()
+    }
+
+    #[test]
+    fn test_allows_own_process() {
+        let guard = Guardrails::new(DEV);
+        assert_eq!(
+            guard.check(&target(own_pid(), "node", ("dev", DEV), 3000)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_refuses_pid_one() {
+        let guard = Guardrails::new(ROOT);
+        let reason = guard
+            .check(&target(1, "node", ("root", ROOT), 3000))
+            .unwrap();
+        assert!(reason.contains("PID 1"));
+    }
+
+    #[test]
+    fn test_refuses_protected_name_and_port() {
+        let guard = Guardrails::new(ROOT);
+        let reason = guard
+            .check(&target(own_pid(), "sshd", ("root", ROOT), 2222))
+            .unwrap();
+        assert!(reason.contains("sshd"));
+
+        let reason = guard
+            .check(&target(own_pid(), "dnsmasq", ("root", ROOT), 53))
+            .unwrap();
+        assert!(reason.contains("port 53"));
+    }
+
+    #[test]
+    fn test_refuses_other_users() {
+        let guard = Guardrails::new(DEV);
+        let reason = guard
+            .check(&target(own_pid(), "postgres", ("postgres", 70), 5432))
+            .unwrap();
+        assert!(reason.contains("another user"));
+    }
+
+    #[test]
+    fn test_refuses_other_unnamed_uids() {
+        // Neither UID has a passwd entry, so both owners are named "unknown"
+        let guard = Guardrails::new(100_000);
+        let mut other = target(own_pid(), "node", ("unknown", 100_001), 3000);
+        let reason = guard.check(&other).unwrap();
+        assert!(reason.contains("another user"));
+
+        // An owner whose UID could not be read is someone else's too
+        other.process.uid = None;
+        assert!(guard.check(&other).is_some());
+
+        let guard = Guardrails::new(100_001);
+        other.process.uid = Some(100_001);
+        assert_eq!(guard.check(&other), None);
+    }
+
+    #[test]
+    fn test_protected_set_is_configurable() {
+        let mut guard = Guardrails::new(DEV);
+        guard.protected_names.push("redis-server".to_string());
+        guard.protected_ports.clear();
+
+        assert!(
+            guard
+                .check(&target(own_pid(), "redis-server", ("dev", DEV), 6379))
+                .is_some()
+        );
+        assert!(
+            guard
+                .check(&target(own_pid(), "dnsmasq", ("dev", DEV), 53))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_partition() {
+        let guard = Guardrails::new(DEV);
+        let (allowed, refused) = guard.partition(vec![
+            target(own_pid(), "node", ("dev", DEV), 3000),
+            target(1, "systemd", ("root", ROOT), 3001),
+        ]);
+        assert_eq!(allowed.len(), 1);
+        assert_eq!(refused.len(), 1);
+        assert_eq!(refused[0].0.process.pid, 1);
+    }
+}
@@ -0,0 +1,361 @@
+//! Holding ports so nothing else can bind them, and releasing them again
+
+use crate::error::{PortDetectiveError, Result};
+use crate::model::{ProcessIdentity, Protocol};
+use crate::proc::{self, ProcessHandle};
+use crate::state;
+use chrono::{DateTime, Local};
+use nix::sys::signal::{SigSet, Signal};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, UdpSocket};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Address holds bind unless told otherwise
+pub const DEFAULT_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// How long `kill --and-hold` keeps trying to bind a port its old owner is leaving
+pub const GRAB_WINDOW: Duration = Duration::from_secs(5);
+
+const GRAB_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A hold in progress, written to the state directory so `release` can find it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoldRecord {
+    pub port: u16,
+    pub addrs: Vec<IpAddr>,
+    pub protocol: Protocol,
+    /// The portdetective process keeping the port bound
+    pub holder: ProcessIdentity,
+    pub started: DateTime<Local>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Local>>,
+}
+
+impl HoldRecord {
+    /// Where the record for a held port lives
+    pub fn path(port: u16) -> PathBuf {
+        state::state_dir()
+            .join("holds")
+            .join(format!("{}.json", port))
+    }
+}
+
+/// Why a hold ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ended {
+    Signal(Signal),
+    Timeout,
+    /// `release` took back every port
+    Released,
+}
+
+/// Sockets bound on one port; the port is held for as long as this lives
+#[derive(Debug)]
+pub struct Bound {
+    _tcp: Option<TcpListener>,
+    _udp: Option<UdpSocket>,
+}
+
+/// Bind the port on `addr` for the given protocol(s)
+pub fn bind(addr: IpAddr, port: u16, protocol: Protocol) -> Result<Bound> {
+    bind_raw(addr, port, protocol).map_err(|e| PortDetectiveError::Hold {
+        port,
+        reason: e.to_string(),
+    })
+}
+
+/// Whether the port can be bound right now on both the IPv4 and the IPv6
+/// wildcard, so listeners on either family count as busy. A host without
+/// IPv6 only needs IPv4 to be free.
+pub fn is_free(port: u16, protocol: Protocol) -> bool {
+    let v6 = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+    bind(DEFAULT_ADDR, port, protocol).is_ok()
+        && match bind_raw(v6, port, protocol) {
+            Ok(_) => true,
+            Err(e) => e.kind() != std::io::ErrorKind::AddrInUse,
+        }
+}
+
+fn bind_raw(addr: IpAddr, port: u16, protocol: Protocol) -> std::io::Result<Bound> {
+    let socket = SocketAddr::new(addr, port);
+    let tcp = match protocol {
+        Protocol::Tcp | Protocol::Both => Some(TcpListener::bind(socket)?),
+        Protocol::Udp => None,
+    };
+    let udp = match protocol {
+        Protocol::Udp | Protocol::Both => Some(UdpSocket::bind(socket)?),
+        Protocol::Tcp => None,
+    };
+    Ok(Bound {
+        _tcp: tcp,
+        _udp: udp,
+    })
+}
+
+/// One port bound on one or more addresses; held for as long as this lives
+#[derive(Debug)]
+pub struct Held {
+    pub port: u16,
+    pub addrs: Vec<IpAddr>,
+    pub protocol: Protocol,
+    _bound: Vec<Bound>,
+}
+
+/// Hold `port` on `addr`
+pub fn hold(addr: IpAddr, port: u16, protocol: Protocol) -> Result<Held> {
+    Ok(Held {
+        port,
+        addrs: vec![addr],
+        protocol,
+        _bound: vec![bind(addr, port, protocol)?],
+    })
+}
+
+/// Take back `port` on every address its old owner had bound, waiting up to
+/// `window` for each to be let go.
+///
+/// A wildcard IPv6 socket is dual-stack, so once it is held the IPv4
+/// wildcard counts as covered if it cannot be bound separately.
+pub fn grab_sockets(port: u16, sockets: &[(IpAddr, Protocol)], window: Duration) -> Result<Held> {
+    let mut sockets = sockets.to_vec();
+    sockets.sort_by_key(|(addr, _)| (addr.is_ipv4(), *addr));
+
+    let mut held = Held {
+        port,
+        addrs: Vec::new(),
+        protocol: sockets
+            .iter()
+            .map(|&(_, p)| p)
+            .reduce(|a, b| if a == b { a } else { Protocol::Both })
+            .unwrap_or(Protocol::Tcp),
+        _bound: Vec::new(),
+    };
+    for (addr, protocol) in sockets {
+        let covered =
+            addr == DEFAULT_ADDR && held.addrs.contains(&IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+        let bound = if covered {
+            bind(addr, port, protocol).ok()
+        } else {
+            Some(grab(addr, port, protocol, window)?)
+        };
+        if !held.addrs.contains(&addr) {
+            held.addrs.push(addr);
+        }
+        held._bound.extend(bound);
+    }
+    Ok(held)
+}
+
+/// Keep trying to bind until the port's old owner lets go or `window` expires
+pub fn grab(addr: IpAddr, port: u16, protocol: Protocol, window: Duration) -> Result<Bound> {
+    let start = Instant::now();
+    loop {
+        match bind(addr, port, protocol) {
+            Ok(bound) => return Ok(bound),
+            Err(e) if start.elapsed() >= window => return Err(e),
+            Err(_) => thread::sleep(GRAB_INTERVAL),
+        }
+    }
+}
+
+/// Signals sent to a holding process
+pub struct Signals(mpsc::Receiver<Signal>);
+
+/// Block SIGINT, SIGTERM and SIGHUP, which end a hold, and SIGUSR1, which
+/// `release` sends after removing the record of one port, and forward them
+/// for `next_signal`.
+///
+/// Must run before any other thread is started so that every thread
+/// inherits the mask.
+pub fn block_signals(port: u16) -> Result<Signals> {
+    let mut set = SigSet::empty();
+    set.add(Signal::SIGINT);
+    set.add(Signal::SIGTERM);
+    set.add(Signal::SIGHUP);
+    set.add(Signal::SIGUSR1);
+    set.thread_block().map_err(|e| PortDetectiveError::Hold {
+        port,
+        reason: format!("cannot block signals: {}", e),
+    })?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        while let Ok(signal) = set.wait() {
+            if tx.send(signal).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(Signals(rx))
+}
+
+/// Wait for the next blocked signal, or `None` once `deadline` passes
+pub fn next_signal(signals: &Signals, deadline: Option<Instant>) -> Option<Signal> {
+    match deadline {
+        Some(deadline) => signals
+            .0
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .ok(),
+        None => signals.0.recv().ok(),
+    }
+}
+
+/// Whether `release` has taken the port back from this process
+pub fn is_released(port: u16) -> bool {
+    state::read::<HoldRecord>(&HoldRecord::path(port))
+        .map(|record| record.holder.pid != std::process::id())
+        .unwrap_or(true)
+}
+
+/// Write the records for ports this process now holds
+pub fn record(held: &[Held], timeout: Option<Duration>) -> Result<Vec<HoldRecord>> {
+    let holder = proc::identity(std::process::id())?;
+    let started = Local::now();
+    let until = timeout
+        .and_then(|t| chrono::Duration::from_std(t).ok())
+        .and_then(|t| started.checked_add_signed(t));
+
+    held.iter()
+        .map(|held| {
+            let port = held.port;
+            let record = HoldRecord {
+                port,
+                addrs: held.addrs.clone(),
+                protocol: held.protocol,
+                holder: holder.clone(),
+                started,
+                until,
+            };
+            state::save(&HoldRecord::path(port), &record)?;
+            Ok(record)
+        })
+        .collect()
+}
+
+/// Remove the records of a finished hold
+pub fn forget(ports: &[u16]) {
+    for &port in ports {
+        let _ = fs::remove_file(HoldRecord::path(port));
+    }
+}
+
+/// End the hold on one port, started from another shell.
+///
+/// The record is removed first and the holder is then sent SIGUSR1, so it
+/// lets go of this port and keeps any others it holds. A record whose holder
+/// is gone or was replaced is stale and reported as not held.
+pub fn release(port: u16) -> Result<HoldRecord> {
+    let path = HoldRecord::path(port);
+    if !path.exists() {
+        return Err(PortDetectiveError::NotHeld(port));
+    }
+    let record: HoldRecord = state::read(&path)?;
+    let holder = ProcessHandle::open(record.holder.clone());
+    let _ = fs::remove_file(&path);
+
+    match holder.send(Signal::SIGUSR1) {
+        Ok(()) => Ok(record),
+        Err(PortDetectiveError::ProcessNotFound(_) | PortDetectiveError::ProcessChanged { .. }) => {
+            Err(PortDetectiveError::NotHeld(port))
+        }
+        Err(e) => {
+            state::save(&path, &record)?;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn free_port() -> u16 {
+        TcpListener::bind((LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    #[test]
+    fn test_bind_holds_tcp_and_udp() {
+        let port = free_port();
+        let bound = bind(LOCALHOST, port, Protocol::Both).unwrap();
+
+        assert!(TcpListener::bind((LOCALHOST, port)).is_err());
+        assert!(UdpSocket::bind((LOCALHOST, port)).is_err());
+
+        drop(bound);
+        assert!(TcpListener::bind((LOCALHOST, port)).is_ok());
+    }
+
+    #[test]
+    fn test_bind_reports_busy_port() {
+        let taken = TcpListener::bind((LOCALHOST, 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+
+        match bind(LOCALHOST, port, Protocol::Tcp) {
+            Err(PortDetectiveError::Hold { port: p, .. }) => assert_eq!(p, port),
+            other => panic!("expected Hold error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_free_sees_ipv6_only_listener() {
+        let Ok(taken) = TcpListener::bind((Ipv6Addr::LOCALHOST, 0)) else {
+            return; // no IPv6 on this host
+        };
+        let port = taken.local_addr().unwrap().port();
+
+        assert!(!is_free(port, Protocol::Tcp));
+        drop(taken);
+        assert!(is_free(port, Protocol::Tcp));
+    }
+
+    #[test]
+    fn test_grab_waits_for_owner_to_leave() {
+        let taken = TcpListener::bind((LOCALHOST, 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let owner = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            drop(taken);
+        });
+
+        assert!(grab(LOCALHOST, port, Protocol::Tcp, Duration::from_secs(5)).is_ok());
+        owner.join().unwrap();
+    }
+
+    #[test]
+    fn test_grab_gives_up() {
+        let taken = TcpListener::bind((LOCALHOST, 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+        assert!(grab(LOCALHOST, port, Protocol::Tcp, Duration::from_millis(30)).is_err());
+    }
+
+    #[test]
+    fn test_grab_sockets_binds_every_address() {
+        let port = free_port();
+        let held = grab_sockets(
+            port,
+            &[(LOCALHOST, Protocol::Tcp), (LOCALHOST, Protocol::Udp)],
+            Duration::from_millis(30),
+        )
+        .unwrap();
+
+        assert_eq!(held.addrs, vec![LOCALHOST]);
+        assert_eq!(held.protocol, Protocol::Both);
+        assert!(TcpListener::bind((LOCALHOST, port)).is_err());
+        assert!(UdpSocket::bind((LOCALHOST, port)).is_err());
+    }
+
+    #[test]
+    fn test_record_path_is_per_port() {
+        assert!(HoldRecord::path(3000).ends_with("holds/3000.json"));
+    }
+}
@@ -0,0 +1,494 @@
+//! User hooks fired on port events
+
+use crate::cli::{parse_duration, parse_path, parse_port_range};
+use crate::error::{PortDetectiveError, Result};
+use crate::listing::Selector;
+use crate::monitor::{EventKind, PortEvent};
+use crate::state;
+use chrono::Local;
+use nix::sys::signal::{Signal, killpg};
+use nix::unistd::Pid;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A command to run when a matching port event happens
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    pub name: String,
+    /// Events to fire on; all of them if empty
+    #[serde(default)]
+    pub on: Vec<EventKind>,
+    pub port: Option<u16>,
+    #[serde(default, deserialize_with = "port_range")]
+    pub ports: Option<RangeInclusive<u16>>,
+    /// Only fire for owners whose name or command line matches
+    #[serde(default, deserialize_with = "regex")]
+    pub process: Option<Regex>,
+    /// Only fire for owners whose name is not one of these
+    #[serde(default)]
+    pub known: Vec<String>,
+    /// Shell command, run with `sh -c`
+    pub run: String,
+    /// Fire at most once per this long
+    #[serde(default, deserialize_with = "duration")]
+    pub debounce: Option<Duration>,
+    /// Kill the command if it runs longer than this
+    #[serde(default, deserialize_with = "duration")]
+    pub timeout: Option<Duration>,
+    #[serde(default, deserialize_with = "path")]
+    pub log: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HooksFile {
+    #[serde(default)]
+    hook: Vec<Hook>,
+}
+
+fn port_range<'de, D: Deserializer<'de>>(
+    d: D,
+) -> std::result::Result<Option<RangeInclusive<u16>>, D::Error> {
+    let s = String::deserialize(d)?;
+    parse_port_range(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn regex<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<Regex>, D::Error> {
+    let s = String::deserialize(d)?;
+    Regex::new(&s).map(Some).map_err(serde::de::Error::custom)
+}
+
+fn duration<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<Duration>, D::Error> {
+    let s = String::deserialize(d)?;
+    parse_duration(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn path<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<PathBuf>, D::Error> {
+    let s = String::deserialize(d)?;
+    parse_path(&s).map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Default location of the hooks file
+pub fn default_path() -> PathBuf {
+    state::config_dir().join("hooks.toml")
+}
+
+/// Read the `[[hook]]` tables from a TOML file
+pub fn load(path: &Path) -> Result<Vec<Hook>> {
+    let config_error = |reason: String| PortDetectiveError::Config {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let text = fs::read_to_string(path).map_err(|e| config_error(e.to_string()))?;
+    parse(&text).map_err(config_error)
+}
+
+fn parse(text: &str) -> std::result::Result<Vec<Hook>, String> {
+    let file: HooksFile = toml::from_str(text).map_err(|e| e.message().to_string())?;
+    if file.hook.is_empty() {
+        return Err("no [[hook]] entries".to_string());
+    }
+    for (i, hook) in file.hook.iter().enumerate() {
+        if file.hook[..i].iter().any(|h| h.name == hook.name) {
+            return Err(format!("hook name `{}` is used twice", hook.name));
+        }
+    }
+    Ok(file.hook)
+}
+
+impl Hook {
+    /// Whether this hook cares about the event
+    pub fn matches(&self, event: &PortEvent) -> bool {
+        if !self.on.is_empty() && !self.on.contains(&event.event) {
+            return false;
+        }
+        if self.port.is_some_and(|p| p != event.port) {
+            return false;
+        }
+        if self
+            .ports
+            .as_ref()
+            .is_some_and(|r| !r.contains(&event.port))
+        {
+            return false;
+        }
+
+        let owner = event.process.as_ref();
+        if let Some(regex) = &self.process {
+            let selector = Selector {
+                name: Some(regex.clone()),
+                ..Selector::default()
+            };
+            if !owner.is_some_and(|p| selector.matches(p)) {
+                return false;
+            }
+        }
+        !owner.is_some_and(|p| self.known.contains(&p.name))
+    }
+
+    /// Where the command's output and the firing history go. The name is
+    /// reduced to a safe file name so it cannot point outside the state directory.
+    pub fn log_path(&self) -> PathBuf {
+        self.log.clone().unwrap_or_else(|| {
+            let name: String = self
+                .name
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            state::state_dir()
+                .join("hooks")
+                .join(format!("{}.log", name))
+        })
+    }
+}
+
+/// Matches events against hooks, applying each hook's debounce
+#[derive(Debug)]
+pub struct Dispatcher {
+    hooks: Vec<Arc<Hook>>,
+    last_fired: Vec<Option<Instant>>,
+}
+
+impl Dispatcher {
+    pub fn new(hooks: Vec<Hook>) -> Self {
+        let last_fired = vec![None; hooks.len()];
+        Dispatcher {
+            hooks: hooks.into_iter().map(Arc::new).collect(),
+            last_fired,
+        }
+    }
+
+    /// The hooks that should fire for the event now
+    pub fn due(&mut self, event: &PortEvent, now: Instant) -> Vec<Arc<Hook>> {
+        let mut due = Vec::new();
+        for (i, hook) in self.hooks.iter().enumerate() {
+            if !hook.matches(event) {
+                continue;
+            }
+            let quiet = match (self.last_fired[i], hook.debounce) {
+                (Some(last), Some(debounce)) => now.duration_since(last) >= debounce,
+                _ => true,
+            };
+            if quiet {
+                self.last_fired[i] = Some(now);
+                due.push(Arc::clone(hook));
+            }
+        }
+        due
+    }
+}
+
+/// Event fields for the hook's environment
+pub fn env(event: &PortEvent) -> Vec<(String, String)> {
+    let mut vars = vec![
+        ("PORTDETECTIVE_EVENT", event.event.to_string()),
+        ("PORTDETECTIVE_TIME", event.time.to_rfc3339()),
+        ("PORTDETECTIVE_PORT", event.port.to_string()),
+        ("PORTDETECTIVE_PROTOCOL", event.protocol.to_string()),
+        ("PORTDETECTIVE_ADDRESS", event.address.to_string()),
+    ];
+    if let Some(pid) = event.pid {
+        vars.push(("PORTDETECTIVE_PID", pid.to_string()));
+    }
+    if let Some(pid) = event.previous_pid {
+        vars.push(("PORTDETECTIVE_PREVIOUS_PID", pid.to_string()));
+    }
+    if let Some(process) = &event.process {
+        vars.push(("PORTDETECTIVE_PROCESS", process.name.clone()));
+        vars.push(("PORTDETECTIVE_USER", process.user.clone()));
+    }
+    vars.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+}
+
+/// How a hook run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Exited(ExitStatus),
+    TimedOut(Duration),
+}
+
+/// Run the hook's command for an event, with the event as JSON on stdin
+/// and in the environment, and its output appended to the hook's log
+pub fn fire(hook: &Hook, event: &PortEvent) -> Result<Outcome> {
+    let log_path = hook.log_path();
+    let log_error = |e: std::io::Error| PortDetectiveError::Log {
+        path: log_path.clone(),
+        reason: e.to_string(),
+    };
+    if let Some(dir) = log_path.parent() {
+        fs::create_dir_all(dir).map_err(log_error)?;
+    }
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .map_err(log_error)?;
+
+    let json = serde_json::to_string(event).unwrap_or_else(|_| "{}".to_string());
+    writeln!(log, "[{}] {}", Local::now().to_rfc3339(), json).map_err(log_error)?;
+
+    // Its own process group, so a timeout takes down whatever the shell started
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(&hook.run)
+        .process_group(0)
+        .envs(env(event))
+        .stdin(Stdio::piped())
+        .stdout(clone_log(&log).map_err(log_error)?)
+        .stderr(clone_log(&log).map_err(log_error)?)
+        .spawn()
+        .map_err(log_error)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that ignores stdin may close it early; that is fine
+        let _ = writeln!(stdin, "{}", json);
+    }
+
+    let timeout = hook.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let start = Instant::now();
+    let outcome = loop {
+        if let Some(status) = child.try_wait().map_err(log_error)? {
+            break Outcome::Exited(status);
+        }
+        if start.elapsed() >= timeout {
+            let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+            let _ = child.wait();
+            break Outcome::TimedOut(timeout);
+        }
+        thread::sleep(WAIT_INTERVAL);
+    };
+
+    let summary = match outcome {
+        Outcome::Exited(status) => format!("exited with {}", status),
+        Outcome::TimedOut(t) => format!("killed after {:?} timeout", t),
+    };
+    writeln!(log, "[{}] {}", Local::now().to_rfc3339(), summary).map_err(log_error)?;
+    Ok(outcome)
+}
+
+fn clone_log(log: &File) -> std::io::Result<Stdio> {
+    log.try_clone().map(Stdio::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ProcessInfo, Protocol};
+
+    fn event(kind: EventKind, port: u16, owner: Option<&str>) -> PortEvent {
+        PortEvent {
+            event: kind,
+            time: Local::now(),
+            port,
+            protocol: Protocol::Tcp,
+            address: "127.0.0.1".parse().unwrap(),
+            pid: owner.map(|_| 42),
+            previous_pid: None,
+            process: owner.map(|name| ProcessInfo {
+                pid: 42,
+                name: name.to_string(),
+                user: "dev".to_string(),
+                uid: None,
+                command: vec![name.to_string()],
+                cwd: None,
+                exe: None,
+                parent_pid: None,
+                parent_name: None,
+                started: None,
+                manager: None,
+                protocol: Protocol::Tcp,
+            }),
+        }
+    }
+
+    const HOOKS: &str = r#"
+        [[hook]]
+        name = "db-down"
+        on = ["closed"]
+        port = 5432
+        run = "notify-send 'Postgres is down'"
+        debounce = "30s"
+
+        [[hook]]
+        name = "stranger"
+        on = ["opened", "owner_changed"]
+        ports = "8000-9000"
+        known = ["nginx", "node"]
+        run = "./alert.sh"
+        timeout = "5s"
+    "#;
+
+    #[test]
+    fn test_parse_hooks() {
+        let hooks = parse(HOOKS).unwrap();
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(hooks[0].debounce, Some(Duration::from_secs(30)));
+        assert_eq!(hooks[1].ports, Some(8000..=9000));
+        assert_eq!(hooks[1].timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_hooks() {
+        assert!(parse("").unwrap_err().contains("no [[hook]]"));
+        assert!(parse("[[hook]]\nname = \"a\"\nrun = \"x\"\ndebounce = \"soon\"").is_err());
+        assert!(parse("[[hook]]\nname = \"a\"\nrun = \"x\"\nwhen = \"closed\"").is_err());
+        let twice = "[[hook]]\nname = \"a\"\nrun = \"x\"\n[[hook]]\nname = \"a\"\nrun = \"y\"";
+        assert!(parse(twice).unwrap_err().contains("used twice"));
+    }
+
+    #[test]
+    fn test_matches() {
+        let hooks = parse(HOOKS).unwrap();
+        assert!(hooks[0].matches(&event(EventKind::Closed, 5432, Some("postgres"))));
+        assert!(!hooks[0].matches(&event(EventKind::Opened, 5432, Some("postgres"))));
+        assert!(!hooks[0].matches(&event(EventKind::Closed, 5433, Some("postgres"))));
+
+        assert!(hooks[1].matches(&event(EventKind::Opened, 8080, Some("python3"))));
+        assert!(hooks[1].matches(&event(EventKind::Opened, 8080, None)));
+        assert!(!hooks[1].matches(&event(EventKind::Opened, 8080, Some("node"))));
+        assert!(!hooks[1].matches(&event(EventKind::Opened, 9001, Some("python3"))));
+    }
+
+    #[test]
+    fn test_debounce() {
+        let mut dispatcher = Dispatcher::new(parse(HOOKS).unwrap());
+        let closed = event(EventKind::Closed, 5432, Some("postgres"));
+        let start = Instant::now();
+
+        let names =
+            |due: Vec<Arc<Hook>>| -> Vec<String> { due.iter().map(|h| h.name.clone()).collect() };
+
+        assert_eq!(names(dispatcher.due(&closed, start)), vec!["db-down"]);
+        assert!(
+            dispatcher
+                .due(&closed, start + Duration::from_secs(10))
+                .is_empty()
+        );
+        assert_eq!(
+            names(dispatcher.due(&closed, start + Duration::from_secs(31))),
+            vec!["db-down"]
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        let vars = env(&event(EventKind::OwnerChanged, 8080, Some("python3")));
+        let get = |key: &str| vars.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        assert_eq!(get("PORTDETECTIVE_EVENT"), Some("owner_changed"));
+        assert_eq!(get("PORTDETECTIVE_PORT"), Some("8080"));
+        assert_eq!(get("PORTDETECTIVE_PROCESS"), Some("python3"));
+        assert_eq!(get("PORTDETECTIVE_PREVIOUS_PID"), None);
+    }
+
+    fn hook(name: &str, run: &str, timeout: Option<Duration>) -> (Hook, PathBuf) {
+        let log = std::env::temp_dir()
+            .join(format!("portdetective-hooks-{}", std::process::id()))
+            .join(format!("{}.log", name));
+        let _ = fs::remove_file(&log);
+        let hook = Hook {
+            name: name.to_string(),
+            on: Vec::new(),
+            port: None,
+            ports: None,
+            process: None,
+            known: Vec::new(),
+            run: run.to_string(),
+            debounce: None,
+            timeout,
+            log: Some(log.clone()),
+        };
+        (hook, log)
+    }
+
+    #[test]
+    fn test_fire_passes_event_and_logs_output() {
+        let (hook, log) = hook(
+            "echo",
+            "echo port=$PORTDETECTIVE_PORT; grep -o '\"event\":\"closed\"'",
+            None,
+        );
+        let outcome = fire(&hook, &event(EventKind::Closed, 5432, None)).unwrap();
+        assert!(matches!(outcome, Outcome::Exited(status) if status.success()));
+
+        let text = fs::read_to_string(&log).unwrap();
+        assert!(text.contains("port=5432"));
+        assert!(text.contains("\"event\":\"closed\""));
+        assert!(text.contains("exited with exit status: 0"));
+        let _ = fs::remove_file(&log);
+    }
+
+    #[test]
+    fn test_fire_kills_on_timeout() {
+        let (hook, log) = hook("slow", "sleep 5", Some(Duration::from_millis(100)));
+        let start = Instant::now();
+        let outcome = fire(&hook, &event(EventKind::Opened, 8080, None)).unwrap();
+        assert_eq!(outcome, Outcome::TimedOut(Duration::from_millis(100)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let _ = fs::remove_file(&log);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_fire_timeout_kills_the_hooks_children() {
+        let pid_file = std::env::temp_dir().join(format!(
+            "portdetective-hooks-{}-child.pid",
+            std::process::id()
+        ));
+        let run = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let (hook, log) = hook("children", &run, Some(Duration::from_millis(200)));
+        let outcome = fire(&hook, &event(EventKind::Opened, 8080, None)).unwrap();
+        assert_eq!(outcome, Outcome::TimedOut(Duration::from_millis(200)));
+
+        let pid: i32 = fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        // Gone, or a zombie waiting for init to reap it
+        let alive = || {
+            procfs::process::Process::new(pid)
+                .and_then(|p| p.stat())
+                .is_ok_and(|stat| stat.state != 'Z')
+        };
+        let start = Instant::now();
+        while alive() && start.elapsed() < Duration::from_secs(2) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!alive());
+        let _ = fs::remove_file(&pid_file);
+        let _ = fs::remove_file(&log);
+    }
+
+    #[test]
+    fn test_log_path_stays_in_state_dir() {
+        let (mut hook, _) = hook("../../x", "true", None);
+        hook.log = None;
+        let path = hook.log_path();
+        assert_eq!(
+            path.parent(),
+            Some(state::state_dir().join("hooks").as_path())
+        );
+        assert_eq!(path.file_name().unwrap(), "______x.log");
+    }
+}
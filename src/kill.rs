@@ -0,0 +1,943 @@
+//! Kill orchestration: resolving every owner of the requested ports and signaling them
+
+use crate::cli::{OutputFormat, ProtocolFilter};
+use crate::error::{PortDetectiveError, Result};
+use crate::listing::{self, Listener, Selector};
+use crate::manager::Stopper;
+use crate::model::{
+    KillPlan, KillResult, KillSignal, KillTarget, PlannedKill, ProcessIdentity, ProcessInfo,
+    Protocol, Respawn,
+};
+use crate::proc::ProcessHandle;
+use chrono::Local;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Flags shared by every kill flow
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KillOptions {
+    /// Send SIGKILL instead of SIGTERM
+    pub force: bool,
+    /// Skip the confirmation prompt
+    pub no_prompt: bool,
+    /// Bypass the guardrails
+    pub unguarded: bool,
+    /// Stop managed processes through their service manager
+    pub via_manager: bool,
+    /// Wait up to this long for established connections to close before signaling
+    pub drain: Option<Duration>,
+    /// How long to watch killed ports for a new owner; zero disables it
+    pub respawn_window: Duration,
+    /// Snapshot killed processes for `restart`
+    pub remember: bool,
+    /// Keep secret-looking environment variables in the snapshot
+    pub remember_secrets: bool,
+    /// Take the freed ports and hold them after killing
+    pub and_hold: bool,
+    /// Hold timeout with `and_hold`; held until released when unset
+    pub hold_for: Option<Duration>,
+    pub format: OutputFormat,
+}
+
+/// How often the socket table is polled while draining
+pub const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often killed ports are checked for a new owner
+pub const RESPAWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Find listeners on the given ports that also match the selector.
+///
+/// An empty port list means any port.
+pub fn select(ports: &[u16], selector: &Selector, filter: ProtocolFilter) -> Result<Vec<Listener>> {
+    Ok(listing::collect(filter)?
+        .into_iter()
+        .filter(|l| ports.is_empty() || ports.contains(&l.port))
+        .filter(|l| selector.matches(&l.process))
+        .collect())
+}
+
+/// Pin every target at discovery time, so that a PID recycled while the user
+/// reads the prompt is never signaled
+pub fn pin(targets: &[KillTarget]) -> HashMap<u32, ProcessHandle> {
+    targets
+        .iter()
+        .map(|t| (t.process.pid, ProcessHandle::open(t.process.identity())))
+        .collect()
+}
+
+/// Send the signal to every target, collecting a result per PID.
+///
+/// Signals go through the handle pinned for each PID. With `via_manager`,
+/// managed targets are stopped through their service manager instead; each
+/// stop command runs once even if several targets (a master and its workers)
+/// share it.
+pub fn execute(
+    targets: &[KillTarget],
+    handles: &HashMap<u32, ProcessHandle>,
+    opts: KillOptions,
+    stopper: &dyn Stopper,
+) -> Vec<KillResult> {
+    let mut stopped: HashMap<String, std::result::Result<(), String>> = HashMap::new();
+    let mut results = Vec::new();
+
+    for target in targets {
+        let stop = target
+            .process
+            .manager
+            .as_ref()
+            .filter(|_| opts.via_manager)
+            .and_then(|m| m.stop_command().map(|argv| (m, argv.join(" "))));
+
+        let (outcome, via) = match stop {
+            Some((manager, command)) => {
+                let outcome = stopped
+                    .entry(command.clone())
+                    .or_insert_with(|| stopper.stop(manager).map_err(|e| e.to_string()))
+                    .clone();
+                (outcome, Some(command))
+            }
+            None => {
+                let outcome = match handles.get(&target.process.pid) {
+                    Some(handle) => handle.signal(opts.force),
+                    None => ProcessHandle::open(target.process.identity()).signal(opts.force),
+                };
+                (outcome.map_err(|e| e.to_string()), None)
+            }
+        };
+
+        results.push(KillResult {
+            pid: target.process.pid,
+            name: target.process.name.clone(),
+            ports: target.ports.clone(),
+            signal: KillSignal::from_force(opts.force).to_string(),
+            via,
+            open_connections: None,
+            success: outcome.is_ok(),
+            error: outcome.err(),
+            respawns: Vec::new(),
+        });
+    }
+
+    results
+}
+
+/// Wait until no connections are established on `ports` or `timeout` expires.
+///
+/// `count` reads the per-port connection counts and `progress` is told the
+/// total still open and the time waited after every poll. Returns the counts
+/// from the last poll, i.e. what is still open when the signal goes out.
+pub fn drain(
+    ports: &[u16],
+    timeout: Duration,
+    interval: Duration,
+    mut count: impl FnMut(&[u16]) -> Result<HashMap<u16, usize>>,
+    mut progress: impl FnMut(usize, Duration),
+) -> Result<HashMap<u16, usize>> {
+    let start = Instant::now();
+    loop {
+        let counts = count(ports)?;
+        let open: usize = counts.values().sum();
+        let waited = start.elapsed();
+        progress(open, waited);
+
+        if open == 0 || waited >= timeout {
+            return Ok(counts);
+        }
+        thread::sleep(interval.min(timeout - waited));
+    }
+}
+
+/// Record on each result how many connections its ports still had open
+pub fn record_open_connections(results: &mut [KillResult], counts: &HashMap<u16, usize>) {
+    for result in results {
+        result.open_connections = Some(result.ports.iter().filter_map(|p| counts.get(p)).sum());
+    }
+}
+
+/// Watch the ports of killed targets for `window` and report new owners.
+///
+/// `ancestry` holds each killed PID's parent chain, recorded before the
+/// signal. A port is reported once, for the first new PID seen on it; the
+/// watch ends early when every port is back.
+pub fn watch_respawns(
+    killed: &[KillTarget],
+    ancestry: &HashMap<u32, Vec<(u32, String)>>,
+    window: Duration,
+    interval: Duration,
+    mut live: impl FnMut() -> Result<Vec<Listener>>,
+    ancestors_of: impl Fn(u32) -> Vec<(u32, String)>,
+) -> Result<Vec<Respawn>> {
+    let mut ports: Vec<u16> = killed.iter().flat_map(|t| t.ports.clone()).collect();
+    ports.sort_unstable();
+    ports.dedup();
+    let killed_pids: Vec<u32> = killed.iter().map(|t| t.process.pid).collect();
+
+    let start = Instant::now();
+    let mut respawns: Vec<Respawn> = Vec::new();
+    while !ports.is_empty() && respawns.len() < ports.len() {
+        for listener in live()? {
+            if !ports.contains(&listener.port)
+                || killed_pids.contains(&listener.process.pid)
+                || respawns.iter().any(|r| r.port == listener.port)
+            {
+                continue;
+            }
+            let Some(old) = killed.iter().find(|t| t.ports.contains(&listener.port)) else {
+                continue;
+            };
+            let old_ancestors = ancestry
+                .get(&old.process.pid)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            respawns.push(Respawn {
+                port: listener.port,
+                pid: listener.process.pid,
+                name: listener.process.name.clone(),
+                respawner: respawner(
+                    &old.process,
+                    old_ancestors,
+                    &listener.process,
+                    &ancestors_of(listener.process.pid),
+                ),
+            });
+        }
+
+        let waited = start.elapsed();
+        if waited >= window {
+            break;
+        }
+        thread::sleep(interval.min(window - waited));
+    }
+
+    respawns.sort_by_key(|r| r.port);
+    Ok(respawns)
+}
+
+/// Who most likely restarted a process: a service manager of either the old
+/// or new owner, else the nearest ancestor they share that is still alive
+fn respawner(
+    old: &ProcessInfo,
+    old_ancestors: &[(u32, String)],
+    new: &ProcessInfo,
+    new_ancestors: &[(u32, String)],
+) -> Option<String> {
+    if let Some(manager) = new.manager.as_ref().or(old.manager.as_ref()) {
+        return Some(manager.label());
+    }
+    new_ancestors
+        .iter()
+        .find(|(pid, _)| old_ancestors.iter().any(|(old_pid, _)| old_pid == pid))
+        .map(|(pid, name)| format!("{} (PID {})", name, pid))
+}
+
+/// Attach each respawn to the successful result that held its port
+pub fn record_respawns(results: &mut [KillResult], respawns: Vec<Respawn>) {
+    for respawn in respawns {
+        if let Some(result) = results
+            .iter_mut()
+            .find(|r| r.success && r.ports.contains(&respawn.port))
+        {
+            result.respawns.push(respawn);
+        }
+    }
+}
+
+/// Result for a target the guardrails refused to signal
+pub fn refused(target: &KillTarget, force: bool, reason: &str) -> KillResult {
+    KillResult {
+        pid: target.process.pid,
+        name: target.process.name.clone(),
+        ports: target.ports.clone(),
+        signal: KillSignal::from_force(force).to_string(),
+        via: None,
+        open_connections: None,
+        success: false,
+        error: Some(format!("refused: {}", reason)),
+        respawns: Vec::new(),
+    }
+}
+
+/// Turn targets into a plan for review; nothing is signaled
+pub fn plan(
+    targets: &[KillTarget],
+    signal: KillSignal,
+    host: String,
+    reason: impl Fn(&KillTarget) -> String,
+) -> KillPlan {
+    KillPlan {
+        created: Local::now(),
+        host,
+        targets: targets
+            .iter()
+            .map(|t| PlannedKill {
+                identity: t.process.identity(),
+                ports: t.ports.clone(),
+                signal,
+                reason: reason(t),
+                process: t.process.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Plan targets re-checked against the live system
+#[derive(Debug, Default)]
+pub struct Verified {
+    /// Targets that still match the plan, with their planned signal
+    pub targets: Vec<(KillTarget, KillSignal)>,
+    /// Failed results for targets that drifted and will not be signaled
+    pub skipped: Vec<KillResult>,
+    /// Every difference found, for the report
+    pub drift: Vec<String>,
+}
+
+/// Re-verify each planned target against the live listeners.
+///
+/// A target is skipped if its process exited, its PID now belongs to a
+/// different process, or it no longer holds any of the planned ports.
+/// Owners of planned ports that are not in the plan are reported as drift
+/// but left alone.
+pub fn verify(
+    plan: &KillPlan,
+    live: &[Listener],
+    current: impl Fn(u32) -> Result<ProcessIdentity>,
+) -> Verified {
+    let mut verified = Verified::default();
+
+    for planned in &plan.targets {
+        let pid = planned.identity.pid;
+        let label = format!("PID {} ({})", pid, planned.process.name);
+
+        let problem = match current(pid) {
+            Err(_) => Some("no longer running".to_string()),
+            Ok(identity) => planned.identity.mismatch(&identity),
+        };
+        if let Some(problem) = problem {
+            verified.drift.push(format!("{}: {}", label, problem));
+            verified.skipped.push(skipped(planned, &problem));
+            continue;
+        }
+
+        let held: Vec<&Listener> = live
+            .iter()
+            .filter(|l| l.process.pid == pid && planned.ports.contains(&l.port))
+            .collect();
+        let Some(first) = held.first() else {
+            let problem = format!("no longer listening on port {}", join(&planned.ports));
+            verified.drift.push(format!("{}: {}", label, problem));
+            verified.skipped.push(skipped(planned, &problem));
+            continue;
+        };
+
+        let mut ports: Vec<u16> = held.iter().map(|l| l.port).collect();
+        ports.sort_unstable();
+        ports.dedup();
+        let released: Vec<u16> = planned
+            .ports
+            .iter()
+            .copied()
+            .filter(|p| !ports.contains(p))
+            .collect();
+        if !released.is_empty() {
+            verified.drift.push(format!(
+                "{}: no longer listening on port {}",
+                label,
+                join(&released)
+            ));
+        }
+
+        verified.targets.push((
+            KillTarget {
+                ports,
+                process: first.process.clone(),
+            },
+            planned.signal,
+        ));
+    }
+
+    let planned_pids: Vec<u32> = plan.targets.iter().map(|t| t.identity.pid).collect();
+    let mut reported = Vec::new();
+    for listener in live {
+        let on_planned_port = plan
+            .targets
+            .iter()
+            .any(|t| t.ports.contains(&listener.port));
+        let key = (listener.port, listener.process.pid);
+        if on_planned_port
+            && !planned_pids.contains(&listener.process.pid)
+            && !reported.contains(&key)
+        {
+            reported.push(key);
+            verified.drift.push(format!(
+                "port {} is also held by PID {} ({}), which is not in the plan",
+                listener.port, listener.process.pid, listener.process.name
+            ));
+        }
+    }
+
+    verified
+}
+
+/// Write a plan as pretty JSON
+pub fn write_plan(path: &Path, plan: &KillPlan) -> Result<()> {
+    let json = serde_json::to_string_pretty(plan).map_err(|e| plan_error(path, e))?;
+    fs::write(path, json + "\n").map_err(|e| plan_error(path, e))
+}
+
+/// Read a plan written by `write_plan`
+pub fn read_plan(path: &Path) -> Result<KillPlan> {
+    let json = fs::read_to_string(path).map_err(|e| plan_error(path, e))?;
+    serde_json::from_str(&json).map_err(|e| plan_error(path, e))
+}
+
+fn plan_error(path: &Path, e: impl std::fmt::Display) -> PortDetectiveError {
+    PortDetectiveError::Plan {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    }
+}
+
+/// Result for a planned target that drifted and was not signaled
+fn skipped(planned: &PlannedKill, problem: &str) -> KillResult {
+    KillResult {
+        pid: planned.identity.pid,
+        name: planned.process.name.clone(),
+        ports: planned.ports.clone(),
+        signal: planned.signal.to_string(),
+        via: None,
+        open_connections: None,
+        success: false,
+        error: Some(format!("drift: {}", problem)),
+        respawns: Vec::new(),
+    }
+}
+
+fn join(ports: &[u16]) -> String {
+    ports
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Collapse listeners into one target per PID, ordered by lowest port then PID.
+///
+/// A process that holds several ports (or the same port over both TCP and
+/// UDP) becomes a single target with every port attached.
+pub fn group_by_pid(listeners: Vec<Listener>) -> Vec<KillTarget> {
+    let mut targets: Vec<KillTarget> = Vec::new();
+
+    for listener in listeners {
+        match targets
+            .iter_mut()
+            .find(|t| t.process.pid == listener.process.pid)
+        {
+            Some(target) => {
+                if !target.ports.contains(&listener.port) {
+                    target.ports.push(listener.port);
+                }
+                if target.process.protocol != listener.protocol {
+                    target.process.protocol = Protocol::Both;
+                }
+            }
+            None => targets.push(KillTarget {
+                ports: vec![listener.port],
+                process: listener.process,
+            }),
+        }
+    }
+
+    for target in &mut targets {
+        target.ports.sort_unstable();
+    }
+    targets.sort_by_key(|t| (t.ports[0], t.process.pid));
+    targets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manager::ServiceManager;
+    use std::cell::RefCell;
+
+    fn process(pid: u32) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: "server".to_string(),
+            user: "dev".to_string(),
+            uid: None,
+            command: vec![],
+            cwd: None,
+            exe: None,
+            parent_pid: None,
+            parent_name: None,
+            started: None,
+            manager: None,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    fn listener(pid: u32, port: u16, protocol: Protocol) -> Listener {
+        Listener {
+            port,
+            protocol,
+            local_addr: "0.0.0.0".to_string(),
+            process: ProcessInfo {
+                protocol,
+                ..process(pid)
+            },
+        }
+    }
+
+    #[test]
+    fn test_group_by_pid_keeps_every_owner() {
+        // Master and two workers sharing one listening socket
+        let listeners = vec![
+            listener(100, 8080, Protocol::Tcp),
+            listener(101, 8080, Protocol::Tcp),
+            listener(102, 8080, Protocol::Tcp),
+        ];
+        let pids: Vec<u32> = group_by_pid(listeners)
+            .iter()
+            .map(|t| t.process.pid)
+            .collect();
+        assert_eq!(pids, vec![100, 101, 102]);
+    }
+
+    #[test]
+    fn test_group_by_pid_merges_ports_and_protocols() {
+        let listeners = vec![
+            listener(7, 5353, Protocol::Udp),
+            listener(7, 3000, Protocol::Tcp),
+            listener(7, 3000, Protocol::Udp),
+        ];
+        let targets = group_by_pid(listeners);
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].ports, vec![3000, 5353]);
+        assert_eq!(targets[0].process.protocol, Protocol::Both);
+    }
+
+    #[test]
+    fn test_group_by_pid_orders_by_port() {
+        let listeners = vec![
+            listener(9, 9000, Protocol::Tcp),
+            listener(3, 3000, Protocol::Tcp),
+            listener(1, 3000, Protocol::Udp),
+        ];
+        let pids: Vec<u32> = group_by_pid(listeners)
+            .iter()
+            .map(|t| t.process.pid)
+            .collect();
+        assert_eq!(pids, vec![1, 3, 9]);
+    }
+
+    /// Records stop requests instead of running anything
+    #[derive(Default)]
+    struct FakeStopper {
+        calls: RefCell<Vec<ServiceManager>>,
+        fail: bool,
+    }
+
+    impl Stopper for FakeStopper {
+        fn stop(&self, manager: &ServiceManager) -> crate::error::Result<()> {
+            self.calls.borrow_mut().push(manager.clone());
+            if self.fail {
+                Err(crate::error::PortDetectiveError::StopFailed {
+                    command: manager.label(),
+                    reason: "Access denied".to_string(),
+                })
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn managed(pid: u32, unit: &str) -> KillTarget {
+        KillTarget {
+            ports: vec![80],
+            process: ProcessInfo {
+                manager: Some(ServiceManager::Systemd {
+                    unit: unit.to_string(),
+                    user: false,
+                }),
+                ..process(pid)
+            },
+        }
+    }
+
+    #[test]
+    fn test_execute_via_manager_stops_each_unit_once() {
+        let stopper = FakeStopper::default();
+        let opts = KillOptions {
+            via_manager: true,
+            ..Default::default()
+        };
+        // nginx master and worker live in the same unit
+        let targets = vec![
+            managed(i32::MAX as u32, "nginx.service"),
+            managed(i32::MAX as u32 - 1, "nginx.service"),
+        ];
+
+        let results = execute(&targets, &HashMap::new(), opts, &stopper);
+        assert_eq!(stopper.calls.borrow().len(), 1);
+        assert!(results.iter().all(|r| r.success));
+        assert_eq!(
+            results[1].via.as_deref(),
+            Some("systemctl stop nginx.service")
+        );
+    }
+
+    #[test]
+    fn test_execute_via_manager_reports_stop_failure() {
+        let stopper = FakeStopper {
+            fail: true,
+            ..Default::default()
+        };
+        let opts = KillOptions {
+            via_manager: true,
+            ..Default::default()
+        };
+        let results = execute(
+            &[managed(1, "nginx.service")],
+            &HashMap::new(),
+            opts,
+            &stopper,
+        );
+        assert!(!results[0].success);
+        assert!(results[0].error.as_ref().unwrap().contains("Access denied"));
+    }
+
+    #[test]
+    fn test_execute_ignores_manager_without_flag() {
+        let stopper = FakeStopper::default();
+        let results = execute(
+            &[managed(i32::MAX as u32, "nginx.service")],
+            &HashMap::new(),
+            KillOptions::default(),
+            &stopper,
+        );
+        assert!(stopper.calls.borrow().is_empty());
+        assert_eq!(results[0].via, None);
+        // Signal went to a PID that does not exist
+        assert!(!results[0].success);
+    }
+
+    #[test]
+    fn test_execute_reports_failure_per_pid() {
+        let missing = KillTarget {
+            ports: vec![2],
+            process: process(i32::MAX as u32),
+        };
+        let results = execute(
+            &[missing],
+            &HashMap::new(),
+            KillOptions::default(),
+            &FakeStopper::default(),
+        );
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].success);
+        assert!(results[0].error.is_some());
+        assert_eq!(results[0].signal, "SIGTERM");
+    }
+
+    #[test]
+    fn test_execute_uses_pinned_handle() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let target = KillTarget {
+            ports: vec![3000],
+            process: crate::proc::inspect(child.id(), Protocol::Tcp).unwrap(),
+        };
+        let handles = pin(std::slice::from_ref(&target));
+        assert!(handles.contains_key(&child.id()));
+
+        let results = execute(
+            &[target],
+            &handles,
+            KillOptions::default(),
+            &FakeStopper::default(),
+        );
+        assert!(results[0].success, "{:?}", results[0].error);
+        assert!(!child.wait().unwrap().success());
+    }
+
+    fn planned(pid: u32, ports: &[u16]) -> PlannedKill {
+        let process = ProcessInfo {
+            exe: Some(std::path::PathBuf::from("/usr/bin/server")),
+            ..process(pid)
+        };
+        PlannedKill {
+            identity: process.identity(),
+            ports: ports.to_vec(),
+            signal: KillSignal::Term,
+            reason: "test".to_string(),
+            process,
+        }
+    }
+
+    fn plan_of(targets: Vec<PlannedKill>) -> KillPlan {
+        KillPlan {
+            created: Local::now(),
+            host: "test".to_string(),
+            targets,
+        }
+    }
+
+    fn alive(pid: u32) -> Result<ProcessIdentity> {
+        Ok(planned(pid, &[]).identity)
+    }
+
+    #[test]
+    fn test_drain_stops_when_connections_close() {
+        let mut polls = vec![0, 1, 3];
+        let mut seen = Vec::new();
+        let counts = drain(
+            &[3000],
+            Duration::from_secs(10),
+            Duration::from_millis(1),
+            |ports| Ok(HashMap::from([(ports[0], polls.pop().unwrap())])),
+            |open, _| seen.push(open),
+        )
+        .unwrap();
+
+        assert_eq!(seen, vec![3, 1, 0]);
+        assert_eq!(counts[&3000], 0);
+    }
+
+    #[test]
+    fn test_drain_gives_up_after_timeout() {
+        let mut polls = 0;
+        let counts = drain(
+            &[3000, 3001],
+            Duration::from_millis(20),
+            Duration::from_millis(5),
+            |_| {
+                polls += 1;
+                Ok(HashMap::from([(3000, 2), (3001, 1)]))
+            },
+            |_, _| {},
+        )
+        .unwrap();
+
+        assert!(polls > 1);
+        assert_eq!(counts.values().sum::<usize>(), 3);
+    }
+
+    fn watch(
+        killed: &[KillTarget],
+        ancestry: &HashMap<u32, Vec<(u32, String)>>,
+        live: Vec<Listener>,
+        ancestors_of: impl Fn(u32) -> Vec<(u32, String)>,
+    ) -> Vec<Respawn> {
+        watch_respawns(
+            killed,
+            ancestry,
+            Duration::from_millis(10),
+            Duration::from_millis(1),
+            || Ok(live.clone()),
+            ancestors_of,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_watch_respawns_names_common_ancestor() {
+        let killed = vec![KillTarget {
+            ports: vec![3000],
+            process: process(42),
+        }];
+        let ancestry = HashMap::from([(
+            42,
+            vec![(40, "nodemon".to_string()), (10, "zsh".to_string())],
+        )]);
+        let live = vec![
+            listener(43, 3000, Protocol::Tcp),
+            listener(44, 3000, Protocol::Udp),
+            listener(50, 8080, Protocol::Tcp),
+        ];
+
+        let respawns = watch(&killed, &ancestry, live, |_| {
+            vec![(40, "nodemon".to_string()), (10, "zsh".to_string())]
+        });
+        assert_eq!(
+            respawns,
+            vec![Respawn {
+                port: 3000,
+                pid: 43,
+                name: "server".to_string(),
+                respawner: Some("nodemon (PID 40)".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_watch_respawns_prefers_manager() {
+        let mut old = process(42);
+        old.manager = Some(ServiceManager::Pm2 {
+            name: "api".to_string(),
+        });
+        let killed = vec![KillTarget {
+            ports: vec![3000],
+            process: old,
+        }];
+
+        let respawns = watch(
+            &killed,
+            &HashMap::new(),
+            vec![listener(43, 3000, Protocol::Tcp)],
+            |_| Vec::new(),
+        );
+        assert_eq!(respawns[0].respawner.as_deref(), Some("pm2 app api"));
+    }
+
+    #[test]
+    fn test_watch_respawns_ignores_freed_and_still_dying_ports() {
+        let killed = vec![KillTarget {
+            ports: vec![3000, 3001],
+            process: process(42),
+        }];
+
+        // 42 is still shutting down on 3000 and 3001 is free
+        let respawns = watch(
+            &killed,
+            &HashMap::new(),
+            vec![listener(42, 3000, Protocol::Tcp)],
+            |_| Vec::new(),
+        );
+        assert!(respawns.is_empty());
+    }
+
+    #[test]
+    fn test_record_respawns() {
+        let target = KillTarget {
+            ports: vec![3000],
+            process: process(42),
+        };
+        let mut results = vec![refused(&target, false, "test")];
+        results[0].success = true;
+
+        record_respawns(
+            &mut results,
+            vec![Respawn {
+                port: 3000,
+                pid: 43,
+                name: "server".to_string(),
+                respawner: None,
+            }],
+        );
+        assert_eq!(results[0].respawns.len(), 1);
+    }
+
+    #[test]
+    fn test_record_open_connections() {
+        let mut results = vec![refused(
+            &KillTarget {
+                ports: vec![3000, 3001],
+                process: process(42),
+            },
+            false,
+            "test",
+        )];
+        record_open_connections(
+            &mut results,
+            &HashMap::from([(3000, 2), (3001, 1), (4000, 7)]),
+        );
+        assert_eq!(results[0].open_connections, Some(3));
+    }
+
+    #[test]
+    fn test_plan_records_identity_and_reason() {
+        let targets = vec![KillTarget {
+            ports: vec![3000],
+            process: process(42),
+        }];
+        let plan = plan(&targets, KillSignal::Kill, "box".to_string(), |t| {
+            format!("listening on port {}", t.ports[0])
+        });
+        assert_eq!(plan.host, "box");
+        assert_eq!(plan.targets[0].identity.pid, 42);
+        assert_eq!(plan.targets[0].signal, KillSignal::Kill);
+        assert_eq!(plan.targets[0].reason, "listening on port 3000");
+    }
+
+    #[test]
+    fn test_verify_unchanged_plan() {
+        let plan = plan_of(vec![planned(42, &[3000])]);
+        let live = vec![listener(42, 3000, Protocol::Tcp)];
+
+        let verified = verify(&plan, &live, alive);
+        assert_eq!(verified.targets.len(), 1);
+        assert!(verified.skipped.is_empty());
+        assert!(verified.drift.is_empty());
+    }
+
+    #[test]
+    fn test_verify_skips_exited_and_reused_pids() {
+        let plan = plan_of(vec![planned(42, &[3000]), planned(43, &[3001])]);
+        let live = vec![
+            listener(42, 3000, Protocol::Tcp),
+            listener(43, 3001, Protocol::Tcp),
+        ];
+
+        let verified = verify(&plan, &live, |pid| {
+            if pid == 42 {
+                Err(crate::error::PortDetectiveError::ProcessNotFound(pid))
+            } else {
+                Ok(ProcessIdentity {
+                    exe: Some(std::path::PathBuf::from("/usr/bin/other")),
+                    ..process(pid).identity()
+                })
+            }
+        });
+        assert!(verified.targets.is_empty());
+        assert_eq!(verified.skipped.len(), 2);
+        assert!(verified.drift[0].contains("no longer running"));
+        assert!(verified.drift[1].contains("executable changed"));
+    }
+
+    #[test]
+    fn test_verify_reports_port_drift() {
+        let plan = plan_of(vec![planned(42, &[3000, 3001]), planned(44, &[4000])]);
+        let live = vec![
+            listener(42, 3000, Protocol::Tcp),
+            listener(99, 3000, Protocol::Tcp),
+        ];
+
+        let verified = verify(&plan, &live, alive);
+
+        // 42 still holds 3000 and is killed; 44 released its only port
+        assert_eq!(verified.targets.len(), 1);
+        assert_eq!(verified.targets[0].0.ports, vec![3000]);
+        assert_eq!(verified.skipped.len(), 1);
+        assert_eq!(verified.skipped[0].pid, 44);
+
+        let drift = verified.drift.join("\n");
+        assert!(drift.contains("PID 42 (server): no longer listening on port 3001"));
+        assert!(drift.contains("PID 44 (server): no longer listening on port 4000"));
+        assert!(drift.contains("port 3000 is also held by PID 99"));
+    }
+
+    #[test]
+    fn test_plan_file_round_trip() {
+        let path = std::env::temp_dir().join(format!("pd-plan-{}.json", std::process::id()));
+        let plan = plan_of(vec![planned(42, &[3000])]);
+
+        write_plan(&path, &plan).unwrap();
+        let back = read_plan(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(back.targets.len(), 1);
+        assert_eq!(back.targets[0].identity, plan.targets[0].identity);
+    }
+
+    #[test]
+    fn test_read_plan_reports_path() {
+        let err = read_plan(Path::new("/nonexistent/plan.json")).unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/plan.json"));
+    }
+}
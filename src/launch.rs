@@ -0,0 +1,56 @@
+//! Launching a command once its port is sorted out
+
+use crate::error::PortDetectiveError;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/// First port after `port` that nothing listens on and `is_free` accepts
+pub fn next_free_port(port: u16, listening: &[u16], is_free: impl Fn(u16) -> bool) -> Option<u16> {
+    (port.checked_add(1)?..=u16::MAX).find(|p| !listening.contains(p) && is_free(*p))
+}
+
+/// Replace this process with `command`, with `PORT` set to `port`.
+///
+/// Exec keeps the PID, so the caller's signals reach the command directly and
+/// its exit code becomes ours. Only returns if the exec failed.
+pub fn exec(command: &[String], port: u16) -> PortDetectiveError {
+    let Some((program, args)) = command.split_first() else {
+        return PortDetectiveError::RunFailed {
+            command: String::new(),
+            reason: "no command given".to_string(),
+        };
+    };
+
+    let err = Command::new(program)
+        .args(args)
+        .env("PORT", port.to_string())
+        .exec();
+    PortDetectiveError::RunFailed {
+        command: command.join(" "),
+        reason: err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_free_port_skips_busy_ports() {
+        assert_eq!(next_free_port(3000, &[3001, 3002], |_| true), Some(3003));
+        assert_eq!(next_free_port(3000, &[], |p| p != 3001), Some(3002));
+    }
+
+    #[test]
+    fn test_next_free_port_at_the_top() {
+        assert_eq!(next_free_port(u16::MAX, &[], |_| true), None);
+        assert_eq!(next_free_port(65534, &[65535], |_| true), None);
+    }
+
+    #[test]
+    fn test_exec_missing_program_fails() {
+        let err = exec(&["portdetective-no-such-program".to_string()], 3000);
+        assert!(matches!(err, PortDetectiveError::RunFailed { .. }));
+        assert!(exec(&[], 3000).to_string().contains("no command given"));
+    }
+}
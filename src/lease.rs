@@ -0,0 +1,268 @@
+//! Cooperative port leases, so parallel jobs on one machine never get the same port
+
+use crate::error::{PortDetectiveError, Result};
+use crate::state;
+use chrono::{DateTime, Local};
+use nix::fcntl::{Flock, FlockArg};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A port handed out to one owner until it expires or is released
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Lease {
+    pub port: u16,
+    pub owner: String,
+    pub granted: DateTime<Local>,
+    pub expires: DateTime<Local>,
+}
+
+/// Every lease currently handed out
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    pub leases: Vec<Lease>,
+}
+
+impl Registry {
+    /// Location of the shared registry
+    pub fn path() -> PathBuf {
+        state::runtime_dir().join("leases.json")
+    }
+
+    /// Drop leases that have expired
+    pub fn prune(&mut self, now: DateTime<Local>) {
+        self.leases.retain(|l| l.expires > now);
+    }
+
+    /// Lease `count` ports from `range` that are not leased and that
+    /// `is_free` reports as bindable right now, for `ttl` (which must not be zero)
+    pub fn acquire(
+        &mut self,
+        range: RangeInclusive<u16>,
+        count: usize,
+        owner: &str,
+        ttl: Duration,
+        now: DateTime<Local>,
+        is_free: impl Fn(u16) -> bool,
+    ) -> Result<Vec<Lease>> {
+        if ttl.is_zero() {
+            return Err(PortDetectiveError::InvalidTtl(
+                "must be longer than zero".to_string(),
+            ));
+        }
+        let expires = chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| now.checked_add_signed(ttl))
+            .ok_or_else(|| PortDetectiveError::InvalidTtl(format!("{:?} is too long", ttl)))?;
+        self.prune(now);
+
+        let ports: Vec<u16> = range
+            .clone()
+            .filter(|port| !self.leases.iter().any(|l| l.port == *port))
+            .filter(|&port| is_free(port))
+            .take(count)
+            .collect();
+        if ports.len() < count {
+            return Err(PortDetectiveError::NoFreePort {
+                start: *range.start(),
+                end: *range.end(),
+            });
+        }
+
+        let granted: Vec<Lease> = ports
+            .into_iter()
+            .map(|port| Lease {
+                port,
+                owner: owner.to_string(),
+                granted: now,
+                expires,
+            })
+            .collect();
+        self.leases.extend(granted.iter().cloned());
+        Ok(granted)
+    }
+
+    /// Remove leases on the given ports, or all leases of `owner`, and return them
+    pub fn release(&mut self, ports: &[u16], owner: Option<&str>) -> Vec<Lease> {
+        let (released, kept) = self
+            .leases
+            .drain(..)
+            .partition(|l| ports.contains(&l.port) || owner.is_some_and(|owner| l.owner == owner));
+        self.leases = kept;
+        released
+    }
+}
+
+/// Run `f` on the registry while holding an exclusive lock on it, saving
+/// whatever it changed
+pub fn with_registry<T>(path: &Path, f: impl FnOnce(&mut Registry) -> Result<T>) -> Result<T> {
+    let lock_error = |e: String| PortDetectiveError::State {
+        path: path.to_path_buf(),
+        reason: e,
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| lock_error(e.to_string()))?;
+    }
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))
+        .map_err(|e| lock_error(e.to_string()))?;
+    let _lock = Flock::lock(lock_file, FlockArg::LockExclusive)
+        .map_err(|(_, errno)| lock_error(format!("cannot lock: {}", errno)))?;
+
+    let mut registry: Registry = state::load(path)?;
+    let value = f(&mut registry)?;
+    state::save(path, &registry)?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    const TTL: Duration = Duration::from_secs(600);
+
+    fn registry_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("portdetective-lease-{}", std::process::id()))
+            .join(format!("{}.json", name))
+    }
+
+    #[test]
+    fn test_acquire_skips_leased_and_busy_ports() {
+        let now = Local::now();
+        let mut registry = Registry::default();
+
+        let first = registry
+            .acquire(20000..=20010, 1, "job-1", TTL, now, |_| true)
+            .unwrap();
+        assert_eq!(first[0].port, 20000);
+
+        let second = registry
+            .acquire(20000..=20010, 2, "job-2", TTL, now, |port| port != 20001)
+            .unwrap();
+        let ports: Vec<u16> = second.iter().map(|l| l.port).collect();
+        assert_eq!(ports, vec![20002, 20003]);
+        assert_eq!(registry.leases.len(), 3);
+    }
+
+    #[test]
+    fn test_acquire_fails_when_range_exhausted() {
+        let now = Local::now();
+        let mut registry = Registry::default();
+        registry
+            .acquire(20000..=20001, 2, "job-1", TTL, now, |_| true)
+            .unwrap();
+
+        let result = registry.acquire(20000..=20001, 1, "job-2", TTL, now, |_| true);
+        assert!(matches!(
+            result,
+            Err(PortDetectiveError::NoFreePort {
+                start: 20000,
+                end: 20001
+            })
+        ));
+        // A failed request leases nothing
+        assert_eq!(registry.leases.len(), 2);
+    }
+
+    #[test]
+    fn test_expired_leases_are_reused() {
+        let now = Local::now();
+        let mut registry = Registry::default();
+        registry
+            .acquire(
+                20000..=20000,
+                1,
+                "job-1",
+                Duration::from_secs(1),
+                now,
+                |_| true,
+            )
+            .unwrap();
+
+        let later = now + chrono::Duration::seconds(2);
+        let lease = registry
+            .acquire(20000..=20000, 1, "job-2", TTL, later, |_| true)
+            .unwrap();
+        assert_eq!(lease[0].owner, "job-2");
+        assert_eq!(registry.leases.len(), 1);
+    }
+
+    #[test]
+    fn test_acquire_rejects_zero_and_huge_ttl() {
+        let now = Local::now();
+        let mut registry = Registry::default();
+
+        for ttl in [Duration::ZERO, Duration::from_secs(99_999_999_999 * 86_400)] {
+            let result = registry.acquire(20000..=20010, 1, "job-1", ttl, now, |_| true);
+            assert!(matches!(result, Err(PortDetectiveError::InvalidTtl(_))));
+        }
+        assert!(registry.leases.is_empty());
+    }
+
+    #[test]
+    fn test_release_by_port_and_owner() {
+        let now = Local::now();
+        let mut registry = Registry::default();
+        registry
+            .acquire(20000..=20010, 2, "job-1", TTL, now, |_| true)
+            .unwrap();
+        registry
+            .acquire(20000..=20010, 1, "job-2", TTL, now, |_| true)
+            .unwrap();
+
+        let released = registry.release(&[20002], None);
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].owner, "job-2");
+
+        let released = registry.release(&[], Some("job-1"));
+        assert_eq!(released.len(), 2);
+        assert!(registry.leases.is_empty());
+    }
+
+    #[test]
+    fn test_concurrent_callers_never_share_a_port() {
+        let path = Arc::new(registry_path("concurrent"));
+        let _ = fs::remove_file(path.as_ref());
+
+        let workers: Vec<_> = (0..8)
+            .map(|i| {
+                let path = Arc::clone(&path);
+                thread::spawn(move || {
+                    with_registry(&path, |registry| {
+                        registry.acquire(
+                            20000..=20100,
+                            2,
+                            &format!("job-{}", i),
+                            TTL,
+                            Local::now(),
+                            |_| true,
+                        )
+                    })
+                    .unwrap()
+                })
+            })
+            .collect();
+
+        let mut ports: Vec<u16> = workers
+            .into_iter()
+            .flat_map(|w| w.join().unwrap())
+            .map(|l| l.port)
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        assert_eq!(ports.len(), 16);
+
+        let registry: Registry = state::load(&path).unwrap();
+        assert_eq!(registry.leases.len(), 16);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,316 @@
+//! Listening sockets joined with the processes that own them
+
+use crate::cli::ProtocolFilter;
+use crate::error::Result;
+use crate::model::{PortEntry, ProcessInfo, Protocol};
+use crate::net;
+use crate::proc;
+use chrono::Local;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A listening socket and the process behind it
+#[derive(Debug, Clone)]
+pub struct Listener {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub local_addr: String,
+    pub process: ProcessInfo,
+}
+
+impl Listener {
+    /// Flatten into a row for the port list
+    pub fn entry(&self) -> PortEntry {
+        let command = if self.process.command.is_empty() {
+            self.process.name.clone()
+        } else {
+            self.process.command.join(" ")
+        };
+
+        PortEntry {
+            port: self.port,
+            protocol: self.protocol,
+            pid: self.process.pid,
+            name: self.process.name.clone(),
+            user: self.process.user.clone(),
+            command,
+            address: self.local_addr.clone(),
+        }
+    }
+}
+
+/// Collect every listening socket with its owning process, sorted by port.
+///
+/// Each PID is inspected once; sockets whose process exited between
+/// discovery and inspection are skipped.
+pub fn collect(filter: ProtocolFilter) -> Result<Vec<Listener>> {
+    let sockets = net::get_listening_sockets(filter)?;
+    let mut processes: HashMap<u32, Option<ProcessInfo>> = HashMap::new();
+    let mut listeners = Vec::new();
+
+    for socket in sockets {
+        let info = processes
+            .entry(socket.pid)
+            .or_insert_with(|| proc::inspect(socket.pid, socket.protocol).ok());
+
+        if let Some(info) = info {
+            listeners.push(Listener {
+                port: socket.port,
+                protocol: socket.protocol,
+                local_addr: socket.local_addr,
+                process: ProcessInfo {
+                    protocol: socket.protocol,
+                    ..info.clone()
+                },
+            });
+        }
+    }
+
+    listeners.sort_by_key(|l| (l.port, l.process.pid));
+    Ok(listeners)
+}
+
+/// Every listening socket, including those whose owner cannot be inspected
+/// (another user's, when not running as root), sorted by port.
+///
+/// Sockets come from the socket table first, so none is dropped for want of
+/// an owner; those get `ProcessInfo::unknown`. For reports, never for killing.
+pub fn collect_all(filter: ProtocolFilter) -> Result<Vec<Listener>> {
+    let mut bindings = net::listening_bindings(filter)?;
+    net::attach_owners(&mut bindings);
+    let mut processes: HashMap<u32, Option<ProcessInfo>> = HashMap::new();
+    let mut listeners = Vec::new();
+
+    for binding in bindings {
+        let owners: Vec<ProcessInfo> = binding
+            .pids
+            .iter()
+            .filter_map(|&pid| {
+                processes
+                    .entry(pid)
+                    .or_insert_with(|| proc::inspect(pid, binding.protocol).ok())
+                    .clone()
+            })
+            .collect();
+        let owners = if owners.is_empty() {
+            vec![ProcessInfo::unknown(binding.protocol)]
+        } else {
+            owners
+        };
+        for process in owners {
+            listeners.push(Listener {
+                port: binding.port,
+                protocol: binding.protocol,
+                local_addr: binding.address.to_string(),
+                process: ProcessInfo {
+                    protocol: binding.protocol,
+                    ..process
+                },
+            });
+        }
+    }
+
+    listeners.sort_by_key(|l| (l.port, l.process.pid));
+    Ok(listeners)
+}
+
+/// Rows for the port list, one per port and PID.
+///
+/// A process bound to several addresses on the same port (IPv4 and IPv6,
+/// say) gets one row listing all of them.
+pub fn entries(filter: ProtocolFilter) -> Result<Vec<PortEntry>> {
+    Ok(rows(collect(filter)?))
+}
+
+/// Rows for every listening socket, including those `collect_all` can only
+/// give an `unknown` owner
+pub fn all_entries(filter: ProtocolFilter) -> Result<Vec<PortEntry>> {
+    Ok(rows(collect_all(filter)?))
+}
+
+fn rows(listeners: Vec<Listener>) -> Vec<PortEntry> {
+    let mut entries: Vec<PortEntry> = Vec::new();
+    let mut addresses: HashMap<(u16, u32), Vec<String>> = HashMap::new();
+
+    for listener in listeners {
+        let bound = addresses
+            .entry((listener.port, listener.process.pid))
+            .or_default();
+        if bound.is_empty() {
+            entries.push(listener.entry());
+        }
+        if !bound.contains(&listener.local_addr) {
+            bound.push(listener.local_addr);
+        }
+    }
+
+    for entry in &mut entries {
+        if let Some(bound) = addresses.get_mut(&(entry.port, entry.pid)) {
+            bound.sort();
+            entry.address = bound.join(", ");
+        }
+    }
+    entries
+}
+
+/// Criteria for picking processes out of the port list
+#[derive(Debug, Clone, Default)]
+pub struct Selector {
+    pub name: Option<Regex>,
+    pub user: Option<String>,
+    pub cwd_under: Option<PathBuf>,
+    pub older_than: Option<Duration>,
+}
+
+impl Selector {
+    /// Whether no criteria are set
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.user.is_none()
+            && self.cwd_under.is_none()
+            && self.older_than.is_none()
+    }
+
+    /// Whether the process satisfies every criterion that is set
+    pub fn matches(&self, info: &ProcessInfo) -> bool {
+        if let Some(re) = &self.name {
+            let command = info.command.join(" ");
+            if !re.is_match(&info.name) && !re.is_match(&command) {
+                return false;
+            }
+        }
+
+        if let Some(user) = &self.user
+            && &info.user != user
+        {
+            return false;
+        }
+
+        if let Some(root) = &self.cwd_under {
+            match &info.cwd {
+                Some(cwd) if cwd.starts_with(root) => {}
+                _ => return false,
+            }
+        }
+
+        if let Some(min_age) = self.older_than {
+            let Some(started) = info.started else {
+                return false;
+            };
+            let age = (Local::now() - started).to_std().unwrap_or_default();
+            if age < min_age {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn process(name: &str, user: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid: 4242,
+            name: name.to_string(),
+            user: user.to_string(),
+            uid: None,
+            command: vec![name.to_string(), "--port".to_string(), "5173".to_string()],
+            cwd: Some(PathBuf::from("/home/dev/projects/site")),
+            exe: None,
+            parent_pid: Some(1),
+            parent_name: None,
+            started: Some(Local::now() - TimeDelta::hours(5)),
+            manager: None,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    #[test]
+    fn test_empty_selector_matches_everything() {
+        let selector = Selector::default();
+        assert!(selector.is_empty());
+        assert!(selector.matches(&process("vite", "dev")));
+    }
+
+    #[test]
+    fn test_selector_name_regex() {
+        let selector = Selector {
+            name: Some(Regex::new("^(vite|jupyter)").unwrap()),
+            ..Default::default()
+        };
+        assert!(selector.matches(&process("vite", "dev")));
+        assert!(selector.matches(&process("jupyter-lab", "dev")));
+        assert!(!selector.matches(&process("postgres", "dev")));
+    }
+
+    #[test]
+    fn test_selector_user_and_cwd() {
+        let selector = Selector {
+            user: Some("dev".to_string()),
+            cwd_under: Some(PathBuf::from("/home/dev/projects")),
+            ..Default::default()
+        };
+        assert!(selector.matches(&process("vite", "dev")));
+        assert!(!selector.matches(&process("vite", "root")));
+
+        let mut elsewhere = process("vite", "dev");
+        elsewhere.cwd = Some(PathBuf::from("/home/dev/projectsold"));
+        assert!(!selector.matches(&elsewhere));
+        elsewhere.cwd = None;
+        assert!(!selector.matches(&elsewhere));
+    }
+
+    #[test]
+    fn test_selector_older_than() {
+        let selector = Selector {
+            older_than: Some(Duration::from_secs(4 * 3600)),
+            ..Default::default()
+        };
+        assert!(selector.matches(&process("vite", "dev")));
+
+        let mut young = process("vite", "dev");
+        young.started = Some(Local::now() - TimeDelta::minutes(10));
+        assert!(!selector.matches(&young));
+        young.started = None;
+        assert!(!selector.matches(&young));
+    }
+
+    #[test]
+    fn test_listener_entry_falls_back_to_name() {
+        let mut info = process("vite", "dev");
+        info.command.clear();
+        let listener = Listener {
+            port: 5173,
+            protocol: Protocol::Tcp,
+            local_addr: "127.0.0.1".to_string(),
+            process: info,
+        };
+        let entry = listener.entry();
+        assert_eq!(entry.port, 5173);
+        assert_eq!(entry.command, "vite");
+    }
+
+    #[test]
+    fn test_collect_is_sorted() {
+        let listeners = collect(ProtocolFilter::Both).unwrap();
+        assert!(listeners.windows(2).all(|w| w[0].port <= w[1].port));
+    }
+
+    #[test]
+    fn test_collect_all_includes_own_listener() {
+        let socket = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        let listeners = collect_all(ProtocolFilter::TcpOnly).unwrap();
+        assert!(listeners.windows(2).all(|w| w[0].port <= w[1].port));
+        let ours = listeners.iter().find(|l| l.port == port).unwrap();
+        assert_eq!(ours.process.pid, std::process::id());
+        assert_eq!(ours.local_addr, "127.0.0.1");
+    }
+}
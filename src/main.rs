@@ -3,34 +3,259 @@
 //! A tiny CLI that answers: "What's running on port 3000 right now, and how do I safely kill it?"
 
 mod cli;
+mod color;
+mod config;
 mod error;
+mod exposure;
+mod guard;
+mod hold;
+mod hooks;
+mod kill;
+mod launch;
+mod lease;
+mod listing;
+mod manager;
 mod model;
+mod monitor;
 mod net;
 mod output;
+mod policy;
 mod proc;
+mod project;
+mod prompt;
+mod remember;
+mod render;
+mod snapshot;
+#[cfg(target_os = "linux")]
+mod sockdiag;
+mod state;
+mod status;
+mod sweep;
+mod trap;
+mod wait;
+mod watch;
 
 use clap::Parser;
-use cli::{Cli, Commands, ProtocolFilter};
+use cli::{
+    Cli, Commands, ConfigAction, LeaseAction, LeaseArgs, OnConflict, OutputFormat, ProtocolFilter,
+    SelectorArgs, WaitUntil,
+};
 use error::{PortDetectiveError, Result};
-use model::{PortEntry, PortReport, Protocol};
-use std::io::{self, Write};
+use kill::KillOptions;
+use listing::{Listener, Selector};
+use model::{ApplyReport, KillResult, KillSignal, KillTarget, PortEntry, PortReport, Protocol};
+use proc::ProcessHandle;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::net::IpAddr;
+use std::path::Path;
 use std::process::ExitCode;
+use std::time::Duration;
 
 fn main() -> ExitCode {
-    let cli = Cli::parse();
+    // A broken config must not keep `--help` or `config show` from working,
+    // so its errors are reported once color settings are known
+    let (settings, config_errors) = config::load();
+    config::init_port_names(settings.ports.clone());
+    let mut cli = Cli::parse_from(config::expand_alias(
+        std::env::args().collect(),
+        &settings.aliases,
+    ));
+    let settings = config::init(settings.with_flags(&mut cli));
+    color::set_enabled(settings.color_enabled());
+
+    let showing_config = matches!(
+        cli.command,
+        Some(Commands::Config {
+            action: ConfigAction::Show
+        })
+    );
+    if !showing_config {
+        for e in &config_errors {
+            output::print_warning(&format!("{}; ignoring it", e));
+        }
+    }
+    // Refuse before anything is killed or bound, not after
+    let format = match settings.format_for(&cli) {
+        Ok(format) => format,
+        Err(e) => {
+            output::print_error(&e.to_string());
+            return ExitCode::from(1);
+        }
+    };
+    cli.json = format == OutputFormat::Json;
 
     let result = match &cli.command {
         Some(Commands::Kill {
+            ports,
+            selector,
+            all,
+            force,
+            no_prompt,
+            unguarded,
+            via_manager,
+            drain,
+            respawn_window,
+            remember,
+            remember_secrets,
+            and_hold,
+            hold_for,
+            plan,
+            apply,
+        }) => {
+            let selector = build_selector(selector);
+            let opts = KillOptions {
+                force: *force,
+                no_prompt: *no_prompt,
+                unguarded: *unguarded,
+                via_manager: *via_manager,
+                drain: *drain,
+                // The port is taken back right away, so nothing can respawn onto it
+                respawn_window: if *and_hold {
+                    Duration::ZERO
+                } else {
+                    *respawn_window
+                },
+                remember: *remember,
+                remember_secrets: *remember_secrets,
+                and_hold: *and_hold,
+                hold_for: *hold_for,
+                format,
+            };
+            if let Some(path) = apply {
+                run_apply(path, opts)
+            } else if selector.is_empty() {
+                run_kill(ports, opts, plan.as_deref(), cli.protocol_filter())
+            } else {
+                run_bulk_kill(
+                    ports,
+                    &selector,
+                    *all,
+                    opts,
+                    plan.as_deref(),
+                    cli.protocol_filter(),
+                )
+            }
+        }
+        Some(Commands::Wait {
+            port,
+            until,
+            timeout,
+            interval,
+            name,
+            user,
+        }) => {
+            let selector = build_selector(&SelectorArgs {
+                name: name.clone(),
+                user: user.clone(),
+                ..SelectorArgs::default()
+            });
+            run_wait(
+                *port,
+                *until,
+                &selector,
+                *timeout,
+                *interval,
+                cli.protocol_filter(),
+                cli.json,
+            )
+        }
+        Some(Commands::Trap {
+            port,
+            duration,
+            interval,
+            log,
+        }) => run_trap(
+            *port,
+            *duration,
+            *interval,
+            log.as_deref(),
+            cli.protocol_filter(),
+            cli.json,
+        ),
+        Some(Commands::Run {
             port,
+            on_conflict,
+            command,
+        }) => run_command(*port, *on_conflict, command),
+        Some(Commands::Sweep {
+            idle,
+            older_than,
+            dry_run,
             force,
             no_prompt,
-        }) => run_kill(*port, *force, *no_prompt, cli.protocol_filter(), cli.json),
-        Some(Commands::List) => run_list(cli.protocol_filter(), cli.json),
-        Some(Commands::Inspect { port }) => run_inspect(*port, cli.protocol_filter(), cli.json),
+        }) => {
+            let criteria = sweep::Criteria {
+                idle: *idle,
+                older_than: *older_than,
+            };
+            let opts = KillOptions {
+                force: *force,
+                no_prompt: *no_prompt,
+                format,
+                ..KillOptions::default()
+            };
+            run_sweep(criteria, *dry_run, opts, cli.protocol_filter())
+        }
+        Some(Commands::Hold {
+            ports,
+            addr,
+            timeout,
+        }) => {
+            let protocol = match cli.protocol_filter() {
+                ProtocolFilter::TcpOnly => Protocol::Tcp,
+                ProtocolFilter::UdpOnly => Protocol::Udp,
+                ProtocolFilter::Both => Protocol::Both,
+            };
+            run_hold(ports, *addr, protocol, *timeout, cli.json)
+        }
+        Some(Commands::Release { ports }) => run_release(ports, cli.json),
+        Some(Commands::Lease { action, request }) => match action {
+            None => run_lease(request, cli.json),
+            Some(LeaseAction::Release { ports, owner }) => {
+                run_lease_release(ports, owner.as_deref(), cli.json)
+            }
+            Some(LeaseAction::List) => run_lease_list(cli.json),
+        },
+        Some(Commands::Restart { target }) => run_restart(*target, cli.json),
+        Some(Commands::List) => run_list(cli.protocol_filter(), format),
+        Some(Commands::Monitor { interval, existing }) => {
+            run_monitor(*interval, *existing, cli.protocol_filter())
+        }
+        Some(Commands::Hooks { config, interval }) => {
+            run_hooks(config.as_deref(), *interval, cli.protocol_filter())
+        }
+        Some(Commands::Audit { policy }) => {
+            run_audit(policy.as_deref(), cli.protocol_filter(), cli.json)
+        }
+        Some(Commands::Exposure) => run_exposure(cli.protocol_filter(), cli.json),
+        Some(Commands::Config {
+            action: ConfigAction::Show,
+        }) => {
+            if cli.json {
+                output::print_config_json(settings);
+            } else {
+                output::print_config(settings);
+            }
+            for e in &config_errors {
+                output::print_error(&e.to_string());
+            }
+            Ok(ExitCode::from(if config_errors.is_empty() { 0 } else { 1 }))
+        }
+        Some(Commands::Project { dir }) => {
+            run_project(dir.as_deref(), cli.protocol_filter(), cli.json)
+        }
+        Some(Commands::Status) => run_status(cli.protocol_filter(), cli.json),
+        Some(Commands::Snapshot) => run_snapshot(cli.protocol_filter()),
+        Some(Commands::Diff { before, after }) => {
+            run_diff(before, after.as_deref(), cli.protocol_filter(), cli.json)
+        }
+        Some(Commands::Watch { interval }) => run_watch(*interval, cli.protocol_filter(), cli.json),
+        Some(Commands::Inspect { port }) => run_inspect(*port, cli.protocol_filter(), format),
         None => {
             // Default: if port provided, inspect it
             if let Some(port) = cli.port {
-                run_inspect(port, cli.protocol_filter(), cli.json)
+                run_inspect(port, cli.protocol_filter(), format)
             } else {
                 // No port provided, show help hint
                 eprintln!("Usage: portdetective <PORT>");
@@ -51,7 +276,17 @@ fn main() -> ExitCode {
                 PortDetectiveError::PortFree(_) => ExitCode::from(0),
                 PortDetectiveError::PermissionDenied(_) => ExitCode::from(2),
                 PortDetectiveError::ProcessNotFound(_) => ExitCode::from(3),
+                PortDetectiveError::ProcessChanged { .. } => ExitCode::from(3),
                 PortDetectiveError::Cancelled => ExitCode::from(4),
+                PortDetectiveError::AmbiguousSelector(_) => ExitCode::from(5),
+                PortDetectiveError::Protected { .. } => ExitCode::from(6),
+                PortDetectiveError::NotInteractive => ExitCode::from(4),
+                PortDetectiveError::Plan { .. } => ExitCode::from(7),
+                PortDetectiveError::NotRemembered(_) => ExitCode::from(3),
+                PortDetectiveError::NotHeld(_) => ExitCode::from(3),
+                PortDetectiveError::NotLeased(_) => ExitCode::from(3),
+                PortDetectiveError::StillInUse(_) => ExitCode::from(9),
+                PortDetectiveError::RunFailed { .. } => ExitCode::from(127),
                 _ => ExitCode::from(1),
             }
         }
@@ -59,7 +294,7 @@ fn main() -> ExitCode {
 }
 
 /// Inspect what's running on a port
-fn run_inspect(port: u16, filter: ProtocolFilter, json: bool) -> Result<ExitCode> {
+fn run_inspect(port: u16, filter: ProtocolFilter, format: OutputFormat) -> Result<ExitCode> {
     let protocol = match filter {
         ProtocolFilter::TcpOnly => Protocol::Tcp,
         ProtocolFilter::UdpOnly => Protocol::Udp,
@@ -70,8 +305,8 @@ fn run_inspect(port: u16, filter: ProtocolFilter, json: bool) -> Result<ExitCode
 
     if sockets.is_empty() {
         let report = PortReport::free(port, protocol);
-        if json {
-            output::print_report_json(&report);
+        if format.is_structured() {
+            output::print_report_as(&report, format);
         } else {
             output::print_report(&report);
         }
@@ -99,8 +334,8 @@ fn run_inspect(port: u16, filter: ProtocolFilter, json: bool) -> Result<ExitCode
 
     if processes.is_empty() {
         let report = PortReport::free(port, protocol);
-        if json {
-            output::print_report_json(&report);
+        if format.is_structured() {
+            output::print_report_as(&report, format);
         } else {
             output::print_report(&report);
         }
@@ -108,8 +343,8 @@ fn run_inspect(port: u16, filter: ProtocolFilter, json: bool) -> Result<ExitCode
     }
 
     let report = PortReport::in_use(port, protocol, processes);
-    if json {
-        output::print_report_json(&report);
+    if format.is_structured() {
+        output::print_report_as(&report, format);
     } else {
         output::print_report(&report);
     }
@@ -117,97 +352,1153 @@ fn run_inspect(port: u16, filter: ProtocolFilter, json: bool) -> Result<ExitCode
     Ok(ExitCode::from(1)) // Port is in use
 }
 
-/// Kill the process on a port
+/// Kill every process on the given port(s)
 fn run_kill(
+    ports: &[u16],
+    opts: KillOptions,
+    plan: Option<&Path>,
+    filter: ProtocolFilter,
+) -> Result<ExitCode> {
+    let listeners = kill::select(ports, &Selector::default(), filter)?;
+    // Where each port was bound, so `--and-hold` can take back the same sockets
+    let sockets: Vec<(u16, IpAddr, Protocol)> = listeners
+        .iter()
+        .filter_map(|l| Some((l.port, l.local_addr.parse().ok()?, l.protocol)))
+        .collect();
+    let targets = kill::group_by_pid(listeners);
+
+    if targets.is_empty() {
+        let protocol = match filter {
+            ProtocolFilter::TcpOnly => Protocol::Tcp,
+            ProtocolFilter::UdpOnly => Protocol::Udp,
+            ProtocolFilter::Both => Protocol::Both,
+        };
+        let reports: Vec<PortReport> = ports
+            .iter()
+            .map(|&port| PortReport::free(port, protocol))
+            .collect();
+        if opts.format.is_structured() {
+            output::print_reports_as(&reports, opts.format);
+        } else {
+            reports.iter().for_each(output::print_report);
+        }
+        return Ok(ExitCode::from(0));
+    }
+    if let Some(path) = plan {
+        return write_kill_plan(targets, opts, path, "listening");
+    }
+
+    let handles = kill::pin(&targets);
+    let (targets, refused) = apply_guardrails(targets, opts)?;
+    let targets = if opts.no_prompt {
+        targets
+    } else {
+        prompt::confirm_kill_targets(targets)?
+    };
+
+    let results = kill_targets(&targets, &handles, refused, opts)?;
+    if opts.and_hold {
+        return hold_after_kill(&targets, &sockets, results, opts);
+    }
+    Ok(kill_exit_code(&results))
+}
+
+/// Kill every listening process matched by a selector, after one confirmation
+fn run_bulk_kill(
+    ports: &[u16],
+    selector: &Selector,
+    all: bool,
+    opts: KillOptions,
+    plan: Option<&Path>,
+    filter: ProtocolFilter,
+) -> Result<ExitCode> {
+    let listeners = kill::select(ports, selector, filter)?;
+    let entries: Vec<PortEntry> = listeners.iter().map(Listener::entry).collect();
+    let targets = kill::group_by_pid(listeners);
+
+    if !opts.format.is_structured() {
+        output::print_port_list(&entries, config::current());
+    }
+    if targets.is_empty() {
+        return Ok(ExitCode::from(0));
+    }
+    if targets.len() > 1 && !all {
+        return Err(PortDetectiveError::AmbiguousSelector(targets.len()));
+    }
+    if let Some(path) = plan {
+        return write_kill_plan(targets, opts, path, "matched the selector, listening");
+    }
+
+    let handles = kill::pin(&targets);
+    let (targets, refused) = apply_guardrails(targets, opts)?;
+    if !opts.no_prompt {
+        prompt::confirm_bulk_kill(targets.len())?;
+    }
+
+    signal_targets(&targets, &handles, refused, opts)
+}
+
+/// Block until the port is listening or free; exit 8 on timeout
+fn run_wait(
     port: u16,
-    force: bool,
-    no_prompt: bool,
+    until: WaitUntil,
+    selector: &Selector,
+    timeout: Duration,
+    interval: Duration,
     filter: ProtocolFilter,
     json: bool,
 ) -> Result<ExitCode> {
-    let sockets = net::find_processes_by_port(port, filter)?;
+    let report = wait::wait_for(port, until, selector, timeout, interval, || {
+        port_listeners(port, filter)
+    })?;
 
-    if sockets.is_empty() {
-        if json {
-            let protocol = match filter {
-                ProtocolFilter::TcpOnly => Protocol::Tcp,
-                ProtocolFilter::UdpOnly => Protocol::Udp,
-                ProtocolFilter::Both => Protocol::Both,
+    if json {
+        output::print_wait_report_json(&report);
+    } else {
+        output::print_wait_report(&report);
+    }
+
+    if report.reached {
+        Ok(ExitCode::from(0))
+    } else {
+        Ok(ExitCode::from(8))
+    }
+}
+
+/// Listening sockets on a port, counted whether or not their owner can be
+/// seen, with the owners that can
+fn port_listeners(port: u16, filter: ProtocolFilter) -> Result<wait::Listeners> {
+    let sockets: Vec<net::Binding> = net::bindings_on_port(port, filter)?
+        .into_iter()
+        .filter(|b| b.listening)
+        .collect();
+    let mut seen = std::collections::HashSet::new();
+    let owners = sockets
+        .iter()
+        .flat_map(|b| b.pids.iter().map(move |&pid| (pid, b.protocol)))
+        .filter(|(pid, _)| seen.insert(*pid))
+        .filter_map(|(pid, protocol)| proc::inspect(pid, protocol).ok())
+        .collect();
+    Ok(wait::Listeners {
+        sockets: sockets.len(),
+        owners,
+    })
+}
+
+/// Log every process that binds the port while the trap is set
+fn run_trap(
+    port: u16,
+    duration: Duration,
+    interval: Duration,
+    log_path: Option<&Path>,
+    filter: ProtocolFilter,
+    json: bool,
+) -> Result<ExitCode> {
+    let log_error = |path: &Path, e: std::io::Error| PortDetectiveError::Log {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    };
+    let mut log_file = match log_path {
+        Some(path) => Some(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| log_error(path, e))?,
+        ),
+        None => None,
+    };
+
+    if !json {
+        output::print_trap_set(port, duration);
+    }
+    let sightings = trap::trap(
+        duration,
+        interval,
+        || net::sockets_on_port(port, filter),
+        |binding| {
+            let mut binding = binding.clone();
+            net::attach_owners(std::slice::from_mut(&mut binding));
+            trap::capture(&binding)
+        },
+        |sighting| {
+            if json {
+                output::print_sighting_json(sighting);
+            } else {
+                output::print_sighting(sighting);
+            }
+            if let (Some(file), Some(path)) = (log_file.as_mut(), log_path) {
+                let line = serde_json::to_string(sighting).unwrap_or_default();
+                writeln!(file, "{}", line).map_err(|e| log_error(path, e))?;
+            }
+            Ok(())
+        },
+    )?;
+    if !json {
+        output::print_trap_summary(port, sightings, duration);
+    }
+
+    if sightings == 0 {
+        Ok(ExitCode::from(0))
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+/// Make sure the port is usable, then exec the command with `PORT` set
+fn run_command(port: u16, on_conflict: Option<OnConflict>, command: &[String]) -> Result<ExitCode> {
+    if port_listeners(port, ProtocolFilter::Both)?.sockets == 0 {
+        return Err(launch::exec(command, port));
+    }
+    let owners = kill::group_by_pid(kill::select(
+        &[port],
+        &Selector::default(),
+        ProtocolFilter::Both,
+    )?);
+
+    if owners.is_empty() {
+        output::print_hidden_owner(port);
+    }
+    for target in &owners {
+        output::print_kill_prompt(&target.ports, &target.process);
+    }
+    let choice = match on_conflict {
+        Some(choice) => choice,
+        None => prompt::choose_on_conflict()?,
+    };
+
+    let port = match choice {
+        OnConflict::Abort => return Err(PortDetectiveError::Cancelled),
+        OnConflict::Kill if owners.is_empty() => {
+            return Err(PortDetectiveError::PermissionDenied(format!(
+                "cannot see who owns port {}; run as root to kill it",
+                port
+            )));
+        }
+        OnConflict::Kill => {
+            let opts = KillOptions {
+                no_prompt: true,
+                ..KillOptions::default()
             };
-            let report = PortReport::free(port, protocol);
-            output::print_report_json(&report);
-        } else {
-            output::print_report(&PortReport::free(port, Protocol::Both));
+            let handles = kill::pin(&owners);
+            let (owners, refused) = apply_guardrails(owners, opts)?;
+            let results = kill_targets(&owners, &handles, refused, opts)?;
+            if results.iter().any(|r| !r.success) {
+                return Ok(ExitCode::from(1));
+            }
+            let freed = wait::wait_for(
+                port,
+                WaitUntil::Free,
+                &Selector::default(),
+                hold::GRAB_WINDOW,
+                Duration::from_millis(50),
+                || port_listeners(port, ProtocolFilter::Both),
+            )?;
+            if !freed.reached {
+                return Err(PortDetectiveError::StillInUse(port));
+            }
+            port
         }
+        OnConflict::Next => {
+            let listening: Vec<u16> = net::listening_bindings(ProtocolFilter::Both)?
+                .iter()
+                .map(|b| b.port)
+                .collect();
+            let next =
+                launch::next_free_port(port, &listening, |p| hold::is_free(p, Protocol::Both))
+                    .ok_or_else(|| PortDetectiveError::RunFailed {
+                        command: command.join(" "),
+                        reason: format!("no free port above {}", port),
+                    })?;
+            output::print_using_port(port, next);
+            next
+        }
+    };
+
+    Err(launch::exec(command, port))
+}
+
+/// Find the current user's stale listeners, show them and offer to kill them
+fn run_sweep(
+    criteria: sweep::Criteria,
+    dry_run: bool,
+    opts: KillOptions,
+    filter: ProtocolFilter,
+) -> Result<ExitCode> {
+    let user = proc::current_user().unwrap_or_else(|| "unknown".to_string());
+    let targets: Vec<KillTarget> = kill::group_by_pid(listing::collect(filter)?)
+        .into_iter()
+        .filter(|t| t.process.user == user)
+        .collect();
+
+    let mut ports: Vec<u16> = targets.iter().flat_map(|t| t.ports.clone()).collect();
+    ports.sort_unstable();
+    ports.dedup();
+    let connections = net::established_connections(&ports)?;
+    let open: Vec<usize> = targets
+        .iter()
+        .map(|t| t.ports.iter().filter_map(|p| connections.get(p)).sum())
+        .collect();
+
+    let path = sweep::Activity::path();
+    let mut activity = sweep::Activity::load(&path)?;
+    let now = chrono::Local::now();
+    let candidates: Vec<sweep::Candidate> = targets
+        .iter()
+        .zip(&open)
+        .filter_map(|(target, &open_connections)| {
+            let facts = sweep::Facts {
+                has_tty: proc::has_controlling_tty(target.process.pid),
+                open_connections,
+                last_active: activity.get(target),
+            };
+            let reasons = sweep::reasons(target, &facts, &criteria, now);
+            (!reasons.is_empty()).then(|| sweep::Candidate {
+                target: target.clone(),
+                reasons,
+            })
+        })
+        .collect();
+    let seen: Vec<(&KillTarget, usize)> = targets.iter().zip(open).collect();
+    activity.observe(&seen, now);
+    activity.save(&path)?;
+
+    if opts.format.is_structured() && (dry_run || candidates.is_empty()) {
+        output::print_sweep_candidates_as(&candidates, opts.format);
+    } else if !opts.format.is_structured() {
+        output::print_sweep_candidates(&candidates);
+    }
+    if dry_run || candidates.is_empty() {
         return Ok(ExitCode::from(0));
     }
 
-    // Get first socket's process info
-    let socket = &sockets[0];
-    let info = proc::inspect(socket.pid, socket.protocol)?;
+    let targets: Vec<KillTarget> = candidates.into_iter().map(|c| c.target).collect();
+    let handles = kill::pin(&targets);
+    let (targets, refused) = apply_guardrails(targets, opts)?;
+    let targets = if opts.no_prompt {
+        targets
+    } else {
+        prompt::confirm_kill_targets(targets)?
+    };
+
+    signal_targets(&targets, &handles, refused, opts)
+}
+
+/// Write the targets the guardrails allow to a plan file instead of signaling them
+fn write_kill_plan(
+    targets: Vec<KillTarget>,
+    opts: KillOptions,
+    path: &Path,
+    reason: &str,
+) -> Result<ExitCode> {
+    let (targets, _refused) = apply_guardrails(targets, opts)?;
+    let plan = kill::plan(
+        &targets,
+        KillSignal::from_force(opts.force),
+        proc::host_name(),
+        |t| format!("{} on port {}", reason, output::join_ports(&t.ports)),
+    );
+    kill::write_plan(path, &plan)?;
+
+    if opts.format.is_structured() {
+        output::print_kill_plan_json(&plan);
+    } else {
+        output::print_kill_plan(&plan, path);
+    }
+    Ok(ExitCode::from(0))
+}
+
+/// Re-verify a kill plan against the live system and execute what still matches
+fn run_apply(path: &Path, opts: KillOptions) -> Result<ExitCode> {
+    let plan = kill::read_plan(path)?;
+    let host = proc::host_name();
+    if plan.host != host {
+        return Err(PortDetectiveError::Plan {
+            path: path.to_path_buf(),
+            reason: format!("created on host {}, this is {}", plan.host, host),
+        });
+    }
+
+    let live = listing::collect(ProtocolFilter::Both)?;
+    let verified = kill::verify(&plan, &live, proc::identity);
+    if !opts.format.is_structured() {
+        output::print_drift(&verified.drift);
+    }
+
+    let signals: HashMap<u32, KillSignal> = verified
+        .targets
+        .iter()
+        .map(|(t, signal)| (t.process.pid, *signal))
+        .collect();
+    let targets: Vec<KillTarget> = verified.targets.into_iter().map(|(t, _)| t).collect();
+    let handles = kill::pin(&targets);
+
+    let (targets, refused) = if targets.is_empty() {
+        (targets, Vec::new())
+    } else {
+        apply_guardrails(targets, opts)?
+    };
+    if !targets.is_empty() && !opts.no_prompt {
+        prompt::confirm_bulk_kill(targets.len())?;
+    }
+
+    let drained = drain_targets(&targets, opts)?;
+    let ancestry = record_ancestry(&targets, opts);
+    let mut results = Vec::new();
+    for signal in [KillSignal::Term, KillSignal::Kill] {
+        let group: Vec<KillTarget> = targets
+            .iter()
+            .filter(|t| signals.get(&t.process.pid) == Some(&signal))
+            .cloned()
+            .collect();
+        let opts = KillOptions {
+            force: signal.is_force(),
+            ..opts
+        };
+        results.extend(kill::execute(
+            &group,
+            &handles,
+            opts,
+            &manager::CommandStopper,
+        ));
+    }
+    if let Some(counts) = &drained {
+        kill::record_open_connections(&mut results, counts);
+    }
+    watch_respawns(&targets, &ancestry, &mut results, opts)?;
+    results.extend(refused);
+    results.extend(verified.skipped);
+
+    let clean = verified.drift.is_empty() && results.iter().all(|r| r.success);
+    let report = ApplyReport {
+        drift: verified.drift,
+        results,
+    };
+    if opts.format.is_structured() {
+        output::print_apply_report_json(&report);
+    } else {
+        output::print_kill_results(&report.results);
+    }
+
+    if clean {
+        Ok(ExitCode::from(0))
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+/// Drop targets the guardrails refuse, keeping a failed result for each.
+///
+/// Errors out if nothing is left to kill.
+fn apply_guardrails(
+    targets: Vec<KillTarget>,
+    opts: KillOptions,
+) -> Result<(Vec<KillTarget>, Vec<KillResult>)> {
+    if opts.unguarded {
+        return Ok((targets, Vec::new()));
+    }
+
+    let mut guard = guard::Guardrails::new(proc::current_uid());
+    guard
+        .protected_names
+        .extend(config::current().protected.iter().cloned());
+    let (allowed, refused) = guard.partition(targets);
 
-    if !no_prompt {
-        output::print_kill_prompt(socket.port, &info);
+    if allowed.is_empty()
+        && let Some((target, reason)) = refused.first()
+    {
+        return Err(PortDetectiveError::Protected {
+            pid: target.process.pid,
+            reason: reason.clone(),
+        });
+    }
+
+    let refused = refused
+        .iter()
+        .map(|(target, reason)| {
+            if !opts.format.is_structured() {
+                output::print_kill_refused(target, reason);
+            }
+            kill::refused(target, opts.force, reason)
+        })
+        .collect();
+
+    Ok((allowed, refused))
+}
+
+/// Signal the confirmed targets and report the outcome for each PID
+fn signal_targets(
+    targets: &[KillTarget],
+    handles: &HashMap<u32, ProcessHandle>,
+    refused: Vec<KillResult>,
+    opts: KillOptions,
+) -> Result<ExitCode> {
+    let results = kill_targets(targets, handles, refused, opts)?;
+    Ok(kill_exit_code(&results))
+}
+
+/// Exit 0 only if every target was killed; respawns are reported, not failures
+fn kill_exit_code(results: &[KillResult]) -> ExitCode {
+    if results.iter().all(|r| r.success) {
+        ExitCode::from(0)
+    } else {
+        ExitCode::from(1)
+    }
+}
 
-        print!("Are you sure you want to kill PID {}? [y/N]: ", info.pid);
-        io::stdout().flush().unwrap();
+/// Signal the targets and print a result per PID
+fn kill_targets(
+    targets: &[KillTarget],
+    handles: &HashMap<u32, ProcessHandle>,
+    refused: Vec<KillResult>,
+    opts: KillOptions,
+) -> Result<Vec<KillResult>> {
+    let drained = drain_targets(targets, opts)?;
+    let ancestry = record_ancestry(targets, opts);
+    let snapshots: Vec<remember::Remembered> = targets
+        .iter()
+        .filter(|_| opts.remember)
+        .map(|t| {
+            remember::snapshot(
+                &t.process,
+                &t.ports,
+                proc::environ(t.process.pid),
+                opts.remember_secrets,
+            )
+        })
+        .collect();
+    let mut results = kill::execute(targets, handles, opts, &manager::CommandStopper);
+    if let Some(counts) = &drained {
+        kill::record_open_connections(&mut results, counts);
+    }
+    watch_respawns(targets, &ancestry, &mut results, opts)?;
+    remember_killed(snapshots, &results, opts)?;
+    results.extend(refused);
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
+    if opts.format.is_structured() {
+        // With `--and-hold` the results are printed together with the holds
+        if !opts.and_hold {
+            output::print_kill_results_as(&results, opts.format);
+        }
+    } else {
+        output::print_kill_results(&results);
+    }
+    Ok(results)
+}
 
-        if !input.trim().eq_ignore_ascii_case("y") {
-            output::print_kill_cancelled();
-            return Err(PortDetectiveError::Cancelled);
+/// With `--and-hold`, bind the killed targets' sockets again as soon as they
+/// are released and hold them
+fn hold_after_kill(
+    targets: &[KillTarget],
+    sockets: &[(u16, IpAddr, Protocol)],
+    results: Vec<KillResult>,
+    opts: KillOptions,
+) -> Result<ExitCode> {
+    let json = opts.format.is_structured();
+    if results.iter().any(|r| !r.success) {
+        if json {
+            output::print_kill_results_as(&results, opts.format);
         }
+        return Ok(ExitCode::from(1));
     }
 
-    proc::kill_process(info.pid, force)?;
-    output::print_kill_success(info.pid, force);
+    let mut ports: Vec<u16> = targets.iter().flat_map(|t| t.ports.clone()).collect();
+    ports.sort_unstable();
+    ports.dedup();
 
+    let signals = hold::block_signals(ports[0])?;
+    let held = ports
+        .iter()
+        .map(|&port| {
+            let mut bound: Vec<(IpAddr, Protocol)> = sockets
+                .iter()
+                .filter(|(p, _, _)| *p == port)
+                .map(|&(_, addr, protocol)| (addr, protocol))
+                .collect();
+            if bound.is_empty() {
+                bound.push((hold::DEFAULT_ADDR, Protocol::Both));
+            }
+            hold::grab_sockets(port, &bound, hold::GRAB_WINDOW)
+        })
+        .collect::<Result<Vec<_>>>();
+    let held = match held {
+        Ok(held) => held,
+        Err(e) => {
+            if json {
+                output::print_kill_results_as(&results, opts.format);
+            }
+            return Err(e);
+        }
+    };
+
+    let records = hold::record(&held, opts.hold_for)?;
+    if json {
+        output::print_kill_and_hold_json(&results, &records);
+    } else {
+        output::print_holding(&records);
+    }
+    wait_held(held, signals, opts.hold_for, json)
+}
+
+/// Bind ports and keep them until a signal, `release` or the timeout
+fn run_hold(
+    ports: &[u16],
+    addr: IpAddr,
+    protocol: Protocol,
+    timeout: Option<Duration>,
+    json: bool,
+) -> Result<ExitCode> {
+    let signals = hold::block_signals(ports[0])?;
+    let held = ports
+        .iter()
+        .map(|&port| hold::hold(addr, port, protocol))
+        .collect::<Result<Vec<_>>>()?;
+
+    let records = hold::record(&held, timeout)?;
+    if json {
+        output::print_holds_json(&records);
+    } else {
+        output::print_holding(&records);
+    }
+    wait_held(held, signals, timeout, json)
+}
+
+/// Block until the hold ends, letting go of each port `release` takes back
+fn wait_held(
+    mut held: Vec<hold::Held>,
+    signals: hold::Signals,
+    timeout: Option<Duration>,
+    json: bool,
+) -> Result<ExitCode> {
+    let deadline = timeout.and_then(|t| std::time::Instant::now().checked_add(t));
+    let ended = loop {
+        match hold::next_signal(&signals, deadline) {
+            Some(nix::sys::signal::Signal::SIGUSR1) => {
+                let (released, kept): (Vec<_>, Vec<_>) =
+                    held.into_iter().partition(|h| hold::is_released(h.port));
+                held = kept;
+                if !json && !released.is_empty() {
+                    let ports: Vec<u16> = released.iter().map(|h| h.port).collect();
+                    output::print_hold_ended(&ports, hold::Ended::Released);
+                }
+                if held.is_empty() {
+                    break None;
+                }
+            }
+            Some(signal) => break Some(hold::Ended::Signal(signal)),
+            None => break Some(hold::Ended::Timeout),
+        }
+    };
+
+    let ports: Vec<u16> = held.iter().map(|h| h.port).collect();
+    drop(held);
+    hold::forget(&ports);
+    if !json && let Some(ended) = ended {
+        output::print_hold_ended(&ports, ended);
+    }
     Ok(ExitCode::from(0))
 }
 
-/// List all listening ports
-fn run_list(filter: ProtocolFilter, json: bool) -> Result<ExitCode> {
-    let ports_map = net::get_listening_ports(filter)?;
+/// End holds started in other shells
+fn run_release(ports: &[u16], json: bool) -> Result<ExitCode> {
+    let records = ports
+        .iter()
+        .map(|&port| hold::release(port))
+        .collect::<Result<Vec<_>>>()?;
 
-    let mut entries: Vec<PortEntry> = Vec::new();
-    let mut seen: std::collections::HashSet<(u16, u32)> = std::collections::HashSet::new();
+    if json {
+        output::print_holds_json(&records);
+    } else {
+        output::print_released(&records);
+    }
+    Ok(ExitCode::from(0))
+}
 
-    for (port, sockets) in ports_map {
-        for socket in sockets {
-            // Deduplicate by (port, pid)
-            if seen.contains(&(port, socket.pid)) {
-                continue;
+/// Lease ports that are free now and not leased to anyone else
+fn run_lease(request: &LeaseArgs, json: bool) -> Result<ExitCode> {
+    let owner = request
+        .owner
+        .clone()
+        .or_else(proc::current_user)
+        .unwrap_or_else(|| "unknown".to_string());
+    let leases = lease::with_registry(&lease::Registry::path(), |registry| {
+        registry.acquire(
+            request.range.clone(),
+            request.count,
+            &owner,
+            request.ttl,
+            chrono::Local::now(),
+            |port| hold::is_free(port, Protocol::Both),
+        )
+    })?;
+
+    if json {
+        output::print_leases_json(&leases);
+    } else {
+        output::print_leased_ports(&leases);
+    }
+    Ok(ExitCode::from(0))
+}
+
+/// Give leases back by port or owner
+fn run_lease_release(ports: &[u16], owner: Option<&str>, json: bool) -> Result<ExitCode> {
+    let released = lease::with_registry(&lease::Registry::path(), |registry| {
+        registry.prune(chrono::Local::now());
+        Ok(registry.release(ports, owner))
+    })?;
+    if released.is_empty() {
+        let what = match owner {
+            Some(owner) => format!("owner {}", owner),
+            None => format!("port {}", output::join_ports(ports)),
+        };
+        return Err(PortDetectiveError::NotLeased(what));
+    }
+
+    if json {
+        output::print_leases_json(&released);
+    } else {
+        output::print_lease_released(&released);
+    }
+    Ok(ExitCode::from(0))
+}
+
+/// Show the leases currently handed out
+fn run_lease_list(json: bool) -> Result<ExitCode> {
+    let leases = lease::with_registry(&lease::Registry::path(), |registry| {
+        registry.prune(chrono::Local::now());
+        Ok(registry.leases.clone())
+    })?;
+
+    if json {
+        output::print_leases_json(&leases);
+    } else {
+        output::print_lease_list(&leases);
+    }
+    Ok(ExitCode::from(0))
+}
+
+/// With `--drain`, wait for the targets' connections to close and return what is still open
+fn drain_targets(targets: &[KillTarget], opts: KillOptions) -> Result<Option<HashMap<u16, usize>>> {
+    let Some(timeout) = opts.drain else {
+        return Ok(None);
+    };
+    let mut ports: Vec<u16> = targets.iter().flat_map(|t| t.ports.clone()).collect();
+    ports.sort_unstable();
+    ports.dedup();
+    if ports.is_empty() {
+        return Ok(None);
+    }
+
+    let counts = kill::drain(
+        &ports,
+        timeout,
+        kill::DRAIN_POLL_INTERVAL,
+        net::established_connections,
+        |open, waited| {
+            if !opts.format.is_structured() {
+                output::print_drain_progress(&ports, open, waited, timeout);
             }
-            if let Ok(info) = proc::inspect(socket.pid, socket.protocol) {
-                seen.insert((port, socket.pid));
-                let cmd = if info.command.is_empty() {
-                    info.name.clone()
-                } else {
-                    info.command.join(" ")
-                };
-
-                entries.push(PortEntry {
-                    port,
-                    protocol: socket.protocol,
-                    pid: socket.pid,
-                    name: info.name,
-                    user: info.user,
-                    command: cmd,
+        },
+    )?;
+    if !opts.format.is_structured() {
+        output::print_drain_done(counts.values().sum());
+    }
+    Ok(Some(counts))
+}
+
+/// Save the snapshots of targets that were actually killed
+fn remember_killed(
+    snapshots: Vec<remember::Remembered>,
+    results: &[KillResult],
+    opts: KillOptions,
+) -> Result<()> {
+    if snapshots.is_empty() {
+        return Ok(());
+    }
+    let path = remember::Store::path();
+    let mut store = remember::Store::load(&path)?;
+    let mut saved = Vec::new();
+    for (snapshot, result) in snapshots.into_iter().zip(results) {
+        if result.success {
+            let id = store.add(snapshot);
+            saved.extend(store.entries.iter().find(|e| e.id == id).cloned());
+        }
+    }
+    store.save(&path)?;
+
+    if !opts.format.is_structured() {
+        for entry in &saved {
+            output::print_remembered(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Relaunch a remembered process, or list them when no target is given
+fn run_restart(target: Option<u32>, json: bool) -> Result<ExitCode> {
+    let path = remember::Store::path();
+    let mut store = remember::Store::load(&path)?;
+
+    let Some(target) = target else {
+        if json {
+            output::print_remembered_list_json(&store.entries);
+        } else {
+            output::print_remembered_list(&store.entries);
+        }
+        return Ok(ExitCode::from(0));
+    };
+
+    let entry = store
+        .find(target)
+        .cloned()
+        .ok_or(PortDetectiveError::NotRemembered(target))?;
+    let failed = |reason: String| PortDetectiveError::RestartFailed {
+        name: entry.name.clone(),
+        reason,
+    };
+
+    let user = proc::current_user().unwrap_or_else(|| "unknown".to_string());
+    if entry.user != user {
+        return Err(failed(format!(
+            "it ran as {}; run restart as that user",
+            entry.user
+        )));
+    }
+    if let Some(busy) = listing::collect(ProtocolFilter::Both)?
+        .into_iter()
+        .find(|l| entry.ports.contains(&l.port))
+    {
+        return Err(failed(format!(
+            "port {} is in use by {} (PID {})",
+            busy.port, busy.process.name, busy.process.pid
+        )));
+    }
+
+    let (pid, log) = remember::relaunch(&entry)?;
+    store.remove(entry.id);
+    store.save(&path)?;
+
+    if json {
+        output::print_restarted_json(&entry, pid, &log);
+    } else {
+        output::print_restarted(&entry, pid, &log);
+    }
+    Ok(ExitCode::from(0))
+}
+
+/// Parent chains of the targets, taken before they are signaled and reparented
+fn record_ancestry(targets: &[KillTarget], opts: KillOptions) -> HashMap<u32, Vec<(u32, String)>> {
+    if opts.respawn_window.is_zero() {
+        return HashMap::new();
+    }
+    targets
+        .iter()
+        .map(|t| (t.process.pid, proc::ancestors(t.process.pid)))
+        .collect()
+}
+
+/// Watch the ports of successfully killed targets and attach any respawns
+fn watch_respawns(
+    targets: &[KillTarget],
+    ancestry: &HashMap<u32, Vec<(u32, String)>>,
+    results: &mut [KillResult],
+    opts: KillOptions,
+) -> Result<()> {
+    if opts.respawn_window.is_zero() {
+        return Ok(());
+    }
+    let killed: Vec<KillTarget> = targets
+        .iter()
+        .filter(|t| results.iter().any(|r| r.pid == t.process.pid && r.success))
+        .cloned()
+        .collect();
+
+    let respawns = kill::watch_respawns(
+        &killed,
+        ancestry,
+        opts.respawn_window,
+        kill::RESPAWN_POLL_INTERVAL,
+        || listing::collect(ProtocolFilter::Both),
+        proc::ancestors,
+    )?;
+    kill::record_respawns(results, respawns);
+    Ok(())
+}
+
+/// Turn selector flags into a `Selector`, resolving `me` and canonicalizing paths
+fn build_selector(args: &SelectorArgs) -> Selector {
+    let user = match args.user.as_deref() {
+        Some("me") => Some(proc::current_user().unwrap_or_else(|| "unknown".to_string())),
+        other => other.map(str::to_string),
+    };
+    let cwd_under = args
+        .cwd_under
+        .as_ref()
+        .map(|p| std::fs::canonicalize(p).unwrap_or_else(|_| p.clone()));
+
+    Selector {
+        name: args.name.clone(),
+        user,
+        cwd_under,
+        older_than: args.older_than,
+    }
+}
+
+/// List all listening ports
+fn run_list(filter: ProtocolFilter, format: OutputFormat) -> Result<ExitCode> {
+    let entries = listing::entries(filter)?;
+
+    if format.is_structured() {
+        output::print_port_list_as(&entries, format);
+    } else {
+        output::print_port_list(&entries, config::current());
+    }
+
+    Ok(ExitCode::from(0))
+}
+
+/// Print listening socket events as JSON lines until interrupted
+fn run_monitor(interval: Duration, existing: bool, filter: ProtocolFilter) -> Result<ExitCode> {
+    let mut monitor = monitor::Monitor::default();
+    let mut announce = existing;
+    loop {
+        let events = monitor.update(
+            net::listening_bindings(filter)?,
+            chrono::Local::now(),
+            announce,
+            net::attach_owners,
+            |pid, protocol| proc::inspect(pid, protocol).ok(),
+        );
+        for event in &events {
+            output::print_port_event(event);
+        }
+        announce = true;
+        std::thread::sleep(interval);
+    }
+}
+
+/// Fire configured hooks on port events until interrupted
+fn run_hooks(
+    config: Option<&Path>,
+    interval: Duration,
+    filter: ProtocolFilter,
+) -> Result<ExitCode> {
+    let path = config
+        .map(Path::to_path_buf)
+        .unwrap_or_else(hooks::default_path);
+    let hooks = hooks::load(&path)?;
+    output::print_hooks_loaded(&hooks, &path);
+
+    let mut dispatcher = hooks::Dispatcher::new(hooks);
+    let mut monitor = monitor::Monitor::default();
+    let mut announce = false;
+    loop {
+        let events = monitor.update(
+            net::listening_bindings(filter)?,
+            chrono::Local::now(),
+            announce,
+            net::attach_owners,
+            |pid, protocol| proc::inspect(pid, protocol).ok(),
+        );
+        for event in events {
+            for hook in dispatcher.due(&event, std::time::Instant::now()) {
+                let event = event.clone();
+                std::thread::spawn(move || {
+                    output::print_hook_fired(&hook, &event, hooks::fire(&hook, &event));
                 });
             }
         }
+        announce = true;
+        std::thread::sleep(interval);
+    }
+}
+
+/// Check the live listeners against a policy; exits 1 on any violation
+fn run_audit(policy: Option<&Path>, filter: ProtocolFilter, json: bool) -> Result<ExitCode> {
+    let path = policy
+        .map(Path::to_path_buf)
+        .unwrap_or_else(policy::default_path);
+    let expected = policy::load(&path)?;
+    let listeners = listing::collect_all(filter)?;
+    warn_unknown_owners(&listeners);
+    let violations = policy::audit(&expected, &listeners, filter);
+
+    if json {
+        output::print_audit_json(&violations);
+    } else {
+        output::print_audit(&violations, &path, expected.len());
+    }
+
+    if violations.is_empty() {
+        Ok(ExitCode::from(0))
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+/// Warn that some listeners' owners are shown as `unknown` because we are not root
+fn warn_unknown_owners(listeners: &[Listener]) {
+    let unknown = listeners.iter().filter(|l| l.process.is_unknown()).count();
+    if unknown > 0 && !proc::is_root() {
+        output::print_warning(&format!(
+            "{} listener(s) belong to processes you cannot inspect and are shown as unknown; run as root to see their owners",
+            unknown
+        ));
+    }
+}
+
+/// Like `warn_unknown_owners`, for port list rows
+fn warn_unknown_entries(entries: &[PortEntry]) {
+    if !proc::is_root() && entries.iter().any(PortEntry::is_unknown) {
+        output::print_warning(
+            "some ports are held by processes you cannot inspect and are shown as unknown; run as root to see their owners",
+        );
+    }
+}
+
+/// Rank the listeners reachable from outside loopback
+fn run_exposure(filter: ProtocolFilter, json: bool) -> Result<ExitCode> {
+    let listeners = listing::collect_all(filter)?;
+    warn_unknown_owners(&listeners);
+    let exposures = exposure::assess(&listeners, &net::interface_addresses()?);
+    if json {
+        output::print_exposure_json(&exposures);
+    } else {
+        output::print_exposure(&exposures);
+    }
+    Ok(ExitCode::from(0))
+}
+
+/// Check the ports a repo declares against the live port list; exits 1 if
+/// any is already taken
+fn run_project(dir: Option<&Path>, filter: ProtocolFilter, json: bool) -> Result<ExitCode> {
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir().map_err(|e| PortDetectiveError::Project {
+            path: ".".into(),
+            reason: e.to_string(),
+        })?,
+    };
+    let root = project::root(&dir);
+    let entries = listing::all_entries(filter)?;
+    warn_unknown_entries(&entries);
+    let findings = project::check(project::scan(&root)?, &entries);
+
+    if json {
+        output::print_project_json(&findings);
+    } else {
+        output::print_project(&findings, &root);
+    }
+
+    if findings.iter().any(|f| f.taken) {
+        Ok(ExitCode::from(1))
+    } else {
+        Ok(ExitCode::from(0))
     }
+}
 
-    // Sort by port number
-    entries.sort_by_key(|e| e.port);
+/// Show each named port as up or down; exits 1 if any is down
+fn run_status(filter: ProtocolFilter, json: bool) -> Result<ExitCode> {
+    let ports = &config::current().ports;
+    if ports.is_empty() {
+        return Err(PortDetectiveError::NoNamedPorts);
+    }
 
+    let entries = listing::all_entries(filter)?;
+    warn_unknown_entries(&entries);
+    let services = status::status(ports, &entries);
     if json {
-        output::print_port_list_json(&entries);
+        output::print_status_json(&services);
     } else {
-        output::print_port_list(&entries);
+        output::print_status(&services);
     }
 
+    if services.iter().all(|s| s.up) {
+        Ok(ExitCode::from(0))
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+/// Print the current listeners as a snapshot
+fn run_snapshot(filter: ProtocolFilter) -> Result<ExitCode> {
+    let snapshot = snapshot::Snapshot::new(listing::entries(filter)?, proc::host_name());
+    output::print_snapshot(&snapshot);
     Ok(ExitCode::from(0))
 }
+
+/// Show what changed between a snapshot and a later one or the live list;
+/// exits 1 if anything did, like `diff`
+fn run_diff(
+    before: &Path,
+    after: Option<&Path>,
+    filter: ProtocolFilter,
+    json: bool,
+) -> Result<ExitCode> {
+    let before = snapshot::Snapshot::read(before)?;
+    let after = match after {
+        Some(path) if path != Path::new("live") => snapshot::Snapshot::read(path)?,
+        _ => snapshot::Snapshot::new(listing::entries(filter)?, proc::host_name()),
+    };
+
+    let diff = snapshot::diff(&before.listeners, &after.listeners);
+    if json {
+        output::print_snapshot_diff_json(&diff);
+    } else {
+        output::print_snapshot_diff(&before, &after, &diff);
+    }
+
+    if diff.is_empty() {
+        Ok(ExitCode::from(0))
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+/// Kill the one process picked in `watch`, with the usual guardrails and prompt
+fn kill_watched(port: u16, pid: u32, filter: ProtocolFilter) -> Result<ExitCode> {
+    let targets: Vec<KillTarget> =
+        kill::group_by_pid(kill::select(&[port], &Selector::default(), filter)?)
+            .into_iter()
+            .filter(|t| t.process.pid == pid)
+            .collect();
+    if targets.is_empty() {
+        return Err(PortDetectiveError::ProcessNotFound(pid));
+    }
+
+    let opts = KillOptions::default();
+    let handles = kill::pin(&targets);
+    let (targets, refused) = apply_guardrails(targets, opts)?;
+    let targets = prompt::confirm_kill_targets(targets)?;
+    let results = kill_targets(&targets, &handles, refused, opts)?;
+    Ok(kill_exit_code(&results))
+}
+
+/// Refresh the port table until interrupted, full-screen on a terminal
+fn run_watch(interval: Duration, filter: ProtocolFilter, json: bool) -> Result<ExitCode> {
+    if std::io::stdout().is_terminal() && !json {
+        watch::run(
+            interval,
+            || listing::entries(filter),
+            |port, pid| {
+                if let Err(e) = kill_watched(port, pid, filter) {
+                    output::print_error(&e.to_string());
+                }
+            },
+        )?;
+        return Ok(ExitCode::from(0));
+    }
+
+    let mut dash = watch::Dashboard::default();
+    loop {
+        let entries = listing::entries(filter)?;
+        dash.refresh(entries.clone());
+        if json {
+            output::print_port_list_json_line(&entries);
+        } else {
+            output::print_port_changes(&dash.added(), dash.removed());
+            output::print_port_list(&entries, config::current());
+        }
+        std::thread::sleep(interval);
+    }
+}
@@ -0,0 +1,435 @@
+//! Service manager detection and manager-aware stopping
+
+use crate::error::{PortDetectiveError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Something that supervises a process and restarts it when it dies
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ServiceManager {
+    /// A systemd unit, system-wide or in the user's instance
+    Systemd { unit: String, user: bool },
+    /// A Docker or Podman container
+    Container { runtime: String, id: String },
+    /// A pm2-managed app
+    Pm2 { name: String },
+    /// A supervisord program (unknown if only seen as an ancestor)
+    Supervisord {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        program: Option<String>,
+    },
+    /// A Procfile runner such as foreman or overmind
+    Procfile {
+        tool: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        process: Option<String>,
+    },
+    /// A Homebrew service started through launchd
+    Brew { formula: String },
+}
+
+impl ServiceManager {
+    /// Short description, e.g. `systemd unit nginx.service`
+    pub fn label(&self) -> String {
+        match self {
+            ServiceManager::Systemd { unit, user: false } => format!("systemd unit {}", unit),
+            ServiceManager::Systemd { unit, user: true } => format!("systemd user unit {}", unit),
+            ServiceManager::Container { runtime, id } => format!("{} container {}", runtime, id),
+            ServiceManager::Pm2 { name } => format!("pm2 app {}", name),
+            ServiceManager::Supervisord { program: Some(p) } => {
+                format!("supervisord program {}", p)
+            }
+            ServiceManager::Supervisord { program: None } => "supervisord".to_string(),
+            ServiceManager::Procfile {
+                tool,
+                process: Some(p),
+            } => format!("{} process {}", tool, p),
+            ServiceManager::Procfile {
+                tool,
+                process: None,
+            } => tool.clone(),
+            ServiceManager::Brew { formula } => format!("brew service {}", formula),
+        }
+    }
+
+    /// Command that stops the service without it being restarted.
+    ///
+    /// `None` when the manager has no way to stop just this process (foreman,
+    /// or a supervisord program we could not identify).
+    pub fn stop_command(&self) -> Option<Vec<String>> {
+        let parts: Vec<&str> = match self {
+            ServiceManager::Systemd { unit, user: false } => vec!["systemctl", "stop", unit],
+            ServiceManager::Systemd { unit, user: true } => {
+                vec!["systemctl", "--user", "stop", unit]
+            }
+            ServiceManager::Container { runtime, id } => vec![runtime, "stop", id],
+            ServiceManager::Pm2 { name } => vec!["pm2", "stop", name],
+            ServiceManager::Supervisord { program: Some(p) } => vec!["supervisorctl", "stop", p],
+            ServiceManager::Procfile {
+                tool,
+                process: Some(p),
+            } if tool == "overmind" => vec!["overmind", "stop", p],
+            ServiceManager::Brew { formula } => vec!["brew", "services", "stop", formula],
+            ServiceManager::Supervisord { program: None } | ServiceManager::Procfile { .. } => {
+                return None;
+            }
+        };
+        Some(parts.into_iter().map(str::to_string).collect())
+    }
+}
+
+/// Detect the manager of a process from what the OS tells us about it.
+///
+/// The environment is checked first because it is the most specific signal:
+/// a pm2 app still lives inside the `pm2-<user>.service` cgroup.
+pub fn detect(
+    environ: &HashMap<String, String>,
+    cgroup: Option<&str>,
+    ancestors: &[String],
+) -> Option<ServiceManager> {
+    from_environ(environ)
+        .or_else(|| cgroup.and_then(from_cgroup))
+        .or_else(|| from_ancestors(ancestors))
+}
+
+fn from_environ(env: &HashMap<String, String>) -> Option<ServiceManager> {
+    if env.contains_key("pm_id") {
+        let name = env.get("name").or_else(|| env.get("pm_id"))?;
+        return Some(ServiceManager::Pm2 { name: name.clone() });
+    }
+
+    if let Some(program) = env.get("SUPERVISOR_PROCESS_NAME") {
+        return Some(ServiceManager::Supervisord {
+            program: Some(
+                env.get("SUPERVISOR_GROUP_NAME")
+                    .filter(|group| *group != program)
+                    .map(|group| format!("{}:{}", group, program))
+                    .unwrap_or_else(|| program.clone()),
+            ),
+        });
+    }
+
+    if let Some(process) = env.get("OVERMIND_PROCESS_NAME") {
+        return Some(ServiceManager::Procfile {
+            tool: "overmind".to_string(),
+            process: Some(process.clone()),
+        });
+    }
+
+    // foreman exports PS=<process>.<index>, e.g. web.1
+    if let Some(ps) = env.get("PS")
+        && let Some((process, index)) = ps.rsplit_once('.')
+        && index.chars().all(|c| c.is_ascii_digit())
+    {
+        return Some(ServiceManager::Procfile {
+            tool: "foreman".to_string(),
+            process: Some(process.to_string()),
+        });
+    }
+
+    // launchd labels Homebrew services homebrew.mxcl.<formula>
+    if let Some(formula) = env
+        .get("XPC_SERVICE_NAME")
+        .and_then(|label| label.strip_prefix("homebrew.mxcl."))
+    {
+        return Some(ServiceManager::Brew {
+            formula: formula.to_string(),
+        });
+    }
+
+    None
+}
+
+/// Parse `/proc/<pid>/cgroup`, preferring the unified (v2) hierarchy
+fn from_cgroup(content: &str) -> Option<ServiceManager> {
+    let path = content
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .or_else(|| {
+            content
+                .lines()
+                .find(|line| line.contains(":name=systemd:"))
+                .and_then(|line| line.splitn(3, ':').nth(2))
+        })?;
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    // Containers: docker-<id>.scope / libpod-<id>.scope (systemd driver),
+    // /docker/<id> (cgroupfs driver)
+    for (i, segment) in segments.iter().enumerate() {
+        for (prefix, runtime) in [("docker-", "docker"), ("libpod-", "podman")] {
+            if let Some(id) = segment
+                .strip_prefix(prefix)
+                .and_then(|s| s.strip_suffix(".scope"))
+                && is_container_id(id)
+            {
+                return Some(container(runtime, id));
+            }
+        }
+        if *segment == "docker"
+            && let Some(id) = segments.get(i + 1)
+            && is_container_id(id)
+        {
+            return Some(container("docker", id));
+        }
+    }
+
+    // The innermost .service is the unit; user@<uid>.service is only the
+    // user manager itself, not something to stop
+    let user = segments.iter().any(|s| s.starts_with("user@"));
+    segments
+        .iter()
+        .rev()
+        .find(|s| s.ends_with(".service") && !s.starts_with("user@"))
+        .map(|unit| ServiceManager::Systemd {
+            unit: unit.to_string(),
+            user,
+        })
+}
+
+/// Last resort when the environment is unreadable: look for a supervisor
+/// among the parents
+fn from_ancestors(ancestors: &[String]) -> Option<ServiceManager> {
+    ancestors.iter().find_map(|name| {
+        if name.starts_with("supervisord") {
+            Some(ServiceManager::Supervisord { program: None })
+        } else {
+            ["foreman", "overmind"]
+                .into_iter()
+                .find(|tool| name.starts_with(tool))
+                .map(|tool| ServiceManager::Procfile {
+                    tool: tool.to_string(),
+                    process: None,
+                })
+        }
+    })
+}
+
+fn is_container_id(id: &str) -> bool {
+    id.len() >= 12 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn container(runtime: &str, id: &str) -> ServiceManager {
+    ServiceManager::Container {
+        runtime: runtime.to_string(),
+        id: id[..12].to_string(),
+    }
+}
+
+/// Something that can stop a managed service
+pub trait Stopper {
+    fn stop(&self, manager: &ServiceManager) -> Result<()>;
+}
+
+/// Stops services by running the manager's own CLI
+pub struct CommandStopper;
+
+impl Stopper for CommandStopper {
+    fn stop(&self, manager: &ServiceManager) -> Result<()> {
+        let argv = manager
+            .stop_command()
+            .ok_or_else(|| PortDetectiveError::StopFailed {
+                command: manager.label(),
+                reason: "no stop command for this manager".to_string(),
+            })?;
+        let failed = |reason: String| PortDetectiveError::StopFailed {
+            command: argv.join(" "),
+            reason,
+        };
+
+        let output = Command::new(&argv[0])
+            .args(&argv[1..])
+            .output()
+            .map_err(|e| failed(e.to_string()))?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            Err(failed(if stderr.is_empty() {
+                output.status.to_string()
+            } else {
+                stderr
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_systemd_system_unit() {
+        let manager = from_cgroup("0::/system.slice/nginx.service\n").unwrap();
+        assert_eq!(
+            manager,
+            ServiceManager::Systemd {
+                unit: "nginx.service".to_string(),
+                user: false
+            }
+        );
+        assert_eq!(
+            manager.stop_command().unwrap(),
+            ["systemctl", "stop", "nginx.service"]
+        );
+    }
+
+    #[test]
+    fn test_systemd_user_unit() {
+        let cgroup = "0::/user.slice/user-1000.slice/user@1000.service/app.slice/jupyter.service";
+        let manager = from_cgroup(cgroup).unwrap();
+        assert_eq!(
+            manager.stop_command().unwrap(),
+            ["systemctl", "--user", "stop", "jupyter.service"]
+        );
+    }
+
+    #[test]
+    fn test_terminal_session_is_unmanaged() {
+        assert_eq!(
+            from_cgroup("0::/user.slice/user-1000.slice/session-2.scope"),
+            None
+        );
+        assert_eq!(
+            from_cgroup(
+                "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-gnome-terminal.scope"
+            ),
+            None
+        );
+        assert_eq!(from_cgroup("0::/"), None);
+    }
+
+    #[test]
+    fn test_cgroup_v1_systemd_hierarchy() {
+        let cgroup =
+            "12:memory:/system.slice/redis.service\n1:name=systemd:/system.slice/redis.service\n";
+        assert_eq!(
+            from_cgroup(cgroup),
+            Some(ServiceManager::Systemd {
+                unit: "redis.service".to_string(),
+                user: false
+            })
+        );
+    }
+
+    #[test]
+    fn test_docker_and_podman_containers() {
+        let id = "3f4e8a9b1c2d3f4e8a9b1c2d3f4e8a9b1c2d3f4e8a9b1c2d3f4e8a9b1c2d3f4e";
+        let systemd_driver = format!("0::/system.slice/docker-{}.scope", id);
+        assert_eq!(
+            from_cgroup(&systemd_driver)
+                .unwrap()
+                .stop_command()
+                .unwrap(),
+            ["docker", "stop", "3f4e8a9b1c2d"]
+        );
+
+        let cgroupfs_driver = format!("0::/docker/{}", id);
+        assert!(matches!(
+            from_cgroup(&cgroupfs_driver),
+            Some(ServiceManager::Container { .. })
+        ));
+
+        let podman = format!("0::/machine.slice/libpod-{}.scope/container", id);
+        assert_eq!(
+            from_cgroup(&podman).unwrap().stop_command().unwrap(),
+            ["podman", "stop", "3f4e8a9b1c2d"]
+        );
+    }
+
+    #[test]
+    fn test_pm2_wins_over_its_systemd_unit() {
+        let manager = detect(
+            &env(&[("pm_id", "0"), ("name", "api")]),
+            Some("0::/system.slice/pm2-dev.service"),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(manager.stop_command().unwrap(), ["pm2", "stop", "api"]);
+    }
+
+    #[test]
+    fn test_supervisord_from_environment() {
+        let manager = from_environ(&env(&[
+            ("SUPERVISOR_ENABLED", "1"),
+            ("SUPERVISOR_PROCESS_NAME", "web_0"),
+            ("SUPERVISOR_GROUP_NAME", "web"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            manager.stop_command().unwrap(),
+            ["supervisorctl", "stop", "web:web_0"]
+        );
+    }
+
+    #[test]
+    fn test_procfile_runners() {
+        let overmind = from_environ(&env(&[("OVERMIND_PROCESS_NAME", "web")])).unwrap();
+        assert_eq!(
+            overmind.stop_command().unwrap(),
+            ["overmind", "stop", "web"]
+        );
+
+        let foreman = from_environ(&env(&[("PS", "worker.1")])).unwrap();
+        assert_eq!(
+            foreman,
+            ServiceManager::Procfile {
+                tool: "foreman".to_string(),
+                process: Some("worker".to_string())
+            }
+        );
+        // foreman cannot stop a single process
+        assert_eq!(foreman.stop_command(), None);
+
+        // A prompt string in PS is not foreman
+        assert_eq!(from_environ(&env(&[("PS", "$ ")])), None);
+    }
+
+    #[test]
+    fn test_brew_service() {
+        let manager =
+            from_environ(&env(&[("XPC_SERVICE_NAME", "homebrew.mxcl.postgresql@14")])).unwrap();
+        assert_eq!(
+            manager.stop_command().unwrap(),
+            ["brew", "services", "stop", "postgresql@14"]
+        );
+    }
+
+    #[test]
+    fn test_ancestor_fallback() {
+        let ancestors = vec!["bash".to_string(), "supervisord".to_string()];
+        let manager = detect(&HashMap::new(), None, &ancestors).unwrap();
+        assert_eq!(manager, ServiceManager::Supervisord { program: None });
+        // Without the program name, stopping would hit every program
+        assert_eq!(manager.stop_command(), None);
+        assert_eq!(detect(&HashMap::new(), None, &["zsh".to_string()]), None);
+    }
+
+    #[test]
+    fn test_label() {
+        let manager = ServiceManager::Container {
+            runtime: "docker".to_string(),
+            id: "3f4e8a9b1c2d".to_string(),
+        };
+        assert_eq!(manager.label(), "docker container 3f4e8a9b1c2d");
+    }
+
+    #[test]
+    fn test_serialization_is_tagged() {
+        let manager = ServiceManager::Pm2 {
+            name: "api".to_string(),
+        };
+        let json = serde_json::to_string(&manager).unwrap();
+        assert_eq!(json, r#"{"kind":"pm2","name":"api"}"#);
+    }
+}
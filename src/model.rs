@@ -1,29 +1,110 @@
 //! Data models for Port Detective
 
+use crate::manager::ServiceManager;
 use chrono::{DateTime, Local};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 /// Information about a process bound to a port
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
     pub user: String,
+    /// Numeric owner, when it could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
     pub command: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cwd: Option<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub exe: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_pid: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub started: Option<DateTime<Local>>,
+    /// Supervisor that would restart the process if it were killed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manager: Option<ServiceManager>,
     pub protocol: Protocol,
 }
 
+impl ProcessInfo {
+    /// Stand-in owner of a socket whose process cannot be inspected, usually
+    /// another user's when not running as root. PID 0 is never a userspace process.
+    pub fn unknown(protocol: Protocol) -> Self {
+        ProcessInfo {
+            pid: 0,
+            name: "unknown".to_string(),
+            user: "unknown".to_string(),
+            uid: None,
+            command: Vec::new(),
+            cwd: None,
+            exe: None,
+            parent_pid: None,
+            parent_name: None,
+            started: None,
+            manager: None,
+            protocol,
+        }
+    }
+
+    /// Whether this is the `unknown` stand-in rather than a real process
+    pub fn is_unknown(&self) -> bool {
+        self.pid == 0
+    }
+
+    /// The facts that tell this process apart from a later one reusing its PID
+    pub fn identity(&self) -> ProcessIdentity {
+        ProcessIdentity {
+            pid: self.pid,
+            started: self.started,
+            exe: self.exe.clone(),
+        }
+    }
+}
+
+/// PID plus the details that change when the PID is recycled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessIdentity {
+    pub pid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started: Option<DateTime<Local>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exe: Option<PathBuf>,
+}
+
+impl ProcessIdentity {
+    /// Describe how `current` differs from this identity, if it does.
+    ///
+    /// Details missing on either side are not compared.
+    pub fn mismatch(&self, current: &ProcessIdentity) -> Option<String> {
+        if let (Some(was), Some(now)) = (&self.started, &current.started)
+            && was != now
+        {
+            return Some(format!(
+                "start time changed from {} to {}",
+                was.format("%Y-%m-%d %H:%M:%S"),
+                now.format("%Y-%m-%d %H:%M:%S")
+            ));
+        }
+        if let (Some(was), Some(now)) = (&self.exe, &current.exe)
+            && was != now
+        {
+            return Some(format!(
+                "executable changed from {} to {}",
+                was.display(),
+                now.display()
+            ));
+        }
+        None
+    }
+}
+
 /// Report about a port's status
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortReport {
     pub port: u16,
     pub protocol: Protocol,
@@ -52,7 +133,7 @@ impl PortReport {
 }
 
 /// Whether a port is in use
-#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum PortStatus {
     Free,
@@ -60,7 +141,7 @@ pub enum PortStatus {
 }
 
 /// Network protocol
-#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Protocol {
     Tcp,
@@ -79,7 +160,7 @@ impl std::fmt::Display for Protocol {
 }
 
 /// Entry in the port list
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PortEntry {
     pub port: u16,
     pub protocol: Protocol,
@@ -87,6 +168,119 @@ pub struct PortEntry {
     pub name: String,
     pub user: String,
     pub command: String,
+    /// Bind addresses, comma-separated when the process listens on several
+    #[serde(default)]
+    pub address: String,
+}
+
+impl PortEntry {
+    /// What stays the same when a listener is restarted or rebound
+    pub fn key(&self) -> (u16, Protocol) {
+        (self.port, self.protocol)
+    }
+
+    /// Whether the owner could not be inspected; see `ProcessInfo::unknown`
+    pub fn is_unknown(&self) -> bool {
+        self.pid == 0
+    }
+}
+
+/// A process selected to be signaled, along with the requested ports it holds
+#[derive(Debug, Clone, Serialize)]
+pub struct KillTarget {
+    pub ports: Vec<u16>,
+    pub process: ProcessInfo,
+}
+
+/// Outcome of signaling a single process
+#[derive(Debug, Clone, Serialize)]
+pub struct KillResult {
+    pub pid: u32,
+    pub name: String,
+    pub ports: Vec<u16>,
+    pub signal: String,
+    /// Manager stop command run instead of sending the signal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via: Option<String>,
+    /// Connections still established when the signal went out, with `--drain`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_connections: Option<usize>,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// New owners that bound the ports again after the kill
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub respawns: Vec<Respawn>,
+}
+
+/// A process that bound a killed process's port again shortly afterwards
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Respawn {
+    pub port: u16,
+    pub pid: u32,
+    pub name: String,
+    /// Manager or surviving ancestor that most likely restarted it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub respawner: Option<String>,
+}
+
+/// Signal a kill sends
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum KillSignal {
+    #[serde(rename = "SIGTERM")]
+    Term,
+    #[serde(rename = "SIGKILL")]
+    Kill,
+}
+
+impl KillSignal {
+    pub fn from_force(force: bool) -> Self {
+        if force {
+            KillSignal::Kill
+        } else {
+            KillSignal::Term
+        }
+    }
+
+    pub fn is_force(self) -> bool {
+        self == KillSignal::Kill
+    }
+}
+
+impl std::fmt::Display for KillSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KillSignal::Term => write!(f, "SIGTERM"),
+            KillSignal::Kill => write!(f, "SIGKILL"),
+        }
+    }
+}
+
+/// A reviewed set of kills, written by `kill --plan` and run by `kill --apply`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillPlan {
+    pub created: DateTime<Local>,
+    pub host: String,
+    pub targets: Vec<PlannedKill>,
+}
+
+/// One process in a kill plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedKill {
+    pub identity: ProcessIdentity,
+    pub ports: Vec<u16>,
+    pub signal: KillSignal,
+    pub reason: String,
+    /// The process as it was when the plan was made, for review
+    pub process: ProcessInfo,
+}
+
+/// Outcome of applying a kill plan
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplyReport {
+    /// Differences between the plan and the live system
+    pub drift: Vec<String>,
+    pub results: Vec<KillResult>,
 }
 
 #[cfg(test)]
@@ -108,11 +302,14 @@ mod tests {
             pid: 1234,
             name: "node".to_string(),
             user: "dev".to_string(),
+            uid: None,
             command: vec!["node".to_string(), "server.js".to_string()],
             cwd: None,
+            exe: None,
             parent_pid: None,
             parent_name: None,
             started: None,
+            manager: None,
             protocol: Protocol::Tcp,
         };
 
@@ -146,11 +343,14 @@ mod tests {
             pid: 100,
             name: "test".to_string(),
             user: "root".to_string(),
+            uid: None,
             command: vec![],
             cwd: None,
+            exe: None,
             parent_pid: None,
             parent_name: None,
             started: None,
+            manager: None,
             protocol: Protocol::Udp,
         };
 
@@ -161,6 +361,8 @@ mod tests {
         assert!(!json.contains("parent_pid"));
         assert!(!json.contains("parent_name"));
         assert!(!json.contains("started"));
+        assert!(!json.contains("manager"));
+        assert!(!json.contains("exe"));
 
         // Required fields should always appear
         assert!(json.contains("\"pid\":100"));
@@ -174,11 +376,14 @@ mod tests {
             pid: 200,
             name: "nginx".to_string(),
             user: "www".to_string(),
+            uid: None,
             command: vec!["nginx".to_string(), "-g".to_string()],
             cwd: Some(std::path::PathBuf::from("/var/www")),
+            exe: None,
             parent_pid: Some(1),
             parent_name: Some("systemd".to_string()),
             started: None,
+            manager: None,
             protocol: Protocol::Tcp,
         };
 
@@ -198,10 +403,140 @@ mod tests {
             name: "sshd".to_string(),
             user: "root".to_string(),
             command: "/usr/sbin/sshd -D".to_string(),
+            address: "0.0.0.0".to_string(),
         };
 
         let json = serde_json::to_string(&entry).unwrap();
         assert!(json.contains("\"port\":22"));
         assert!(json.contains("\"name\":\"sshd\""));
+
+        let back: PortEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, entry);
+        assert_eq!(back.key(), (22, Protocol::Tcp));
+    }
+
+    #[test]
+    fn test_port_report_round_trip() {
+        let report = PortReport::free(8080, Protocol::Tcp);
+        let json = serde_json::to_string(&report).unwrap();
+        let back: PortReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.status, PortStatus::Free);
+        assert_eq!(back.port, 8080);
+    }
+
+    #[test]
+    fn test_kill_result_serialization() {
+        let ok = KillResult {
+            pid: 42,
+            name: "node".to_string(),
+            ports: vec![3000, 3001],
+            signal: "SIGTERM".to_string(),
+            via: None,
+            open_connections: None,
+            success: true,
+            error: None,
+            respawns: Vec::new(),
+        };
+        let json = serde_json::to_string(&ok).unwrap();
+        assert!(json.contains("\"ports\":[3000,3001]"));
+        assert!(json.contains("\"success\":true"));
+        assert!(!json.contains("error"));
+        assert!(!json.contains("via"));
+
+        let failed = KillResult {
+            success: false,
+            error: Some("Permission denied".to_string()),
+            ..ok
+        };
+        let json = serde_json::to_string(&failed).unwrap();
+        assert!(json.contains("\"error\":\"Permission denied\""));
+    }
+
+    #[test]
+    fn test_identity_mismatch() {
+        use chrono::TimeZone;
+
+        let started = Local.timestamp_opt(1_700_000_000, 0).single();
+        let identity = ProcessIdentity {
+            pid: 42,
+            started,
+            exe: Some(PathBuf::from("/usr/bin/node")),
+        };
+        assert_eq!(identity.mismatch(&identity.clone()), None);
+
+        let restarted = ProcessIdentity {
+            started: Local.timestamp_opt(1_700_000_100, 0).single(),
+            ..identity.clone()
+        };
+        assert!(
+            identity
+                .mismatch(&restarted)
+                .unwrap()
+                .starts_with("start time changed")
+        );
+
+        let other_binary = ProcessIdentity {
+            exe: Some(PathBuf::from("/usr/bin/python3")),
+            ..identity.clone()
+        };
+        assert!(
+            identity
+                .mismatch(&other_binary)
+                .unwrap()
+                .contains("/usr/bin/python3")
+        );
+
+        // Unknown details are not evidence of a change
+        let unknown = ProcessIdentity {
+            pid: 42,
+            started: None,
+            exe: None,
+        };
+        assert_eq!(identity.mismatch(&unknown), None);
+    }
+
+    #[test]
+    fn test_kill_plan_round_trip() {
+        let process = ProcessInfo {
+            pid: 4242,
+            name: "node".to_string(),
+            user: "dev".to_string(),
+            uid: None,
+            command: vec!["node".to_string(), "server.js".to_string()],
+            cwd: None,
+            exe: Some(PathBuf::from("/usr/bin/node")),
+            parent_pid: None,
+            parent_name: None,
+            started: None,
+            manager: None,
+            protocol: Protocol::Tcp,
+        };
+        let plan = KillPlan {
+            created: Local::now(),
+            host: "staging-1".to_string(),
+            targets: vec![PlannedKill {
+                identity: process.identity(),
+                ports: vec![3000],
+                signal: KillSignal::Kill,
+                reason: "listening on port 3000".to_string(),
+                process,
+            }],
+        };
+
+        let json = serde_json::to_string_pretty(&plan).unwrap();
+        assert!(json.contains("\"signal\": \"SIGKILL\""));
+
+        let back: KillPlan = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.host, "staging-1");
+        assert_eq!(back.targets[0].identity, plan.targets[0].identity);
+        assert_eq!(back.targets[0].signal, KillSignal::Kill);
+        assert_eq!(back.targets[0].process.exe, plan.targets[0].process.exe);
+    }
+
+    #[test]
+    fn test_kill_signal() {
+        assert_eq!(KillSignal::from_force(false), KillSignal::Term);
+        assert!(KillSignal::from_force(true).is_force());
+        assert_eq!(KillSignal::Kill.to_string(), "SIGKILL");
     }
 }
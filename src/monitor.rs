@@ -0,0 +1,276 @@
+//! Port open and close events for `monitor`
+
+use crate::model::{ProcessInfo, Protocol};
+use crate::net::Binding;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+/// What happened to a listening socket
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Opened,
+    Closed,
+    OwnerChanged,
+}
+
+impl std::fmt::Display for EventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventKind::Opened => write!(f, "opened"),
+            EventKind::Closed => write!(f, "closed"),
+            EventKind::OwnerChanged => write!(f, "owner_changed"),
+        }
+    }
+}
+
+/// One line of the event stream
+#[derive(Debug, Clone, Serialize)]
+pub struct PortEvent {
+    pub event: EventKind,
+    pub time: DateTime<Local>,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub address: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_pid: Option<u32>,
+    /// The owner as last seen; for `closed` it was taken when the port opened
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<ProcessInfo>,
+}
+
+type SocketKey = (u16, Protocol, IpAddr);
+
+#[derive(Debug)]
+struct Tracked {
+    id: u64,
+    pid: Option<u32>,
+    process: Option<ProcessInfo>,
+}
+
+/// Listening sockets seen so far, diffed against each new snapshot.
+///
+/// A socket is only looked up again when its inode changes, so a snapshot
+/// where nothing changed costs a single socket table dump.
+#[derive(Debug, Default)]
+pub struct Monitor {
+    tracked: BTreeMap<SocketKey, Tracked>,
+}
+
+impl Monitor {
+    /// Apply a snapshot and return the events it implies.
+    ///
+    /// `resolve` fills in the owners of sockets not seen before and
+    /// `inspect` snapshots an owner. With `announce` false the snapshot is
+    /// only recorded, which is how the initial state is taken.
+    pub fn update(
+        &mut self,
+        snapshot: Vec<Binding>,
+        now: DateTime<Local>,
+        announce: bool,
+        resolve: impl FnOnce(&mut [Binding]),
+        mut inspect: impl FnMut(u32, Protocol) -> Option<ProcessInfo>,
+    ) -> Vec<PortEvent> {
+        // SO_REUSEPORT can put several sockets on one address; track the oldest
+        let mut current: BTreeMap<SocketKey, Binding> = BTreeMap::new();
+        for binding in snapshot {
+            let key = (binding.port, binding.protocol, binding.address);
+            match current.get(&key) {
+                Some(existing) if existing.id <= binding.id => {}
+                _ => {
+                    current.insert(key, binding);
+                }
+            }
+        }
+
+        let mut unknown: Vec<Binding> = current
+            .iter()
+            .filter(|(key, b)| self.tracked.get(key).is_none_or(|t| t.id != b.id))
+            .map(|(_, b)| b.clone())
+            .collect();
+        resolve(&mut unknown);
+
+        let mut events = Vec::new();
+        let event = |kind, key: &SocketKey, pid, previous_pid, process| PortEvent {
+            event: kind,
+            time: now,
+            port: key.0,
+            protocol: key.1,
+            address: key.2,
+            pid,
+            previous_pid,
+            process,
+        };
+
+        let gone: Vec<SocketKey> = self
+            .tracked
+            .keys()
+            .filter(|key| !current.contains_key(key))
+            .copied()
+            .collect();
+        for key in gone {
+            if let Some(tracked) = self.tracked.remove(&key)
+                && announce
+            {
+                events.push(event(
+                    EventKind::Closed,
+                    &key,
+                    tracked.pid,
+                    None,
+                    tracked.process,
+                ));
+            }
+        }
+
+        for binding in unknown {
+            let key = (binding.port, binding.protocol, binding.address);
+            let pid = binding.pids.iter().min().copied();
+
+            let previous = self.tracked.remove(&key);
+
+            let (kind, previous_pid) = match &previous {
+                None => (Some(EventKind::Opened), None),
+                Some(previous) if previous.pid != pid => {
+                    (Some(EventKind::OwnerChanged), previous.pid)
+                }
+                // Same owner reopened the socket between two snapshots
+                Some(_) => (None, None),
+            };
+            let process = match previous {
+                Some(previous) if kind.is_none() => previous.process,
+                _ => pid.and_then(|pid| inspect(pid, binding.protocol)),
+            };
+            if let Some(kind) = kind
+                && announce
+            {
+                events.push(event(kind, &key, pid, previous_pid, process.clone()));
+            }
+            self.tracked.insert(
+                key,
+                Tracked {
+                    id: binding.id,
+                    pid,
+                    process,
+                },
+            );
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(port: u16, id: u64, pid: u32) -> Binding {
+        Binding {
+            id,
+            port,
+            protocol: Protocol::Tcp,
+            address: "0.0.0.0".parse().unwrap(),
+            state: Some("LISTEN".to_string()),
+            listening: true,
+            pids: vec![pid],
+        }
+    }
+
+    fn process(pid: u32) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: format!("server-{}", pid),
+            user: "dev".to_string(),
+            uid: None,
+            command: vec![],
+            cwd: None,
+            exe: None,
+            parent_pid: None,
+            parent_name: None,
+            started: None,
+            manager: None,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    /// Event kind, port, PID and previous PID
+    type Summary = (EventKind, u16, Option<u32>, Option<u32>);
+
+    /// Update with owners already attached, counting lookups
+    fn update(
+        monitor: &mut Monitor,
+        snapshot: Vec<Binding>,
+        announce: bool,
+    ) -> (Vec<Summary>, usize) {
+        let mut resolved = 0;
+        let events = monitor.update(
+            snapshot,
+            Local::now(),
+            announce,
+            |unknown| resolved = unknown.len(),
+            |pid, _| Some(process(pid)),
+        );
+        let events = events
+            .into_iter()
+            .map(|e| (e.event, e.port, e.pid, e.previous_pid))
+            .collect();
+        (events, resolved)
+    }
+
+    #[test]
+    fn test_initial_snapshot_is_silent() {
+        let mut monitor = Monitor::default();
+        let (events, _) = update(&mut monitor, vec![binding(3000, 1, 10)], false);
+        assert!(events.is_empty());
+
+        let (events, resolved) = update(&mut monitor, vec![binding(3000, 1, 10)], true);
+        assert!(events.is_empty());
+        assert_eq!(resolved, 0);
+    }
+
+    #[test]
+    fn test_opened_and_closed() {
+        let mut monitor = Monitor::default();
+        update(&mut monitor, vec![binding(3000, 1, 10)], false);
+
+        let (events, resolved) = update(
+            &mut monitor,
+            vec![binding(3000, 1, 10), binding(5432, 2, 20)],
+            true,
+        );
+        assert_eq!(events, vec![(EventKind::Opened, 5432, Some(20), None)]);
+        assert_eq!(resolved, 1);
+
+        let (events, _) = update(&mut monitor, vec![binding(5432, 2, 20)], true);
+        assert_eq!(events, vec![(EventKind::Closed, 3000, Some(10), None)]);
+    }
+
+    #[test]
+    fn test_closed_keeps_snapshot_from_open() {
+        let mut monitor = Monitor::default();
+        update(&mut monitor, vec![], false);
+        update(&mut monitor, vec![binding(3000, 1, 10)], true);
+
+        let events = monitor.update(vec![], Local::now(), true, |_| {}, |_, _| None);
+        assert_eq!(events[0].process.as_ref().unwrap().name, "server-10");
+    }
+
+    #[test]
+    fn test_owner_changed() {
+        let mut monitor = Monitor::default();
+        update(&mut monitor, vec![binding(3000, 1, 10)], false);
+
+        let (events, _) = update(&mut monitor, vec![binding(3000, 2, 11)], true);
+        assert_eq!(
+            events,
+            vec![(EventKind::OwnerChanged, 3000, Some(11), Some(10))]
+        );
+
+        // A new socket from the same owner is not an event
+        let (events, _) = update(&mut monitor, vec![binding(3000, 3, 11)], true);
+        assert!(events.is_empty());
+    }
+}
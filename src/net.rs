@@ -3,10 +3,14 @@
 use crate::cli::ProtocolFilter;
 use crate::error::{PortDetectiveError, Result};
 use crate::model::Protocol;
+#[cfg(target_os = "linux")]
+use crate::sockdiag;
 use netstat2::{
     AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo, SocketInfo, TcpState, get_sockets_info,
 };
+use serde::Serialize;
 use std::collections::HashMap;
+use std::net::IpAddr;
 
 /// A socket bound to a port
 #[derive(Debug, Clone)]
@@ -14,7 +18,6 @@ pub struct BoundSocket {
     pub pid: u32,
     pub port: u16,
     pub protocol: Protocol,
-    #[allow(dead_code)]
     pub local_addr: String,
 }
 
@@ -27,68 +30,235 @@ pub fn find_processes_by_port(port: u16, filter: ProtocolFilter) -> Result<Vec<B
 /// Get all listening sockets
 pub fn get_listening_sockets(filter: ProtocolFilter) -> Result<Vec<BoundSocket>> {
     let af_flags = AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6;
+    let sockets = get_sockets_info(af_flags, protocol_flags(filter))
+        .map_err(|e| PortDetectiveError::NetworkError(e.to_string()))?;
 
-    let proto_flags = match filter {
-        ProtocolFilter::TcpOnly => ProtocolFlags::TCP,
-        ProtocolFilter::UdpOnly => ProtocolFlags::UDP,
-        ProtocolFilter::Both => ProtocolFlags::TCP | ProtocolFlags::UDP,
-    };
+    Ok(sockets.iter().flat_map(extract_listening_socket).collect())
+}
 
-    let sockets = get_sockets_info(af_flags, proto_flags)
+/// Count established TCP connections whose local side is one of the given ports
+pub fn established_connections(ports: &[u16]) -> Result<HashMap<u16, usize>> {
+    let af_flags = AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6;
+    let sockets = get_sockets_info(af_flags, ProtocolFlags::TCP)
         .map_err(|e| PortDetectiveError::NetworkError(e.to_string()))?;
 
-    let mut result = Vec::new();
-
-    for socket in sockets {
-        if let Some(bound) = extract_listening_socket(&socket) {
-            result.push(bound);
+    let mut counts: HashMap<u16, usize> = ports.iter().map(|&p| (p, 0)).collect();
+    for port in sockets.iter().filter_map(established_port) {
+        if let Some(count) = counts.get_mut(&port) {
+            *count += 1;
         }
     }
+    Ok(counts)
+}
+
+/// Local port of an established TCP connection
+fn established_port(socket: &SocketInfo) -> Option<u16> {
+    match &socket.protocol_socket_info {
+        ProtocolSocketInfo::Tcp(tcp) if tcp.state == TcpState::Established => Some(tcp.local_port),
+        _ => None,
+    }
+}
 
-    Ok(result)
+/// A socket of any state whose local side is bound to a port
+#[derive(Debug, Clone)]
+pub struct Binding {
+    /// Socket inode, or the first owner's PID where inodes are not exposed
+    pub id: u64,
+    pub port: u16,
+    pub protocol: Protocol,
+    pub address: IpAddr,
+    /// TCP state; UDP sockets have none
+    pub state: Option<String>,
+    /// Listening TCP socket or bound UDP socket
+    pub listening: bool,
+    pub pids: Vec<u32>,
 }
 
-/// Get all listening ports grouped by port number
-pub fn get_listening_ports(filter: ProtocolFilter) -> Result<HashMap<u16, Vec<BoundSocket>>> {
-    let sockets = get_listening_sockets(filter)?;
-    let mut map: HashMap<u16, Vec<BoundSocket>> = HashMap::new();
+/// Every socket bound to `port` right now, listening or not, with its owners
+pub fn bindings_on_port(port: u16, filter: ProtocolFilter) -> Result<Vec<Binding>> {
+    let mut bindings = sockets_on_port(port, filter)?;
+    attach_owners(&mut bindings);
+    Ok(bindings)
+}
 
-    for socket in sockets {
-        map.entry(socket.port).or_default().push(socket);
+/// Every socket bound to `port` right now, without owners on Linux.
+///
+/// On Linux the kernel filters the socket table by port through sock_diag,
+/// so a poll stays cheap however many other sockets exist; finding owners
+/// is a separate `/proc` walk, left to `attach_owners`.
+#[cfg(target_os = "linux")]
+pub fn sockets_on_port(port: u16, filter: ProtocolFilter) -> Result<Vec<Binding>> {
+    let protocols = match filter {
+        ProtocolFilter::TcpOnly => &[sockdiag::IPPROTO_TCP][..],
+        ProtocolFilter::UdpOnly => &[sockdiag::IPPROTO_UDP],
+        ProtocolFilter::Both => &[sockdiag::IPPROTO_TCP, sockdiag::IPPROTO_UDP],
+    };
+    let mut bindings = Vec::new();
+    for &protocol in protocols {
+        bindings.extend(
+            sockdiag::sockets_on_port(port, protocol)?
+                .iter()
+                // TIME_WAIT leftovers have no inode and no owner
+                .filter(|s| s.inode != 0)
+                .map(|s| binding(s, s.inode as u64)),
+        );
     }
+    Ok(bindings)
+}
 
-    Ok(map)
+/// Every socket bound to `port` right now, with owners
+#[cfg(not(target_os = "linux"))]
+pub fn sockets_on_port(port: u16, filter: ProtocolFilter) -> Result<Vec<Binding>> {
+    bindings(filter, |b| b.port == port)
 }
 
-fn extract_listening_socket(socket: &SocketInfo) -> Option<BoundSocket> {
-    let pids = &socket.associated_pids;
-    if pids.is_empty() {
-        return None;
+/// Listening sockets, without owners on Linux; see `attach_owners`
+pub fn listening_bindings(filter: ProtocolFilter) -> Result<Vec<Binding>> {
+    bindings(filter, |b| b.listening)
+}
+
+#[cfg(target_os = "linux")]
+fn bindings(filter: ProtocolFilter, keep: impl Fn(&Binding) -> bool) -> Result<Vec<Binding>> {
+    let sockets = netstat2::iterate_sockets_info_without_pids(
+        AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6,
+        protocol_flags(filter),
+    )
+    .map_err(|e| PortDetectiveError::NetworkError(e.to_string()))?;
+
+    Ok(sockets
+        .filter_map(|s| s.ok())
+        // TIME_WAIT leftovers have no inode and no owner
+        .filter(|s| s.inode != 0)
+        .map(|s| binding(&s, s.inode as u64))
+        .filter(keep)
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn bindings(filter: ProtocolFilter, keep: impl Fn(&Binding) -> bool) -> Result<Vec<Binding>> {
+    let sockets = get_sockets_info(
+        AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6,
+        protocol_flags(filter),
+    )
+    .map_err(|e| PortDetectiveError::NetworkError(e.to_string()))?;
+
+    Ok(sockets
+        .iter()
+        .map(|s| {
+            let id = s.associated_pids.first().copied().unwrap_or_default() as u64;
+            Binding {
+                pids: s.associated_pids.clone(),
+                ..binding(s, id)
+            }
+        })
+        .filter(keep)
+        .collect())
+}
+
+/// Fill in the owning PIDs of the given sockets
+#[cfg(target_os = "linux")]
+pub fn attach_owners(bindings: &mut [Binding]) {
+    if bindings.is_empty() {
+        return;
     }
+    let inodes: Vec<u64> = bindings.iter().map(|b| b.id).collect();
+    let mut owners = crate::proc::socket_owners(&inodes);
+    for binding in bindings {
+        binding.pids = owners.remove(&binding.id).unwrap_or_default();
+    }
+}
 
-    match &socket.protocol_socket_info {
+/// Owners are already known where the socket table comes with PIDs
+#[cfg(not(target_os = "linux"))]
+pub fn attach_owners(_bindings: &mut [Binding]) {}
+
+fn protocol_flags(filter: ProtocolFilter) -> ProtocolFlags {
+    match filter {
+        ProtocolFilter::TcpOnly => ProtocolFlags::TCP,
+        ProtocolFilter::UdpOnly => ProtocolFlags::UDP,
+        ProtocolFilter::Both => ProtocolFlags::TCP | ProtocolFlags::UDP,
+    }
+}
+
+/// The socket as a `Binding` without owners
+fn binding(socket: &SocketInfo, id: u64) -> Binding {
+    let (port, protocol, address, state, listening) = match &socket.protocol_socket_info {
+        ProtocolSocketInfo::Tcp(tcp) => (
+            tcp.local_port,
+            Protocol::Tcp,
+            tcp.local_addr,
+            Some(tcp.state.to_string()),
+            tcp.state == TcpState::Listen,
+        ),
+        ProtocolSocketInfo::Udp(udp) => (udp.local_port, Protocol::Udp, udp.local_addr, None, true),
+    };
+    Binding {
+        id,
+        port,
+        protocol,
+        address,
+        state,
+        listening,
+        pids: Vec::new(),
+    }
+}
+
+/// Expand a socket into one `BoundSocket` per owning PID.
+///
+/// Pre-fork servers (nginx, gunicorn, ...) share a listening socket between
+/// the master and its workers, so every associated PID is an owner.
+fn extract_listening_socket(socket: &SocketInfo) -> Vec<BoundSocket> {
+    let (port, protocol, local_addr) = match &socket.protocol_socket_info {
         ProtocolSocketInfo::Tcp(tcp) => {
             // Only listening sockets
             if tcp.state != TcpState::Listen {
-                return None;
+                return Vec::new();
             }
-            Some(BoundSocket {
-                pid: pids[0],
-                port: tcp.local_port,
-                protocol: Protocol::Tcp,
-                local_addr: format!("{}", tcp.local_addr),
-            })
+            (tcp.local_port, Protocol::Tcp, tcp.local_addr)
         }
-        ProtocolSocketInfo::Udp(udp) => {
-            // UDP sockets don't have state, include all bound ones
-            Some(BoundSocket {
-                pid: pids[0],
-                port: udp.local_port,
-                protocol: Protocol::Udp,
-                local_addr: format!("{}", udp.local_addr),
+        // UDP sockets don't have state, include all bound ones
+        ProtocolSocketInfo::Udp(udp) => (udp.local_port, Protocol::Udp, udp.local_addr),
+    };
+
+    socket
+        .associated_pids
+        .iter()
+        .map(|&pid| BoundSocket {
+            pid,
+            port,
+            protocol,
+            local_addr: local_addr.to_string(),
+        })
+        .collect()
+}
+
+/// An address assigned to a network interface
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InterfaceAddr {
+    pub interface: String,
+    pub address: IpAddr,
+}
+
+/// IPv4 and IPv6 addresses of every interface, loopback included
+pub fn interface_addresses() -> Result<Vec<InterfaceAddr>> {
+    let interfaces = nix::ifaddrs::getifaddrs().map_err(|e| {
+        PortDetectiveError::NetworkError(format!("could not list interfaces: {}", e))
+    })?;
+
+    Ok(interfaces
+        .filter_map(|interface| {
+            let sockaddr = interface.address?;
+            let address = if let Some(v4) = sockaddr.as_sockaddr_in() {
+                IpAddr::V4(v4.ip())
+            } else {
+                IpAddr::V6(sockaddr.as_sockaddr_in6()?.ip())
+            };
+            Some(InterfaceAddr {
+                interface: interface.interface_name,
+                address,
             })
-        }
-    }
+        })
+        .collect())
 }
 
 #[cfg(test)]
@@ -133,15 +303,49 @@ mod tests {
     }
 
     #[test]
-    fn test_get_listening_ports_returns_hashmap() {
-        let result = get_listening_ports(ProtocolFilter::Both);
-        assert!(result.is_ok());
+    fn test_established_connections_counts_own_connection() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
 
-        let map = result.unwrap();
-        // Each port key should have at least one socket
-        for (_port, sockets) in &map {
-            assert!(!sockets.is_empty());
-        }
+        let counts = established_connections(&[port]).unwrap();
+        assert_eq!(counts.get(&port), Some(&0));
+
+        let _client = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let (_server, _) = listener.accept().unwrap();
+        let counts = established_connections(&[port]).unwrap();
+        assert_eq!(counts.get(&port), Some(&1));
+    }
+
+    #[test]
+    fn test_bindings_on_port_sees_non_listening_sockets() {
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = udp.local_addr().unwrap().port();
+
+        let bindings = bindings_on_port(port, ProtocolFilter::UdpOnly).unwrap();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].protocol, Protocol::Udp);
+        assert!(bindings[0].pids.contains(&std::process::id()));
+
+        let tcp = bindings_on_port(port, ProtocolFilter::TcpOnly).unwrap();
+        assert!(tcp.is_empty());
+    }
+
+    #[test]
+    fn test_listening_bindings_skips_connections() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _client = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+
+        let mut bindings: Vec<Binding> = listening_bindings(ProtocolFilter::TcpOnly)
+            .unwrap()
+            .into_iter()
+            .filter(|b| b.port == port)
+            .collect();
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(bindings[0].address.to_string(), "127.0.0.1");
+
+        attach_owners(&mut bindings);
+        assert_eq!(bindings[0].pids, vec![std::process::id()]);
     }
 
     #[test]
@@ -159,4 +363,10 @@ mod tests {
         assert_eq!(socket.protocol, Protocol::Tcp);
         assert_eq!(socket.local_addr, "127.0.0.1");
     }
+
+    #[test]
+    fn test_interface_addresses_include_loopback() {
+        let addresses = interface_addresses().unwrap();
+        assert!(addresses.iter().any(|a| a.address.is_loopback()));
+    }
 }
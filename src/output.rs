@@ -1,7 +1,29 @@
-//! Output rendering for human and JSON formats
+//! Output rendering for human and machine-readable formats
 
-use crate::model::{PortEntry, PortReport, PortStatus, ProcessInfo};
-use owo_colors::OwoColorize;
+use crate::cli::{OutputFormat, WaitUntil};
+use crate::color::Colorize;
+use crate::config::{Column, Settings};
+use crate::exposure::{Exposure, Risk};
+use crate::hold::{Ended, HoldRecord};
+use crate::hooks::{Hook, Outcome};
+use crate::lease::Lease;
+use crate::model::{
+    ApplyReport, KillPlan, KillResult, KillTarget, PortEntry, PortReport, PortStatus, ProcessInfo,
+    Respawn,
+};
+use crate::monitor::PortEvent;
+use crate::policy::{Violation, ViolationKind};
+use crate::project::Finding;
+use crate::remember::Remembered;
+use crate::render;
+use crate::snapshot::{Diff, Snapshot};
+use crate::status::ServiceStatus;
+use crate::sweep::{self, Candidate};
+use crate::trap::Sighting;
+use crate::wait::WaitReport;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
 
 /// Print a port report in human-readable format
 pub fn print_report(report: &PortReport) {
@@ -81,79 +103,615 @@ fn print_process_details(info: &ProcessInfo) {
         );
     }
 
+    // Service manager
+    if let Some(manager) = &info.manager {
+        println!("{}    {}", "Manager:".bold(), manager.label().magenta());
+    }
+
     println!();
-    print_kill_hints(info.pid);
+    print_kill_hints(info);
 }
 
-fn print_kill_hints(pid: u32) {
+fn print_kill_hints(info: &ProcessInfo) {
+    if let Some(manager) = &info.manager {
+        println!(
+            "{}",
+            format!(
+                "# managed by {}: a plain kill may just be restarted",
+                manager.label()
+            )
+            .dimmed()
+            .italic()
+        );
+        if let Some(argv) = manager.stop_command() {
+            println!("{}", "Suggested stop:".bold().underline());
+            println!("  {}", argv.join(" ").yellow());
+            println!(
+                "  {}",
+                "# or: portdetective kill --via-manager".dimmed().italic()
+            );
+            println!();
+        }
+    }
+
+    let pid = info.pid;
     println!("{}", "Suggested kill:".bold().underline());
     println!("  {} {}", "kill".dimmed(), pid.to_string().yellow());
     println!("  {}", "# or force if needed:".dimmed().italic());
     println!("  {} {}", "kill -9".dimmed(), pid.to_string().yellow());
 }
 
-/// Print a port report as JSON
-pub fn print_report_json(report: &PortReport) {
-    let json = serde_json::to_string_pretty(report).unwrap_or_else(|_| "{}".to_string());
-    println!("{}", json);
+/// Print a port report in a machine-readable format
+pub fn print_report_as(report: &PortReport, format: OutputFormat) {
+    println!("{}", render::render(report, format));
+}
+
+/// Print several port reports as one document
+pub fn print_reports_as(reports: &[PortReport], format: OutputFormat) {
+    println!("{}", render::render(reports, format));
 }
 
 /// Print a list of ports in table format
-pub fn print_port_list(entries: &[PortEntry]) {
+pub fn print_port_list(entries: &[PortEntry], settings: &Settings) {
     if entries.is_empty() {
         println!("{} No listening ports found", "✅".green());
         return;
     }
 
-    // Header
+    let columns = &settings.columns;
+    let port_label = |entry: &PortEntry| match settings.port_name(entry.port) {
+        Some(name) => format!("{} {}", entry.port, name),
+        None => entry.port.to_string(),
+    };
+    let port_width = entries
+        .iter()
+        .map(|e| port_label(e).len() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(7);
+
+    // Every column but the last is padded to its width
+    let width = |column: Column| -> usize {
+        match column {
+            Column::Port => port_width,
+            Column::Proto => 6,
+            Column::Pid => 8,
+            Column::Process => 12,
+            Column::User => 10,
+            Column::Address => 18,
+            Column::Command => 50,
+        }
+    };
+    let last = columns.len().saturating_sub(1);
+
+    let header: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, &column)| {
+            let w = if i == last { 0 } else { width(column) };
+            let title = column.to_string().to_uppercase();
+            format!("{:<w$}", title.bold().underline())
+        })
+        .collect();
+    println!("{}", header.join(" "));
+
+    for entry in entries {
+        let cmd_display = truncate(&entry.command, 50);
+
+        let cells: Vec<String> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, &column)| {
+                let w = if i == last { 0 } else { width(column) };
+                match column {
+                    Column::Port => match settings.port_name(entry.port) {
+                        Some(name) => {
+                            let w = w.saturating_sub(entry.port.to_string().len() + 1);
+                            format!("{} {:<w$}", entry.port.cyan(), name.magenta())
+                        }
+                        None => format!("{:<w$}", entry.port.cyan()),
+                    },
+                    Column::Proto => format!("{:<w$}", entry.protocol.dimmed()),
+                    Column::Pid => format!("{:<w$}", entry.pid.yellow()),
+                    Column::Process => format!("{:<w$}", entry.name.green()),
+                    Column::User => format!("{:<w$}", entry.user.blue()),
+                    Column::Address => format!("{:<w$}", entry.address),
+                    Column::Command => format!("{:<w$}", cmd_display.dimmed()),
+                }
+            })
+            .collect();
+        println!("{}", cells.join(" "));
+    }
+
+    println!();
+    println!(
+        "{} {} listening port(s) found",
+        "📊".blue(),
+        entries.len().to_string().bold()
+    );
+}
+
+/// Print the port list in a machine-readable format
+pub fn print_port_list_as(entries: &[PortEntry], format: OutputFormat) {
+    println!("{}", render::render(entries, format));
+}
+
+/// Print the port list as a single JSON line, for streaming
+pub fn print_port_list_json_line(entries: &[PortEntry]) {
+    let json = serde_json::to_string(entries).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Announce the hooks being watched for
+pub fn print_hooks_loaded(hooks: &[Hook], path: &Path) {
+    println!(
+        "{} Loaded {} hook(s) from {}; watching for port events...",
+        "🪝".blue(),
+        hooks.len().to_string().bold(),
+        path.display().to_string().dimmed()
+    );
+}
+
+/// Print how a hook run for an event ended
+pub fn print_hook_fired(hook: &Hook, event: &PortEvent, outcome: crate::error::Result<Outcome>) {
+    let trigger = format!("{} {}/{}", event.event, event.port, event.protocol);
+    match outcome {
+        Ok(Outcome::Exited(status)) if status.success() => println!(
+            "{} {} ran on {}",
+            "🪝".green(),
+            hook.name.bold(),
+            trigger.cyan()
+        ),
+        Ok(Outcome::Exited(status)) => println!(
+            "{} {} ran on {} but {} (see {})",
+            "🪝".red(),
+            hook.name.bold(),
+            trigger.cyan(),
+            status.to_string().red(),
+            hook.log_path().display().to_string().dimmed()
+        ),
+        Ok(Outcome::TimedOut(timeout)) => println!(
+            "{} {} on {} was killed after {}",
+            "⏰".yellow(),
+            hook.name.bold(),
+            trigger.cyan(),
+            sweep::format_age(timeout)
+        ),
+        Err(e) => print_error(&format!("hook {}: {}", hook.name, e)),
+    }
+}
+
+/// Print a port event as a single JSON line
+pub fn print_port_event(event: &PortEvent) {
+    let json = serde_json::to_string(event).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+/// Print exposed listeners with a remediation hint under each
+pub fn print_exposure(exposures: &[Exposure]) {
+    if exposures.is_empty() {
+        println!("{} Nothing is listening beyond loopback", "✅".green());
+        return;
+    }
+
+    println!(
+        "{:<10} {:<12} {:<18} {:<20} {}",
+        "RISK".bold(),
+        "PORT".bold(),
+        "ADDRESS".bold(),
+        "PROCESS".bold(),
+        "SERVICE".bold()
+    );
+    for e in exposures {
+        let risk = format!("{:<10}", e.risk.to_string().to_uppercase());
+        let risk = match e.risk {
+            Risk::Critical => risk.red().bold().to_string(),
+            Risk::High => risk.red().to_string(),
+            Risk::Medium => risk.yellow().to_string(),
+            Risk::Low => risk.dimmed().to_string(),
+        };
+        let via: Vec<String> = e
+            .reachable
+            .iter()
+            .map(|i| match i.interface.as_str() {
+                "" => i.address.to_string(),
+                name => format!("{} {}", name, i.address),
+            })
+            .collect();
+        println!(
+            "{} {:<12} {:<18} {:<20} {}",
+            risk,
+            format!("{}/{}", e.port, e.protocol).cyan(),
+            e.address,
+            match e.pid {
+                Some(pid) => format!("{} ({})", e.process, pid).green().to_string(),
+                None => e.process.yellow().to_string(),
+            },
+            e.service.unwrap_or("-")
+        );
+        println!("           {} {}", "via".dimmed(), via.join(", ").dimmed());
+        println!("           {} {}", "→".blue(), e.hint);
+    }
+
+    let critical = exposures
+        .iter()
+        .filter(|e| e.risk == Risk::Critical)
+        .count();
+    println!();
+    println!(
+        "{} {} exposed listener(s), {} critical",
+        "🌐".blue(),
+        exposures.len().to_string().bold(),
+        critical.to_string().red().bold()
+    );
+}
+
+/// Print exposed listeners as JSON
+pub fn print_exposure_json(exposures: &[Exposure]) {
+    let json = serde_json::to_string_pretty(exposures).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Print the result of `audit`
+pub fn print_audit(violations: &[Violation], path: &Path, expected: usize) {
+    if violations.is_empty() {
+        println!(
+            "{} All listeners match {} ({} expected)",
+            "✅".green(),
+            path.display().to_string().bold(),
+            expected
+        );
+        return;
+    }
+
+    for v in violations {
+        let port = format!("{}/{}", v.port, v.protocol)
+            .cyan()
+            .bold()
+            .to_string();
+        let owner = match (&v.process, v.pid) {
+            (Some(process), Some(pid)) => format!("{} (PID {})", process.green(), pid),
+            (Some(process), None) => process.yellow().to_string(),
+            _ => String::new(),
+        };
+        let allowed = v.allowed.join(", ");
+        match v.kind {
+            ViolationKind::Unexpected => {
+                println!("{} {} unexpected listener {}", "❌".red(), port, owner)
+            }
+            ViolationKind::Missing => {
+                println!("{} {} expected but nothing is listening", "❌".red(), port)
+            }
+            ViolationKind::Address => println!(
+                "{} {} {} bound to {}, allowed {}",
+                "❌".red(),
+                port,
+                owner,
+                v.address.as_deref().unwrap_or_default().red().bold(),
+                allowed.dimmed()
+            ),
+            ViolationKind::Process => println!(
+                "{} {} held by {}, allowed {}",
+                "❌".red(),
+                port,
+                owner,
+                allowed.dimmed()
+            ),
+            ViolationKind::User => println!(
+                "{} {} {} runs as {}, allowed {}",
+                "❌".red(),
+                port,
+                owner,
+                v.user.as_deref().unwrap_or_default().red().bold(),
+                allowed.dimmed()
+            ),
+            ViolationKind::Unverified => println!(
+                "{} {} held by an owner that cannot be inspected; process and user unchecked",
+                "❌".red(),
+                port
+            ),
+        }
+    }
+
+    println!();
+    println!(
+        "{} {} violation(s) of {}",
+        "📋".blue(),
+        violations.len().to_string().red().bold(),
+        path.display()
+    );
+}
+
+/// Print audit violations as JSON
+pub fn print_audit_json(violations: &[Violation]) {
+    let json = serde_json::to_string_pretty(violations).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Print each declared port and whether it is already taken
+pub fn print_project(findings: &[Finding], root: &Path) {
+    if findings.is_empty() {
+        println!(
+            "{} No declared ports found under {}",
+            "🔍".blue(),
+            root.display().to_string().bold()
+        );
+        return;
+    }
+
+    println!(
+        "{} Ports declared under {}",
+        "📁".blue(),
+        root.display().to_string().bold()
+    );
+    println!();
+    for finding in findings {
+        let declared = &finding.declared;
+        let location = format!("{}:{}", declared.file.display(), declared.line);
+        let port = format!("{}/{}", declared.port, declared.protocol);
+        let status = if finding.taken {
+            let owners: Vec<String> = finding
+                .owners
+                .iter()
+                .map(|o| match o.is_unknown() {
+                    true => o.name.clone(),
+                    false => format!("{} (PID {})", o.name, o.pid),
+                })
+                .collect();
+            format!("{} {}", "taken by".red().bold(), owners.join(", ").yellow())
+        } else {
+            "free".green().to_string()
+        };
+        println!(
+            "{} {:<10} {:<28} {:<16} {}",
+            if finding.taken { "❌" } else { "✅" },
+            port.cyan(),
+            location.dimmed(),
+            declared.context,
+            status
+        );
+    }
+
+    let taken = findings.iter().filter(|f| f.taken).count();
+    println!();
+    if taken == 0 {
+        println!(
+            "{} All {} declared port(s) are free",
+            "✅".green(),
+            findings.len()
+        );
+    } else {
+        println!(
+            "{} {} of {} declared port(s) already taken",
+            "⚠️".yellow(),
+            taken.to_string().red().bold(),
+            findings.len()
+        );
+    }
+}
+
+/// Print declared ports and their owners as JSON
+pub fn print_project_json(findings: &[Finding]) {
+    let json = serde_json::to_string_pretty(findings).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Print each named port as up or down, with its owners
+pub fn print_status(services: &[ServiceStatus]) {
+    let name_width = services
+        .iter()
+        .map(|s| s.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(7);
     println!(
-        "{:<7} {:<6} {:<8} {:<12} {:<10} {}",
+        "{:<name_width$} {:<7} {:<8} {:<8} {:<12} {}",
+        "SERVICE".bold().underline(),
         "PORT".bold().underline(),
-        "PROTO".bold().underline(),
+        "STATUS".bold().underline(),
         "PID".bold().underline(),
         "PROCESS".bold().underline(),
-        "USER".bold().underline(),
         "COMMAND".bold().underline()
     );
 
-    for entry in entries {
-        let cmd_display = if entry.command.len() > 50 {
-            format!("{}...", &entry.command[..47])
-        } else {
-            entry.command.clone()
+    for service in services {
+        let Some((first, rest)) = service.listeners.split_first() else {
+            println!(
+                "{:<name_width$} {:<7} {:<8}",
+                service.name.bold(),
+                service.port.cyan(),
+                "○ down".red()
+            );
+            continue;
         };
+        for (i, entry) in std::iter::once(first).chain(rest).enumerate() {
+            let (name, port, state) = if i == 0 {
+                (service.name.as_str(), service.port.to_string(), "● up")
+            } else {
+                ("", String::new(), "")
+            };
+            let cmd_display = truncate(&entry.command, 50);
+            let pid = if entry.is_unknown() {
+                "-".to_string()
+            } else {
+                entry.pid.to_string()
+            };
+            println!(
+                "{:<name_width$} {:<7} {:<8} {:<8} {:<12} {}",
+                name.bold(),
+                port.cyan(),
+                state.green(),
+                pid.yellow(),
+                entry.name.green(),
+                cmd_display.dimmed()
+            );
+        }
+    }
 
+    let up = services.iter().filter(|s| s.up).count();
+    println!();
+    println!(
+        "{} {}/{} service(s) up",
+        "📊".blue(),
+        up.to_string().bold(),
+        services.len()
+    );
+}
+
+/// Print named port status as JSON
+pub fn print_status_json(services: &[ServiceStatus]) {
+    let json = serde_json::to_string_pretty(services).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Print the effective settings and where each came from
+pub fn print_config(settings: &Settings) {
+    for path in &settings.files {
+        println!("{} Read {}", "📄".blue(), path.display().to_string().bold());
+    }
+    if settings.files.is_empty() {
+        println!("{} No config files found", "📄".blue());
+    }
+    println!();
+
+    println!(
+        "{:<20} {:<40} {}",
+        "SETTING".bold().underline(),
+        "VALUE".bold().underline(),
+        "SOURCE".bold().underline()
+    );
+    for (key, value, source) in settings.entries() {
         println!(
-            "{:<7} {:<6} {:<8} {:<12} {:<10} {}",
-            entry.port.to_string().cyan(),
-            entry.protocol.to_string().dimmed(),
-            entry.pid.to_string().yellow(),
-            entry.name.green(),
-            entry.user.blue(),
-            cmd_display.dimmed()
+            "{:<20} {:<40} {}",
+            key.cyan(),
+            value,
+            source.to_string().dimmed()
+        );
+    }
+}
+
+/// Print the effective settings as JSON
+pub fn print_config_json(settings: &Settings) {
+    let entries: serde_json::Map<String, serde_json::Value> = settings
+        .entries()
+        .into_iter()
+        .map(|(key, value, source)| {
+            (
+                key,
+                serde_json::json!({ "value": value, "source": source.to_string() }),
+            )
+        })
+        .collect();
+    let json = serde_json::json!({ "files": settings.files, "settings": entries });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json).unwrap_or_else(|_| "{}".to_string())
+    );
+}
+
+/// Print a snapshot as JSON
+pub fn print_snapshot(snapshot: &Snapshot) {
+    let json = serde_json::to_string_pretty(snapshot).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+fn print_diff_entry(sign: &str, entry: &PortEntry) {
+    println!(
+        "{} {:<10} {:<14} PID {:<8} {:<18} {}",
+        sign,
+        format!("{}/{}", entry.port, entry.protocol).cyan(),
+        entry.name.green(),
+        entry.pid.to_string().yellow(),
+        entry.address.dimmed(),
+        entry.command.dimmed()
+    );
+}
+
+/// Print what changed between two snapshots
+pub fn print_snapshot_diff(before: &Snapshot, after: &Snapshot, diff: &Diff) {
+    let stamp = |s: &Snapshot| format!("{} {}", s.host, s.taken.format("%Y-%m-%d %H:%M:%S"));
+    println!(
+        "{} Comparing {} with {}",
+        "📸".blue(),
+        stamp(before).bold(),
+        stamp(after).bold()
+    );
+    if diff.is_empty() {
+        println!("{} No changes", "✅".green());
+        return;
+    }
+    println!();
+
+    for entry in &diff.added {
+        print_diff_entry(&"+".green().bold().to_string(), entry);
+    }
+    for entry in &diff.removed {
+        print_diff_entry(&"-".red().bold().to_string(), entry);
+    }
+    for changed in &diff.changed {
+        println!(
+            "{} {} changed {}",
+            "~".yellow().bold(),
+            format!("{}/{}", changed.port, changed.protocol).cyan(),
+            changed.fields.join(", ").yellow()
         );
+        for entry in &changed.before {
+            print_diff_entry(&"   was".dimmed().to_string(), entry);
+        }
+        for entry in &changed.after {
+            print_diff_entry("   now", entry);
+        }
     }
 
     println!();
     println!(
-        "{} {} listening port(s) found",
+        "{} {} added, {} removed, {} changed",
         "📊".blue(),
-        entries.len().to_string().bold()
+        diff.added.len().to_string().green().bold(),
+        diff.removed.len().to_string().red().bold(),
+        diff.changed.len().to_string().yellow().bold()
     );
 }
 
-/// Print port list as JSON
-pub fn print_port_list_json(entries: &[PortEntry]) {
-    let json = serde_json::to_string_pretty(entries).unwrap_or_else(|_| "[]".to_string());
+/// Print a snapshot diff as JSON
+pub fn print_snapshot_diff_json(diff: &Diff) {
+    let json = serde_json::to_string_pretty(diff).unwrap_or_else(|_| "{}".to_string());
     println!("{}", json);
 }
 
+/// Print which ports appeared and disappeared since the last refresh
+pub fn print_port_changes(added: &[&PortEntry], removed: &[PortEntry]) {
+    println!(
+        "{} {}",
+        "🕒".blue(),
+        chrono::Local::now().format("%H:%M:%S").to_string().dimmed()
+    );
+    for entry in added {
+        println!(
+            "{} {} {} (PID {})",
+            "+".green().bold(),
+            entry.port.to_string().cyan(),
+            entry.name.green(),
+            entry.pid.to_string().yellow()
+        );
+    }
+    for entry in removed {
+        println!(
+            "{} {} {} (PID {})",
+            "-".red().bold(),
+            entry.port.to_string().cyan(),
+            entry.name.dimmed(),
+            entry.pid.to_string().dimmed()
+        );
+    }
+}
+
 /// Print kill confirmation prompt
-pub fn print_kill_prompt(port: u16, info: &ProcessInfo) {
+pub fn print_kill_prompt(ports: &[u16], info: &ProcessInfo) {
     println!(
         "{} Port {} ({}) is in use by:",
         "🔎".yellow(),
-        port.to_string().cyan().bold(),
+        join_ports(ports).cyan().bold(),
         info.protocol.to_string().dimmed()
     );
     println!(
@@ -175,15 +733,595 @@ pub fn print_kill_prompt(port: u16, info: &ProcessInfo) {
     println!();
 }
 
-/// Print kill success message
-pub fn print_kill_success(pid: u32, force: bool) {
-    let signal = if force { "SIGKILL" } else { "SIGTERM" };
+/// Print a numbered list of kill targets to pick from
+pub fn print_kill_selection(targets: &[KillTarget]) {
+    for (i, target) in targets.iter().enumerate() {
+        println!(
+            "  [{}] {} (PID {}) on port {}",
+            (i + 1).to_string().bold(),
+            target.process.name.green(),
+            target.process.pid.to_string().yellow(),
+            join_ports(&target.ports).cyan()
+        );
+    }
+}
+
+/// Print a warning for a target the guardrails refused
+pub fn print_kill_refused(target: &KillTarget, reason: &str) {
     println!(
-        "{} Sent {} to PID {}",
-        "✅".green(),
-        signal.yellow(),
-        pid.to_string().bold()
+        "{} Skipping {} (PID {}): {}",
+        "🛡".yellow(),
+        target.process.name.green(),
+        target.process.pid.to_string().yellow(),
+        reason
+    );
+}
+
+/// Print the outcome of each signaled process
+pub fn print_kill_results(results: &[KillResult]) {
+    for result in results {
+        if result.success
+            && let Some(via) = &result.via
+        {
+            println!(
+                "{} Stopped PID {} ({}) via {}",
+                "✅".green(),
+                result.pid.to_string().bold(),
+                result.name,
+                via.yellow()
+            );
+        } else if result.success {
+            println!(
+                "{} Sent {} to PID {} ({}){}",
+                "✅".green(),
+                result.signal.yellow(),
+                result.pid.to_string().bold(),
+                result.name,
+                match result.open_connections {
+                    Some(open) if open > 0 => format!(" with {} connection(s) open", open),
+                    _ => String::new(),
+                }
+            );
+        } else {
+            println!(
+                "{} Failed to {} PID {} ({}): {}",
+                "❌".red(),
+                match &result.via {
+                    Some(via) => format!("stop via {}", via),
+                    None => format!("send {} to", result.signal),
+                }
+                .yellow(),
+                result.pid.to_string().bold(),
+                result.name,
+                result.error.as_deref().unwrap_or("unknown error").red()
+            );
+        }
+        for respawn in &result.respawns {
+            print_respawn(respawn);
+        }
+    }
+}
+
+fn print_respawn(respawn: &Respawn) {
+    println!(
+        "{} Port {} is back: {} (PID {}) bound it again{}",
+        "🔁".yellow(),
+        respawn.port.to_string().cyan(),
+        respawn.name.green(),
+        respawn.pid.to_string().yellow(),
+        match &respawn.respawner {
+            Some(by) => format!(", likely respawned by {}", by.bold()),
+            None => String::new(),
+        }
+    );
+}
+
+/// Rewrite the drain progress line in place
+pub fn print_drain_progress(ports: &[u16], open: usize, waited: Duration, timeout: Duration) {
+    print!(
+        "\r{} Draining port {}: {} connection(s) open ({}s / {}s)   ",
+        "⏳".blue(),
+        join_ports(ports),
+        open.to_string().bold(),
+        waited.as_secs(),
+        timeout.as_secs()
     );
+    let _ = io::stdout().flush();
+}
+
+/// Finish the drain progress line
+pub fn print_drain_done(open: usize) {
+    println!();
+    if open > 0 {
+        println!(
+            "{} Drain timed out with {} connection(s) still open",
+            "⚠️".yellow(),
+            open.to_string().bold()
+        );
+    }
+}
+
+/// Announce ports that are now held
+pub fn print_holding(records: &[HoldRecord]) {
+    for record in records {
+        println!(
+            "{} Holding port {} ({} on {}) as PID {}",
+            "🔒".blue(),
+            record.port.to_string().cyan().bold(),
+            record.protocol,
+            record
+                .addrs
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            record.holder.pid.to_string().yellow()
+        );
+    }
+    let ports: Vec<u16> = records.iter().map(|r| r.port).collect();
+    let until = match records.first().and_then(|r| r.until) {
+        Some(until) => format!(" (or wait until {})", until.format("%H:%M:%S")),
+        None => String::new(),
+    };
+    println!(
+        "   Release with Ctrl-C or {}{}",
+        format!(
+            "portdetective release {}",
+            join_ports(&ports).replace(", ", " ")
+        )
+        .bold(),
+        until
+    );
+}
+
+/// Say why a hold ended
+pub fn print_hold_ended(ports: &[u16], ended: Ended) {
+    let why = match ended {
+        Ended::Signal(signal) => format!("on {}", signal),
+        Ended::Timeout => "after the timeout".to_string(),
+        Ended::Released => "on request".to_string(),
+    };
+    println!(
+        "{} Released port {} {}",
+        "🔓".green(),
+        join_ports(ports).cyan(),
+        why
+    );
+}
+
+/// Confirm holds ended from this shell
+pub fn print_released(records: &[HoldRecord]) {
+    for record in records {
+        println!(
+            "{} Released port {} (held by PID {} since {})",
+            "🔓".green(),
+            record.port.to_string().cyan().bold(),
+            record.holder.pid.to_string().yellow(),
+            record.started.format("%H:%M:%S")
+        );
+    }
+}
+
+/// Print hold records as JSON
+pub fn print_holds_json(records: &[HoldRecord]) {
+    let json = serde_json::to_string_pretty(records).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Print the kill results and the holds that followed as one JSON document
+pub fn print_kill_and_hold_json(results: &[KillResult], records: &[HoldRecord]) {
+    let value = serde_json::json!({ "results": results, "holds": records });
+    let json = serde_json::to_string_pretty(&value).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+/// Print that the port is busy but its owner cannot be inspected
+pub fn print_hidden_owner(port: u16) {
+    println!(
+        "{} Port {} is in use by a process you cannot see (another user's; run as root to inspect it)",
+        "🔎".yellow(),
+        port.to_string().cyan().bold()
+    );
+}
+
+/// Note that `run` moved to another port
+pub fn print_using_port(busy: u16, port: u16) {
+    println!(
+        "{} Port {} is busy; using {} (exported as {})",
+        "➡️".blue(),
+        busy.to_string().cyan(),
+        port.to_string().cyan().bold(),
+        "PORT".bold()
+    );
+}
+
+/// Print just the leased port numbers, one per line, for `PORT=$(...)`
+pub fn print_leased_ports(leases: &[Lease]) {
+    for lease in leases {
+        println!("{}", lease.port);
+    }
+}
+
+/// Confirm leases were given back
+pub fn print_lease_released(leases: &[Lease]) {
+    for lease in leases {
+        println!(
+            "{} Released lease on port {} ({})",
+            "🔓".green(),
+            lease.port.to_string().cyan().bold(),
+            lease.owner
+        );
+    }
+}
+
+/// Print the current leases as a table
+pub fn print_lease_list(leases: &[Lease]) {
+    if leases.is_empty() {
+        println!("{} No active leases", "📭".yellow());
+        return;
+    }
+
+    println!(
+        "{:<8} {:<24} {}",
+        "PORT".bold(),
+        "OWNER".bold(),
+        "EXPIRES".bold()
+    );
+    for lease in leases {
+        println!(
+            "{:<8} {:<24} {}",
+            lease.port.to_string().cyan(),
+            lease.owner.green(),
+            lease
+                .expires
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+                .dimmed()
+        );
+    }
+}
+
+/// Print leases as JSON
+pub fn print_leases_json(leases: &[Lease]) {
+    let json = serde_json::to_string_pretty(leases).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Print how waiting on a port ended
+pub fn print_wait_report(report: &WaitReport) {
+    let state = match report.until {
+        WaitUntil::Listening => "listening",
+        WaitUntil::Free => "free",
+    };
+    let elapsed = format!("{:.1}s", report.elapsed_ms as f64 / 1000.0);
+
+    if !report.reached {
+        println!(
+            "{} Timed out after {} waiting for port {} to be {}",
+            "⏰".yellow(),
+            elapsed.bold(),
+            report.port.to_string().cyan().bold(),
+            state
+        );
+        return;
+    }
+
+    match &report.process {
+        Some(info) => println!(
+            "{} Port {} is {} ({}, PID {}) after {}",
+            "✅".green(),
+            report.port.to_string().cyan().bold(),
+            state.green().bold(),
+            info.name.green(),
+            info.pid.to_string().yellow(),
+            elapsed.bold()
+        ),
+        None => println!(
+            "{} Port {} is {} after {}",
+            "✅".green(),
+            report.port.to_string().cyan().bold(),
+            state.green().bold(),
+            elapsed.bold()
+        ),
+    }
+}
+
+/// Print a wait report as JSON
+pub fn print_wait_report_json(report: &WaitReport) {
+    let json = serde_json::to_string_pretty(report).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+/// Announce that a trap is set
+pub fn print_trap_set(port: u16, duration: Duration) {
+    println!(
+        "{} Watching port {} for {}...",
+        "🪤".yellow(),
+        port.to_string().cyan().bold(),
+        sweep::format_age(duration).bold()
+    );
+}
+
+/// Print a process caught binding the trapped port
+pub fn print_sighting(sighting: &Sighting) {
+    let binding = match &sighting.state {
+        Some(state) => format!("{}/{} ({})", sighting.port, sighting.protocol, state),
+        None => format!("{}/{}", sighting.port, sighting.protocol),
+    };
+    let time = sighting.seen.format("%H:%M:%S%.3f").to_string();
+
+    match (&sighting.process, sighting.pid) {
+        (Some(info), _) => {
+            println!(
+                "{} {} {} (PID {}) bound {}",
+                "🪤".red(),
+                time.dimmed(),
+                info.name.green().bold(),
+                info.pid.to_string().yellow(),
+                binding.cyan()
+            );
+            let cmd = if info.command.is_empty() {
+                info.name.clone()
+            } else {
+                info.command.join(" ")
+            };
+            println!("  Command: {}", cmd);
+            if let Some(cwd) = &info.cwd {
+                println!("  CWD:     {}", cwd.display().to_string().dimmed());
+            }
+        }
+        (None, Some(pid)) => println!(
+            "{} {} PID {} bound {} but exited before it could be inspected",
+            "🪤".red(),
+            time.dimmed(),
+            pid.to_string().yellow(),
+            binding.cyan()
+        ),
+        (None, None) => println!(
+            "{} {} Something bound {} but was gone before its owner could be found",
+            "🪤".red(),
+            time.dimmed(),
+            binding.cyan()
+        ),
+    }
+
+    if !sighting.ancestry.is_empty() {
+        let chain: Vec<String> = sighting
+            .ancestry
+            .iter()
+            .map(|a| format!("{} ({})", a.name, a.pid))
+            .collect();
+        println!("  Parents: {}", chain.join(" ← ").dimmed());
+    }
+}
+
+/// Print a sighting as a single JSON line
+pub fn print_sighting_json(sighting: &Sighting) {
+    let json = serde_json::to_string(sighting).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+/// Print how many binders a trap caught
+pub fn print_trap_summary(port: u16, sightings: usize, duration: Duration) {
+    if sightings == 0 {
+        println!(
+            "{} Nothing bound port {} in {}",
+            "✨".green(),
+            port.to_string().cyan().bold(),
+            sweep::format_age(duration)
+        );
+    } else {
+        println!(
+            "{} Caught {} sighting(s) on port {} in {}",
+            "🪤".yellow(),
+            sightings.to_string().bold(),
+            port.to_string().cyan().bold(),
+            sweep::format_age(duration)
+        );
+    }
+}
+
+/// Print sweep candidates with the reasons they matched
+pub fn print_sweep_candidates(candidates: &[Candidate]) {
+    if candidates.is_empty() {
+        println!("{} Nothing to sweep", "✨".green());
+        return;
+    }
+
+    println!(
+        "{:<12} {:<8} {:<14} {:<8} {}",
+        "PORT".bold(),
+        "PID".bold(),
+        "PROCESS".bold(),
+        "AGE".bold(),
+        "REASONS".bold()
+    );
+    for candidate in candidates {
+        let info = &candidate.target.process;
+        let age = info
+            .started
+            .and_then(|s| (chrono::Local::now() - s).to_std().ok())
+            .map(sweep::format_age)
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{:<12} {:<8} {:<14} {:<8} {}",
+            join_ports(&candidate.target.ports).cyan(),
+            info.pid.to_string().yellow(),
+            info.name.green(),
+            age,
+            candidate.reasons.join(", ").dimmed()
+        );
+    }
+    println!();
+    println!(
+        "{} {} stale listener(s) found",
+        "🧹".blue(),
+        candidates.len().to_string().bold()
+    );
+}
+
+/// Print sweep candidates in a machine-readable format
+pub fn print_sweep_candidates_as(candidates: &[Candidate], format: OutputFormat) {
+    println!("{}", render::render(candidates, format));
+}
+
+/// Note a killed process that can be restarted later
+pub fn print_remembered(entry: &Remembered) {
+    println!(
+        "{} Remembered {} as #{}; restart it with {}",
+        "💾".blue(),
+        entry.name.green(),
+        entry.id,
+        format!("portdetective restart {}", entry.ports[0]).bold()
+    );
+    if !entry.omitted_env.is_empty() {
+        println!(
+            "   {}",
+            format!("left out secrets: {}", entry.omitted_env.join(", ")).dimmed()
+        );
+    }
+}
+
+/// Print the remembered processes as a table
+pub fn print_remembered_list(entries: &[Remembered]) {
+    if entries.is_empty() {
+        println!(
+            "{} Nothing remembered (use {})",
+            "📭".yellow(),
+            "portdetective kill --remember <PORT>".bold()
+        );
+        return;
+    }
+
+    println!(
+        "{:<5} {:<12} {:<12} {:<20} {}",
+        "ID".bold(),
+        "PORT".bold(),
+        "PROCESS".bold(),
+        "KILLED".bold(),
+        "COMMAND".bold()
+    );
+    for entry in entries {
+        println!(
+            "{:<5} {:<12} {:<12} {:<20} {}",
+            entry.id,
+            join_ports(&entry.ports).cyan(),
+            entry.name.green(),
+            entry
+                .killed
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+                .dimmed(),
+            truncate(&entry.command.join(" "), 50)
+        );
+    }
+}
+
+/// Print the remembered processes as JSON
+pub fn print_remembered_list_json(entries: &[Remembered]) {
+    let json = serde_json::to_string_pretty(entries).unwrap_or_else(|_| "[]".to_string());
+    println!("{}", json);
+}
+
+/// Confirm a remembered process was started again
+pub fn print_restarted(entry: &Remembered, pid: u32, log: &Path) {
+    println!(
+        "{} Restarted {} (PID {}): {}",
+        "🚀".green(),
+        entry.name.green(),
+        pid.to_string().yellow(),
+        entry.command.join(" ")
+    );
+    if let Some(cwd) = &entry.cwd {
+        println!("   CWD: {}", cwd.display());
+    }
+    println!("   Log: {}", log.display().to_string().dimmed());
+    if !entry.omitted_env.is_empty() {
+        println!(
+            "   {} not restored: {}",
+            "⚠️".yellow(),
+            entry.omitted_env.join(", ")
+        );
+    }
+}
+
+/// Print a restart as JSON
+pub fn print_restarted_json(entry: &Remembered, pid: u32, log: &Path) {
+    let value = serde_json::json!({
+        "pid": pid,
+        "log": log,
+        "restarted": entry,
+    });
+    let json = serde_json::to_string_pretty(&value).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+/// Print kill results in a machine-readable format
+pub fn print_kill_results_as(results: &[KillResult], format: OutputFormat) {
+    println!("{}", render::render(results, format));
+}
+
+/// Print a freshly written kill plan for review
+pub fn print_kill_plan(plan: &KillPlan, path: &Path) {
+    for target in &plan.targets {
+        println!(
+            "  {} {} (PID {}) — {}",
+            target.signal.to_string().yellow(),
+            target.process.name.green(),
+            target.identity.pid.to_string().yellow(),
+            target.reason.dimmed()
+        );
+    }
+    println!();
+    println!(
+        "{} Wrote kill plan with {} target(s) to {}",
+        "📝".blue(),
+        plan.targets.len().to_string().bold(),
+        path.display().to_string().cyan()
+    );
+    println!(
+        "   Review it, then run: {}",
+        format!("portdetective kill --apply {}", path.display()).bold()
+    );
+}
+
+/// Print a kill plan as JSON
+pub fn print_kill_plan_json(plan: &KillPlan) {
+    let json = serde_json::to_string_pretty(plan).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+/// Print differences between a kill plan and the live system
+pub fn print_drift(drift: &[String]) {
+    for line in drift {
+        println!("{} Drift: {}", "⚠️".yellow(), line);
+    }
+    if !drift.is_empty() {
+        println!();
+    }
+}
+
+/// Print the outcome of applying a kill plan as JSON
+pub fn print_apply_report_json(report: &ApplyReport) {
+    let json = serde_json::to_string_pretty(report).unwrap_or_else(|_| "{}".to_string());
+    println!("{}", json);
+}
+
+/// Shorten `text` to at most `max` characters, ending in `...` if cut
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max.saturating_sub(3)).collect();
+    format!("{}...", kept)
+}
+
+pub fn join_ports(ports: &[u16]) -> String {
+    ports
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 /// Print kill cancelled message
@@ -195,3 +1333,8 @@ pub fn print_kill_cancelled() {
 pub fn print_error(msg: &str) {
     eprintln!("{} {}", "Error:".red().bold(), msg);
 }
+
+/// Print warning message
+pub fn print_warning(msg: &str) {
+    eprintln!("{} {}", "Warning:".yellow().bold(), msg);
+}
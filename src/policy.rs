@@ -0,0 +1,395 @@
+//! Expected listeners declared in `.portdetective.toml`, checked by `audit`
+
+use crate::cli::ProtocolFilter;
+use crate::config;
+use crate::error::{PortDetectiveError, Result};
+use crate::listing::Listener;
+use crate::model::Protocol;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// A listener the policy allows
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expected {
+    pub port: u16,
+    #[serde(default = "default_protocol")]
+    pub protocol: Protocol,
+    /// Addresses it may bind; `0.0.0.0` and `::` also allow narrower ones.
+    /// Any address if empty.
+    #[serde(default)]
+    pub address: Vec<IpAddr>,
+    /// Executable names or absolute paths allowed to hold it; anyone if empty
+    #[serde(default)]
+    pub process: Vec<String>,
+    /// Users allowed to hold it; anyone if empty
+    #[serde(default)]
+    pub user: Vec<String>,
+    /// Not a violation if nothing is listening
+    #[serde(default)]
+    pub optional: bool,
+}
+
+fn default_protocol() -> Protocol {
+    Protocol::Tcp
+}
+
+// Not `deny_unknown_fields`: the file holds other settings too
+#[derive(Debug, Deserialize)]
+struct PolicyFile {
+    #[serde(default)]
+    listener: Vec<Expected>,
+}
+
+/// Read the `[[listener]]` tables from a TOML file
+pub fn load(path: &Path) -> Result<Vec<Expected>> {
+    let config_error = |reason: String| PortDetectiveError::Config {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let text = fs::read_to_string(path).map_err(|e| config_error(e.to_string()))?;
+    parse(&text).map_err(config_error)
+}
+
+fn parse(text: &str) -> std::result::Result<Vec<Expected>, String> {
+    let file: PolicyFile = toml::from_str(text).map_err(|e| e.message().to_string())?;
+    if file.listener.is_empty() {
+        return Err("no [[listener]] entries".to_string());
+    }
+    Ok(file.listener)
+}
+
+/// How a listener breaks the policy
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// Listening on a port the policy does not mention
+    Unexpected,
+    /// An expected port nobody is listening on
+    Missing,
+    /// Bound to an address the policy does not allow
+    Address,
+    /// Held by an executable the policy does not allow
+    Process,
+    /// Held by a user the policy does not allow
+    User,
+    /// The owner could not be inspected, so its process and user are unchecked
+    Unverified,
+}
+
+/// One finding of `audit`
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub port: u16,
+    pub protocol: Protocol,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    /// What the policy allows instead, for address, process and user violations
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
+}
+
+impl Expected {
+    fn covers(&self, listener: &Listener) -> bool {
+        self.port == listener.port
+            && (self.protocol == Protocol::Both || self.protocol == listener.protocol)
+    }
+
+    fn allows_address(&self, address: &str) -> bool {
+        if self.address.is_empty() {
+            return true;
+        }
+        let Ok(address) = address.parse::<IpAddr>() else {
+            return false;
+        };
+        self.address.iter().any(|allowed| {
+            *allowed == address
+                || allowed.is_unspecified() && (allowed.is_ipv6() || address.is_ipv4())
+        })
+    }
+
+    fn allows_process(&self, listener: &Listener) -> bool {
+        let process = &listener.process;
+        self.process.is_empty()
+            || self.process.iter().any(|allowed| {
+                if allowed.starts_with('/') {
+                    process.exe.as_deref() == Some(Path::new(allowed))
+                } else {
+                    *allowed == process.name
+                        || process
+                            .exe
+                            .as_deref()
+                            .and_then(Path::file_name)
+                            .is_some_and(|name| name == allowed.as_str())
+                }
+            })
+    }
+}
+
+/// Check the live listeners against the policy.
+///
+/// Expectations for a protocol the filter leaves out are skipped.
+pub fn audit(
+    policy: &[Expected],
+    listeners: &[Listener],
+    filter: ProtocolFilter,
+) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut push = |violation: Violation| {
+        if !violations.contains(&violation) {
+            violations.push(violation);
+        }
+    };
+
+    for listener in listeners {
+        let process = &listener.process;
+        let violation = |kind, address: Option<&str>, allowed: Vec<String>| Violation {
+            kind,
+            port: listener.port,
+            protocol: listener.protocol,
+            address: address.map(str::to_string),
+            pid: (!process.is_unknown()).then_some(process.pid),
+            process: Some(process.name.clone()),
+            user: (!process.is_unknown()).then(|| process.user.clone()),
+            allowed,
+        };
+
+        let Some(expected) = policy.iter().find(|e| e.covers(listener)) else {
+            push(violation(ViolationKind::Unexpected, None, Vec::new()));
+            continue;
+        };
+        if !expected.allows_address(&listener.local_addr) {
+            let allowed = expected.address.iter().map(IpAddr::to_string).collect();
+            push(violation(
+                ViolationKind::Address,
+                Some(&listener.local_addr),
+                allowed,
+            ));
+        }
+        if process.is_unknown() {
+            // Another user's socket seen without root: it counts as present,
+            // but the owner constraints cannot be confirmed
+            if !expected.process.is_empty() || !expected.user.is_empty() {
+                push(violation(ViolationKind::Unverified, None, Vec::new()));
+            }
+            continue;
+        }
+        if !expected.allows_process(listener) {
+            push(violation(
+                ViolationKind::Process,
+                None,
+                expected.process.clone(),
+            ));
+        }
+        if !expected.user.is_empty() && !expected.user.contains(&process.user) {
+            push(violation(ViolationKind::User, None, expected.user.clone()));
+        }
+    }
+
+    let audited = |protocol: Protocol| match filter {
+        ProtocolFilter::TcpOnly => protocol != Protocol::Udp,
+        ProtocolFilter::UdpOnly => protocol != Protocol::Tcp,
+        ProtocolFilter::Both => true,
+    };
+    for expected in policy {
+        if !expected.optional
+            && audited(expected.protocol)
+            && !listeners.iter().any(|l| expected.covers(l))
+        {
+            push(Violation {
+                kind: ViolationKind::Missing,
+                port: expected.port,
+                protocol: expected.protocol,
+                address: None,
+                pid: None,
+                process: None,
+                user: None,
+                allowed: Vec::new(),
+            });
+        }
+    }
+
+    violations.sort_by_key(|v| (v.port, v.protocol, v.pid));
+    violations
+}
+
+/// Default policy file: the project file in the current directory or the
+/// nearest parent
+pub fn default_path() -> PathBuf {
+    config::project_path().unwrap_or_else(|| PathBuf::from(config::PROJECT_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ProcessInfo;
+
+    const POLICY: &str = r#"
+        [[listener]]
+        port = 22
+        process = ["sshd"]
+        user = ["root"]
+
+        [[listener]]
+        port = 5432
+        address = ["127.0.0.1", "::1"]
+
+        [[listener]]
+        port = 53
+        protocol = "udp"
+        optional = true
+
+        [[listener]]
+        port = 9090
+    "#;
+
+    fn listener(port: u16, address: &str, name: &str, user: &str) -> Listener {
+        Listener {
+            port,
+            protocol: Protocol::Tcp,
+            local_addr: address.to_string(),
+            process: ProcessInfo {
+                pid: u32::from(port),
+                name: name.to_string(),
+                user: user.to_string(),
+                uid: None,
+                command: vec![],
+                cwd: None,
+                exe: Some(PathBuf::from(format!("/usr/sbin/{}", name))),
+                parent_pid: None,
+                parent_name: None,
+                started: None,
+                manager: None,
+                protocol: Protocol::Tcp,
+            },
+        }
+    }
+
+    fn kinds(violations: &[Violation]) -> Vec<(ViolationKind, u16)> {
+        violations.iter().map(|v| (v.kind, v.port)).collect()
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = parse(POLICY).unwrap();
+        assert_eq!(policy.len(), 4);
+        assert_eq!(policy[0].protocol, Protocol::Tcp);
+        assert_eq!(policy[2].protocol, Protocol::Udp);
+        assert!(policy[2].optional);
+        assert_eq!(policy[1].address.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_ignores_other_settings() {
+        let policy = parse("color = false\n[[listener]]\nport = 80\n").unwrap();
+        assert_eq!(policy[0].port, 80);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_policy() {
+        assert_eq!(parse("").unwrap_err(), "no [[listener]] entries");
+        assert!(parse("[[listener]]\nport = 80\nadress = [\"::1\"]\n").is_err());
+        assert!(parse("[[listener]]\nport = 80\naddress = [\"localhost\"]\n").is_err());
+    }
+
+    #[test]
+    fn test_compliant_host() {
+        let policy = parse(POLICY).unwrap();
+        let listeners = vec![
+            listener(22, "0.0.0.0", "sshd", "root"),
+            listener(22, "::", "sshd", "root"),
+            listener(5432, "127.0.0.1", "postgres", "postgres"),
+            listener(9090, "0.0.0.0", "prometheus", "nobody"),
+        ];
+        assert!(audit(&policy, &listeners, ProtocolFilter::Both).is_empty());
+    }
+
+    #[test]
+    fn test_violations() {
+        let policy = parse(POLICY).unwrap();
+        let listeners = vec![
+            listener(22, "0.0.0.0", "dropbear", "dev"),
+            listener(3000, "0.0.0.0", "node", "dev"),
+            listener(3000, "::", "node", "dev"),
+            listener(5432, "0.0.0.0", "postgres", "postgres"),
+        ];
+
+        let violations = audit(&policy, &listeners, ProtocolFilter::Both);
+        assert_eq!(
+            kinds(&violations),
+            vec![
+                (ViolationKind::Process, 22),
+                (ViolationKind::User, 22),
+                (ViolationKind::Unexpected, 3000),
+                (ViolationKind::Address, 5432),
+                (ViolationKind::Missing, 9090),
+            ]
+        );
+        assert_eq!(violations[3].address.as_deref(), Some("0.0.0.0"));
+        assert_eq!(violations[3].allowed, vec!["127.0.0.1", "::1"]);
+    }
+
+    #[test]
+    fn test_unknown_owners_are_audited() {
+        let policy = parse(POLICY).unwrap();
+        let unknown = |port: u16| Listener {
+            process: ProcessInfo::unknown(Protocol::Tcp),
+            ..listener(port, "0.0.0.0", "", "")
+        };
+        let listeners = vec![
+            unknown(22),
+            unknown(6379),
+            listener(5432, "127.0.0.1", "postgres", "postgres"),
+            unknown(9090),
+        ];
+
+        let violations = audit(&policy, &listeners, ProtocolFilter::Both);
+        assert_eq!(
+            kinds(&violations),
+            vec![
+                (ViolationKind::Unverified, 22),
+                (ViolationKind::Unexpected, 6379),
+            ]
+        );
+        assert_eq!(violations[1].pid, None);
+        assert_eq!(violations[1].process.as_deref(), Some("unknown"));
+    }
+
+    #[test]
+    fn test_unspecified_allows_narrower() {
+        let expected = &parse("[[listener]]\nport = 80\naddress = [\"0.0.0.0\"]\n").unwrap()[0];
+        assert!(expected.allows_address("0.0.0.0"));
+        assert!(expected.allows_address("10.0.0.5"));
+        assert!(!expected.allows_address("::"));
+
+        let expected = &parse("[[listener]]\nport = 80\naddress = [\"::\"]\n").unwrap()[0];
+        assert!(expected.allows_address("::1"));
+        assert!(expected.allows_address("0.0.0.0"));
+    }
+
+    #[test]
+    fn test_process_by_path() {
+        let expected =
+            &parse("[[listener]]\nport = 22\nprocess = [\"/usr/sbin/sshd\"]\n").unwrap()[0];
+        assert!(expected.allows_process(&listener(22, "0.0.0.0", "sshd", "root")));
+
+        let expected = &parse("[[listener]]\nport = 22\nprocess = [\"/opt/sshd\"]\n").unwrap()[0];
+        assert!(!expected.allows_process(&listener(22, "0.0.0.0", "sshd", "root")));
+    }
+
+    #[test]
+    fn test_filter_skips_missing_for_other_protocol() {
+        let policy = parse("[[listener]]\nport = 53\nprotocol = \"udp\"\n").unwrap();
+        assert!(audit(&policy, &[], ProtocolFilter::TcpOnly).is_empty());
+        assert_eq!(audit(&policy, &[], ProtocolFilter::Both).len(), 1);
+    }
+}
@@ -1,16 +1,27 @@
 //! Process inspection using sysinfo
 
 use crate::error::{PortDetectiveError, Result};
-use crate::model::{ProcessInfo, Protocol};
+use crate::manager::{self, ServiceManager};
+use crate::model::{ProcessIdentity, ProcessInfo, Protocol};
 use chrono::{DateTime, Local, TimeZone};
-use sysinfo::{Pid, System, Users};
+use std::collections::HashMap;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users};
+
+/// How far up the parent chain to look for a service manager
+const MAX_ANCESTORS: usize = 32;
 
 /// Inspect a process by PID and gather detailed information
 pub fn inspect(pid: u32, protocol: Protocol) -> Result<ProcessInfo> {
     let mut sys = System::new();
-    sys.refresh_processes(
-        sysinfo::ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
         true,
+        ProcessRefreshKind::nothing()
+            .with_user(UpdateKind::OnlyIfNotSet)
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_cwd(UpdateKind::OnlyIfNotSet)
+            .with_environ(UpdateKind::OnlyIfNotSet)
+            .with_exe(UpdateKind::OnlyIfNotSet),
     );
 
     let process = sys
@@ -20,6 +31,7 @@ pub fn inspect(pid: u32, protocol: Protocol) -> Result<ProcessInfo> {
     let users = Users::new_with_refreshed_list();
 
     // Get user name
+    let uid = process.user_id().map(|uid| **uid);
     let user = process
         .user_id()
         .and_then(|uid| users.get_user_by_id(uid))
@@ -33,8 +45,9 @@ pub fn inspect(pid: u32, protocol: Protocol) -> Result<ProcessInfo> {
         .map(|s| s.to_string_lossy().to_string())
         .collect();
 
-    // Get working directory
+    // Get working directory and executable
     let cwd = process.cwd().map(|p| p.to_path_buf());
+    let exe = process.exe().map(|p| p.to_path_buf());
 
     // Get start time
     let started = process_start_time(process.start_time());
@@ -42,19 +55,24 @@ pub fn inspect(pid: u32, protocol: Protocol) -> Result<ProcessInfo> {
     // Extract values we need before dropping the borrow
     let name = process.name().to_string_lossy().to_string();
     let parent = process.parent();
+    let environ = parse_environ(process.environ());
 
     // Now we can borrow sys mutably for parent info
     let (parent_pid, parent_name) = get_parent_info(&mut sys, parent);
+    let manager = detect_manager(&mut sys, pid, parent, &environ);
 
     Ok(ProcessInfo {
         pid,
         name,
         user,
+        uid,
         command,
         cwd,
+        exe,
         parent_pid,
         parent_name,
         started,
+        manager,
         protocol,
     })
 }
@@ -63,7 +81,7 @@ pub fn inspect(pid: u32, protocol: Protocol) -> Result<ProcessInfo> {
 fn get_parent_info(sys: &mut System, parent_pid: Option<Pid>) -> (Option<u32>, Option<String>) {
     match parent_pid {
         Some(ppid) => {
-            sys.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[ppid]), true);
+            sys.refresh_processes(ProcessesToUpdate::Some(&[ppid]), true);
             let parent_name = sys
                 .process(ppid)
                 .map(|p| p.name().to_string_lossy().to_string());
@@ -73,6 +91,69 @@ fn get_parent_info(sys: &mut System, parent_pid: Option<Pid>) -> (Option<u32>, O
     }
 }
 
+/// Work out which service manager, if any, supervises the process
+fn detect_manager(
+    sys: &mut System,
+    pid: u32,
+    parent: Option<Pid>,
+    environ: &HashMap<String, String>,
+) -> Option<ServiceManager> {
+    let cgroup = read_cgroup(pid);
+    let ancestors: Vec<String> = ancestor_chain(sys, parent)
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    manager::detect(environ, cgroup.as_deref(), &ancestors)
+}
+
+/// PIDs and names of a process's parent chain, nearest first, stopping before init
+pub fn ancestors(pid: u32) -> Vec<(u32, String)> {
+    let mut sys = System::new();
+    let pid = Pid::from_u32(pid);
+    sys.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    let parent = sys.process(pid).and_then(|p| p.parent());
+    ancestor_chain(&mut sys, parent)
+}
+
+/// Walk the parent chain starting at `next`, stopping before init
+fn ancestor_chain(sys: &mut System, mut next: Option<Pid>) -> Vec<(u32, String)> {
+    let mut chain = Vec::new();
+    while let Some(pid) = next {
+        if pid.as_u32() <= 1 || chain.len() >= MAX_ANCESTORS {
+            break;
+        }
+        sys.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        let Some(process) = sys.process(pid) else {
+            break;
+        };
+        chain.push((pid.as_u32(), process.name().to_string_lossy().to_string()));
+        next = process.parent();
+    }
+    chain
+}
+
+/// Split `KEY=VALUE` environment entries into a map
+fn parse_environ(entries: &[std::ffi::OsString]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let entry = entry.to_string_lossy();
+            let (key, value) = entry.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn read_cgroup(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_cgroup(_pid: u32) -> Option<String> {
+    None
+}
+
 /// Convert Unix timestamp to local datetime
 fn process_start_time(start_time: u64) -> Option<DateTime<Local>> {
     if start_time == 0 {
@@ -81,24 +162,133 @@ fn process_start_time(start_time: u64) -> Option<DateTime<Local>> {
     Local.timestamp_opt(start_time as i64, 0).single()
 }
 
-/// Kill a process by PID
-pub fn kill_process(pid: u32, force: bool) -> Result<()> {
-    use nix::sys::signal::{Signal, kill};
+/// Environment of a running process, empty if it cannot be read
+pub fn environ(pid: u32) -> HashMap<String, String> {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
+        true,
+        ProcessRefreshKind::nothing().with_environ(UpdateKind::OnlyIfNotSet),
+    );
+    sys.process(Pid::from_u32(pid))
+        .map(|p| parse_environ(p.environ()))
+        .unwrap_or_default()
+}
+
+/// Host name of this machine
+pub fn host_name() -> String {
+    System::host_name().unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Name of the user running portdetective
+pub fn current_user() -> Option<String> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_user(UpdateKind::OnlyIfNotSet),
+    );
+
+    let uid = sys.process(pid)?.user_id()?.clone();
+    Users::new_with_refreshed_list()
+        .get_user_by_id(&uid)
+        .map(|u| u.name().to_string())
+}
+
+/// Re-read the identity of whatever currently runs under `pid`
+pub fn identity(pid: u32) -> Result<ProcessIdentity> {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
+        true,
+        ProcessRefreshKind::nothing().with_exe(UpdateKind::OnlyIfNotSet),
+    );
+
+    let process = sys
+        .process(Pid::from_u32(pid))
+        .ok_or(PortDetectiveError::ProcessNotFound(pid))?;
+
+    Ok(ProcessIdentity {
+        pid,
+        started: process_start_time(process.start_time()),
+        exe: process.exe().map(|p| p.to_path_buf()),
+    })
+}
+
+/// A process pinned at discovery time, so that a recycled PID is never signaled
+#[derive(Debug)]
+pub struct ProcessHandle {
+    identity: ProcessIdentity,
+    #[cfg(target_os = "linux")]
+    pidfd: Option<std::os::fd::OwnedFd>,
+}
+
+impl ProcessHandle {
+    /// Pin the process. On Linux this opens a pidfd, which keeps referring to
+    /// the same process even after its PID is reused.
+    pub fn open(identity: ProcessIdentity) -> Self {
+        #[cfg(target_os = "linux")]
+        let pidfd = rustix::process::Pid::from_raw(identity.pid as i32).and_then(|pid| {
+            rustix::process::pidfd_open(pid, rustix::process::PidfdFlags::empty()).ok()
+        });
+
+        Self {
+            identity,
+            #[cfg(target_os = "linux")]
+            pidfd,
+        }
+    }
+
+    /// Check the process is still the one that was pinned, then signal it
+    pub fn signal(&self, force: bool) -> Result<()> {
+        use nix::sys::signal::Signal;
+        self.send(if force {
+            Signal::SIGKILL
+        } else {
+            Signal::SIGTERM
+        })
+    }
+
+    /// Check the process is still the one that was pinned, then send it `signal`
+    pub fn send(&self, signal: nix::sys::signal::Signal) -> Result<()> {
+        let pid = self.identity.pid;
+        let current = identity(pid)?;
+        if let Some(reason) = self.identity.mismatch(&current) {
+            return Err(PortDetectiveError::ProcessChanged { pid, reason });
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(pidfd) = &self.pidfd
+            && let Some(raw) = rustix::process::Signal::from_named_raw(signal as i32)
+        {
+            use rustix::io::Errno;
+            use rustix::process::pidfd_send_signal;
+
+            return pidfd_send_signal(pidfd, raw).map_err(|e| match e {
+                Errno::SRCH => PortDetectiveError::ProcessNotFound(pid),
+                Errno::PERM => permission_denied(pid),
+                _ => PortDetectiveError::KillFailed {
+                    pid,
+                    reason: e.to_string(),
+                },
+            });
+        }
+
+        send_signal(pid, signal)
+    }
+}
+
+/// Signal a process by PID, without a pidfd
+fn send_signal(pid: u32, signal: nix::sys::signal::Signal) -> Result<()> {
+    use nix::sys::signal::kill;
     use nix::unistd::Pid as NixPid;
 
-    let signal = if force {
-        Signal::SIGKILL
-    } else {
-        Signal::SIGTERM
-    };
     let nix_pid = NixPid::from_raw(pid as i32);
 
     kill(nix_pid, signal).map_err(|e| {
         if e == nix::errno::Errno::EPERM {
-            PortDetectiveError::PermissionDenied(format!(
-                "Cannot kill PID {}. Try running with elevated permissions.",
-                pid
-            ))
+            permission_denied(pid)
         } else {
             PortDetectiveError::KillFailed {
                 pid,
@@ -108,6 +298,76 @@ pub fn kill_process(pid: u32, force: bool) -> Result<()> {
     })
 }
 
+fn permission_denied(pid: u32) -> PortDetectiveError {
+    PortDetectiveError::PermissionDenied(format!(
+        "Cannot kill PID {}. Try running with elevated permissions.",
+        pid
+    ))
+}
+
+/// Effective UID of portdetective
+pub fn current_uid() -> u32 {
+    nix::unistd::geteuid().as_raw()
+}
+
+/// Whether we run as root and so can see every socket's owner
+pub fn is_root() -> bool {
+    nix::unistd::geteuid().is_root()
+}
+
+/// Whether the PID is a kernel thread rather than a userspace process
+#[cfg(target_os = "linux")]
+pub fn is_kernel_thread(pid: u32) -> bool {
+    const PF_KTHREAD: u32 = 0x0020_0000;
+    procfs::process::Process::new(pid as i32)
+        .and_then(|p| p.stat())
+        .map(|stat| stat.flags & PF_KTHREAD != 0)
+        .unwrap_or(false)
+}
+
+/// Whether the PID is a kernel thread rather than a userspace process
+#[cfg(not(target_os = "linux"))]
+pub fn is_kernel_thread(_pid: u32) -> bool {
+    false
+}
+
+/// PIDs holding each of the given socket inodes
+#[cfg(target_os = "linux")]
+pub fn socket_owners(inodes: &[u64]) -> HashMap<u64, Vec<u32>> {
+    let mut owners: HashMap<u64, Vec<u32>> = HashMap::new();
+    let Ok(processes) = procfs::process::all_processes() else {
+        return owners;
+    };
+    for process in processes.flatten() {
+        let Ok(fds) = process.fd() else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            if let procfs::process::FDTarget::Socket(inode) = fd.target
+                && inodes.contains(&inode)
+            {
+                owners.entry(inode).or_default().push(process.pid as u32);
+            }
+        }
+    }
+    owners
+}
+
+/// Whether the process has a controlling terminal, `None` if unknown
+#[cfg(target_os = "linux")]
+pub fn has_controlling_tty(pid: u32) -> Option<bool> {
+    procfs::process::Process::new(pid as i32)
+        .and_then(|p| p.stat())
+        .map(|stat| stat.tty_nr != 0)
+        .ok()
+}
+
+/// Whether the process has a controlling terminal, `None` if unknown
+#[cfg(not(target_os = "linux"))]
+pub fn has_controlling_tty(_pid: u32) -> Option<bool> {
+    None
+}
+
 #[cfg(target_os = "linux")]
 #[allow(dead_code)]
 pub fn get_cwd_linux(pid: u32) -> Option<std::path::PathBuf> {
@@ -182,6 +442,147 @@ mod tests {
         assert!(info.parent_pid.is_some());
     }
 
+    #[test]
+    fn test_current_user_matches_own_process() {
+        let info = inspect(process::id(), Protocol::Tcp).unwrap();
+        // Both fall back to "unknown" when the uid has no passwd entry
+        assert_eq!(
+            current_user().unwrap_or_else(|| "unknown".to_string()),
+            info.user
+        );
+    }
+
+    #[test]
+    fn test_own_process_is_not_kernel_thread() {
+        assert!(!is_kernel_thread(process::id()));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_kthreadd_is_kernel_thread() {
+        // kthreadd is PID 2 on a normal Linux host, absent inside PID namespaces
+        if let Ok(stat) = procfs::process::Process::new(2).and_then(|p| p.stat())
+            && stat.comm == "kthreadd"
+        {
+            assert!(is_kernel_thread(2));
+        }
+    }
+
+    #[test]
+    fn test_inspect_reads_command_line() {
+        let info = inspect(process::id(), Protocol::Tcp).unwrap();
+        assert!(!info.command.is_empty());
+        assert!(info.cwd.is_some());
+    }
+
+    fn spawn_sleeper() -> std::process::Child {
+        process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .expect("sleep should be available")
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_detached_child_has_no_tty() {
+        let mut child = std::process::Command::new("setsid")
+            .arg("sleep")
+            .arg("30")
+            .spawn()
+            .expect("failed to spawn setsid sleep");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let tty = has_controlling_tty(child.id());
+        let _ = child.kill();
+        let _ = child.wait();
+        assert_eq!(tty, Some(false));
+    }
+
+    #[test]
+    fn test_environ_of_own_process() {
+        let env = environ(std::process::id());
+        assert_eq!(
+            env.get("PATH").map(String::as_str),
+            std::env::var("PATH").ok().as_deref()
+        );
+    }
+
+    #[test]
+    fn test_ancestors_start_at_parent() {
+        let mut child = spawn_sleeper();
+        let chain = ancestors(child.id());
+        let _ = child.kill();
+        let _ = child.wait();
+
+        assert_eq!(chain.first().map(|(pid, _)| *pid), Some(std::process::id()));
+        assert!(chain.iter().all(|(pid, _)| *pid > 1));
+    }
+
+    #[test]
+    fn test_identity_of_own_process() {
+        let info = inspect(process::id(), Protocol::Tcp).unwrap();
+        let current = identity(process::id()).unwrap();
+        assert_eq!(current, info.identity());
+        assert!(current.exe.is_some());
+    }
+
+    #[test]
+    fn test_handle_signals_pinned_process() {
+        let mut child = spawn_sleeper();
+        let handle = ProcessHandle::open(identity(child.id()).unwrap());
+
+        handle.signal(true).unwrap();
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn test_handle_refuses_changed_identity() {
+        let mut child = spawn_sleeper();
+        let mut pinned = identity(child.id()).unwrap();
+        pinned.exe = Some(std::path::PathBuf::from("/not/the/same/binary"));
+        let handle = ProcessHandle::open(pinned);
+
+        let result = handle.signal(true);
+        assert!(matches!(
+            result,
+            Err(PortDetectiveError::ProcessChanged { .. })
+        ));
+        // The child must have survived the refused signal
+        assert!(child.try_wait().unwrap().is_none());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_handle_reports_exited_process() {
+        let mut child = spawn_sleeper();
+        let handle = ProcessHandle::open(identity(child.id()).unwrap());
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let result = handle.signal(false);
+        assert!(matches!(
+            result,
+            Err(PortDetectiveError::ProcessNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_environ() {
+        let entries = vec![
+            std::ffi::OsString::from("PS=web.1"),
+            std::ffi::OsString::from("EMPTY="),
+            std::ffi::OsString::from("A=b=c"),
+            std::ffi::OsString::from("garbage"),
+        ];
+        let env = parse_environ(&entries);
+        assert_eq!(env.get("PS").map(String::as_str), Some("web.1"));
+        assert_eq!(env.get("EMPTY").map(String::as_str), Some(""));
+        assert_eq!(env.get("A").map(String::as_str), Some("b=c"));
+        assert_eq!(env.len(), 3);
+    }
+
     #[test]
     fn test_process_start_time_zero_returns_none() {
         // The internal helper should return None for timestamp 0
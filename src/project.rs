@@ -0,0 +1,655 @@
+//! Ports declared in a repository's files, checked against the live port list
+
+use crate::error::{PortDetectiveError, Result};
+use crate::model::{PortEntry, Protocol};
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// How deep below the project root files are looked for
+const MAX_DEPTH: usize = 3;
+
+/// Widest compose port range expanded into findings; wider ranges are skipped
+const MAX_RANGE: u16 = 100;
+
+/// Directories never worth scanning
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "vendor", "dist", "build"];
+
+/// Kind of file a port was declared in
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Origin {
+    Env,
+    Compose,
+    Procfile,
+    PackageJson,
+    Dockerfile,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Env => write!(f, ".env"),
+            Origin::Compose => write!(f, "compose"),
+            Origin::Procfile => write!(f, "Procfile"),
+            Origin::PackageJson => write!(f, "package.json"),
+            Origin::Dockerfile => write!(f, "Dockerfile"),
+        }
+    }
+}
+
+/// A port some project file expects to be able to bind
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Declared {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub origin: Origin,
+    /// Relative to the project root
+    pub file: PathBuf,
+    pub line: usize,
+    /// Variable, service, process or script name the port belongs to
+    pub context: String,
+}
+
+/// A declared port and whoever already listens on it
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    #[serde(flatten)]
+    pub declared: Declared,
+    pub taken: bool,
+    pub owners: Vec<PortEntry>,
+}
+
+/// A port found by one of the parsers, before the file is known
+#[derive(Debug, PartialEq, Eq)]
+struct Found {
+    line: usize,
+    port: u16,
+    protocol: Protocol,
+    context: String,
+}
+
+fn found(line: usize, port: u16, protocol: Protocol, context: &str) -> Found {
+    Found {
+        line,
+        port,
+        protocol,
+        context: context.to_string(),
+    }
+}
+
+/// The repository root: the nearest directory at or above `dir` holding
+/// `.git`, or `dir` itself
+pub fn root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+fn origin(name: &str) -> Option<Origin> {
+    match name {
+        ".env" => Some(Origin::Env),
+        "Procfile" => Some(Origin::Procfile),
+        "package.json" => Some(Origin::PackageJson),
+        "docker-compose.yml" | "docker-compose.yaml" | "compose.yml" | "compose.yaml" => {
+            Some(Origin::Compose)
+        }
+        _ if name.starts_with(".env.") => Some(Origin::Env),
+        _ if name.starts_with("Procfile.") => Some(Origin::Procfile),
+        _ if name.starts_with("docker-compose.")
+            && (name.ends_with(".yml") || name.ends_with(".yaml")) =>
+        {
+            Some(Origin::Compose)
+        }
+        _ if name == "Dockerfile"
+            || name.starts_with("Dockerfile.")
+            || name.ends_with(".Dockerfile") =>
+        {
+            Some(Origin::Dockerfile)
+        }
+        _ => None,
+    }
+}
+
+/// Every port declared in the project's files, in file order
+pub fn scan(root: &Path) -> Result<Vec<Declared>> {
+    let mut files = Vec::new();
+    collect_files(root, 0, &mut files)?;
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut declared = Vec::new();
+    for (path, origin) in files {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let found = match origin {
+            Origin::Env => parse_env(&text),
+            Origin::Compose => parse_compose(&text),
+            Origin::Procfile => parse_procfile(&text),
+            Origin::PackageJson => parse_package_json(&text),
+            Origin::Dockerfile => parse_dockerfile(&text),
+        };
+        let file = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+        declared.extend(found.into_iter().map(|f| Declared {
+            port: f.port,
+            protocol: f.protocol,
+            origin,
+            file: file.clone(),
+            line: f.line,
+            context: f.context,
+        }));
+    }
+    Ok(declared)
+}
+
+fn collect_files(dir: &Path, depth: usize, files: &mut Vec<(PathBuf, Origin)>) -> Result<()> {
+    let entries = fs::read_dir(dir).map_err(|e| PortDetectiveError::Project {
+        path: dir.to_path_buf(),
+        reason: e.to_string(),
+    })?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(kind) = entry.file_type() else {
+            continue;
+        };
+        if kind.is_dir() {
+            if depth < MAX_DEPTH && !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str())
+            {
+                // Unreadable subdirectories are skipped, not fatal
+                let _ = collect_files(&path, depth + 1, files);
+            }
+        } else if let Some(origin) = origin(&name) {
+            files.push((path, origin));
+        }
+    }
+    Ok(())
+}
+
+fn unquote(s: &str) -> &str {
+    s.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+/// `PORT=3000` and `API_PORT=3000` style variables
+fn parse_env(text: &str) -> Vec<Found> {
+    let mut ports = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key != "PORT" && !key.ends_with("_PORT") {
+            continue;
+        }
+        let value = value.split(" #").next().unwrap_or_default();
+        if let Ok(port) = unquote(value).parse::<u16>()
+            && port != 0
+        {
+            ports.push(found(i + 1, port, Protocol::Tcp, key));
+        }
+    }
+    ports
+}
+
+/// A number, or `${VAR:-default}` / `${VAR-default}` with a numeric default
+fn compose_number(s: &str) -> Option<&str> {
+    let s = s.trim();
+    match s.strip_prefix("${").and_then(|s| s.strip_suffix('}')) {
+        Some(var) => var
+            .split_once('-')
+            .map(|(_, default)| default.trim_start_matches('-')),
+        None => Some(s),
+    }
+}
+
+/// Host ports of a short-syntax mapping: `8080:80`, `127.0.0.1:5432:5432/tcp`,
+/// `[::1]:5432:5432`, `8000-8002:8000-8002`. A bare container port publishes
+/// on a random host port and yields nothing, as does a range wider than
+/// `MAX_RANGE`.
+fn compose_mapping(spec: &str) -> (Vec<u16>, Protocol) {
+    let spec = unquote(spec);
+    let (spec, protocol) = match spec.rsplit_once('/') {
+        Some((spec, "udp")) => (spec, Protocol::Udp),
+        Some((spec, _)) => (spec, Protocol::Tcp),
+        None => (spec, Protocol::Tcp),
+    };
+    // Split on the colons outside `${...}` and bracketed IPv6 addresses
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in spec.char_indices() {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ':' if depth == 0 => {
+                parts.push(&spec[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&spec[start..]);
+    if parts.len() < 2 {
+        return (Vec::new(), protocol);
+    }
+
+    let host = parts[parts.len() - 2];
+    let ports = match host.split_once('-').filter(|_| !host.starts_with("${")) {
+        Some((first, last)) => match (first.parse::<u16>(), last.parse::<u16>()) {
+            (Ok(first), Ok(last)) if first <= last && last - first < MAX_RANGE => {
+                (first..=last).collect()
+            }
+            _ => Vec::new(),
+        },
+        None => compose_number(host)
+            .and_then(|p| p.parse().ok())
+            .into_iter()
+            .collect(),
+    };
+    (ports, protocol)
+}
+
+/// `key: value` or `key:` with a plain key, as opposed to a `8080:80` mapping
+fn yaml_key(s: &str) -> Option<(&str, &str)> {
+    let (key, value) = s.split_once(':')?;
+    let plain = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    (plain && (value.is_empty() || value.starts_with(char::is_whitespace)))
+        .then(|| (key, value.trim()))
+}
+
+/// A long-syntax `ports:` item, collected over several lines
+#[derive(Default)]
+struct LongPort {
+    published: Option<(usize, u16)>,
+    udp: bool,
+}
+
+fn flush(long: &mut Option<LongPort>, ports: &mut Vec<Found>, service: &str) {
+    if let Some(LongPort {
+        published: Some((line, port)),
+        udp,
+    }) = long.take()
+    {
+        let protocol = if udp { Protocol::Udp } else { Protocol::Tcp };
+        ports.push(found(line, port, protocol, service));
+    }
+}
+
+/// Host ports under each service's `ports:`, in short or long syntax.
+///
+/// Reads just enough YAML structure, by indentation, to find them.
+fn parse_compose(text: &str) -> Vec<Found> {
+    let mut ports = Vec::new();
+    let mut services: Option<usize> = None;
+    let mut service_level: Option<usize> = None;
+    let mut service = String::new();
+    let mut block: Option<usize> = None;
+    let mut long: Option<LongPort> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let level = line.len() - line.trim_start().len();
+
+        if let Some(ports_level) = block {
+            let item = trimmed.starts_with('-');
+            // List items may sit at the same indent as `ports:`
+            if level > ports_level || (level == ports_level && item) {
+                let entry = trimmed.strip_prefix('-').map_or(trimmed, str::trim_start);
+                if item {
+                    flush(&mut long, &mut ports, &service);
+                }
+                if let Some((key, value)) = yaml_key(entry) {
+                    let long = long.get_or_insert_with(LongPort::default);
+                    match key {
+                        "published" => {
+                            long.published = compose_number(unquote(value))
+                                .and_then(|p| p.parse().ok())
+                                .map(|port| (i + 1, port));
+                        }
+                        "protocol" => long.udp = unquote(value) == "udp",
+                        _ => {}
+                    }
+                } else if item {
+                    let (host_ports, protocol) = compose_mapping(entry);
+                    for port in host_ports {
+                        ports.push(found(i + 1, port, protocol, &service));
+                    }
+                }
+                continue;
+            }
+            flush(&mut long, &mut ports, &service);
+            block = None;
+        }
+
+        match services {
+            None if trimmed == "services:" => services = Some(level),
+            Some(top) if level <= top => {
+                services = (trimmed == "services:").then_some(level);
+                service_level = None;
+            }
+            Some(_) => {
+                let child = *service_level.get_or_insert(level);
+                if level == child {
+                    if let Some((key, _)) = yaml_key(trimmed) {
+                        service = key.to_string();
+                    }
+                } else if trimmed == "ports:" {
+                    block = Some(level);
+                }
+            }
+            None => {}
+        }
+    }
+    flush(&mut long, &mut ports, &service);
+    ports
+}
+
+static COMMAND_PORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:^|\s)(?:--port[= ]|-p[= ]?|PORT=|--(?:bind|listen|host)[= ]\S*:|-b[= ]?\S*:)(\d{1,5})\b",
+    )
+    .expect("valid regex")
+});
+
+fn command_ports(command: &str) -> Vec<u16> {
+    COMMAND_PORT
+        .captures_iter(command)
+        .filter_map(|c| c[1].parse().ok())
+        .filter(|&p| p != 0)
+        .collect()
+}
+
+/// `web: rails server -p 3000` style process lines
+fn parse_procfile(text: &str) -> Vec<Found> {
+    let mut ports = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let Some((name, command)) = line.split_once(':') else {
+            continue;
+        };
+        if name.trim().starts_with('#') {
+            continue;
+        }
+        for port in command_ports(command) {
+            ports.push(found(i + 1, port, Protocol::Tcp, name.trim()));
+        }
+    }
+    ports
+}
+
+/// `--port` and `PORT=` in the `scripts` of a package.json
+fn parse_package_json(text: &str) -> Vec<Found> {
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Vec::new();
+    };
+    let Some(scripts) = json.get("scripts").and_then(|s| s.as_object()) else {
+        return Vec::new();
+    };
+
+    let mut ports = Vec::new();
+    for (name, command) in scripts {
+        let Some(command) = command.as_str() else {
+            continue;
+        };
+        // JSON keeps no line numbers; find the script's key in the text
+        let key = format!("\"{}\"", name);
+        let line = text
+            .lines()
+            .position(|l| l.trim_start().starts_with(&key))
+            .map_or(0, |i| i + 1);
+        for port in command_ports(command) {
+            ports.push(found(line, port, Protocol::Tcp, name));
+        }
+    }
+    ports
+}
+
+/// `EXPOSE 80 443/tcp 53/udp`
+fn parse_dockerfile(text: &str) -> Vec<Found> {
+    let mut ports = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        if !words
+            .next()
+            .is_some_and(|w| w.eq_ignore_ascii_case("EXPOSE"))
+        {
+            continue;
+        }
+        for word in words {
+            let (port, protocol) = match word.split_once('/') {
+                Some((port, "udp")) => (port, Protocol::Udp),
+                Some((port, _)) => (port, Protocol::Tcp),
+                None => (word, Protocol::Tcp),
+            };
+            if let Ok(port) = port.parse::<u16>() {
+                ports.push(found(i + 1, port, protocol, "EXPOSE"));
+            }
+        }
+    }
+    ports
+}
+
+/// Pair each declared port with its current listeners
+pub fn check(declared: Vec<Declared>, entries: &[PortEntry]) -> Vec<Finding> {
+    declared
+        .into_iter()
+        .map(|declared| {
+            let owners: Vec<PortEntry> = entries
+                .iter()
+                .filter(|e| e.port == declared.port && e.protocol == declared.protocol)
+                .cloned()
+                .collect();
+            Finding {
+                taken: !owners.is_empty(),
+                declared,
+                owners,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(found: Vec<Found>) -> Vec<(usize, u16, String)> {
+        found
+            .into_iter()
+            .map(|f| (f.line, f.port, f.context))
+            .collect()
+    }
+
+    #[test]
+    fn test_origin() {
+        assert_eq!(origin(".env.local"), Some(Origin::Env));
+        assert_eq!(origin("docker-compose.override.yml"), Some(Origin::Compose));
+        assert_eq!(origin("compose.yaml"), Some(Origin::Compose));
+        assert_eq!(origin("api.Dockerfile"), Some(Origin::Dockerfile));
+        assert_eq!(origin("Procfile.dev"), Some(Origin::Procfile));
+        assert_eq!(origin(".envrc"), None);
+        assert_eq!(origin("package-lock.json"), None);
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let text = "# ports\nPORT=3000\nexport API_PORT=\"4000\" # api\nDB_HOST=localhost\nREPORT=5\nSUPPORT_PORT=\n";
+        assert_eq!(
+            summary(parse_env(text)),
+            vec![
+                (2, 3000, "PORT".to_string()),
+                (3, 4000, "API_PORT".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_compose() {
+        let text = r#"
+services:
+  web:
+    image: nginx
+    ports:
+      - "8080:80"
+      - 127.0.0.1:8443:443
+      - "9000"
+    environment:
+      - PORT=80
+  db:
+    image: postgres
+    ports:
+      - "${DB_PORT:-5432}:5432"
+      - target: 53
+        published: 5353
+        protocol: udp
+  workers:
+    ports:
+      - "7000-7002:7000-7002"
+volumes:
+  data:
+"#;
+        let found = parse_compose(text);
+        let ports: Vec<(u16, Protocol, &str)> = found
+            .iter()
+            .map(|f| (f.port, f.protocol, f.context.as_str()))
+            .collect();
+        assert_eq!(
+            ports,
+            vec![
+                (8080, Protocol::Tcp, "web"),
+                (8443, Protocol::Tcp, "web"),
+                (5432, Protocol::Tcp, "db"),
+                (5353, Protocol::Udp, "db"),
+                (7000, Protocol::Tcp, "workers"),
+                (7001, Protocol::Tcp, "workers"),
+                (7002, Protocol::Tcp, "workers"),
+            ]
+        );
+        assert_eq!(found[0].line, 6);
+    }
+
+    #[test]
+    fn test_compose_mapping() {
+        assert_eq!(compose_mapping("\"53:53/udp\""), (vec![53], Protocol::Udp));
+        assert_eq!(compose_mapping("3000"), (vec![], Protocol::Tcp));
+        assert_eq!(
+            compose_mapping("${WEB_PORT-8000}:80"),
+            (vec![8000], Protocol::Tcp)
+        );
+        assert_eq!(compose_mapping("${WEB_PORT}:80"), (vec![], Protocol::Tcp));
+        assert_eq!(
+            compose_mapping("\"[::1]:5432:5432\""),
+            (vec![5432], Protocol::Tcp)
+        );
+        assert_eq!(compose_mapping("[::]:53:53/udp"), (vec![53], Protocol::Udp));
+        assert_eq!(
+            compose_mapping("8000-8002:8000-8002"),
+            (vec![8000, 8001, 8002], Protocol::Tcp)
+        );
+        assert_eq!(compose_mapping("1-65535:1-65535"), (vec![], Protocol::Tcp));
+    }
+
+    #[test]
+    fn test_parse_procfile() {
+        let text = "web: bundle exec rails server -p 3000\nworker: sidekiq\napi: gunicorn --bind 0.0.0.0:8000 app:app\n# old: node --port=9000\n";
+        assert_eq!(
+            summary(parse_procfile(text)),
+            vec![(1, 3000, "web".to_string()), (3, 8000, "api".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_package_json() {
+        let text = r#"{
+  "name": "app",
+  "scripts": {
+    "dev": "vite --port 5173",
+    "start": "PORT=3000 node server.js",
+    "test": "vitest"
+  }
+}"#;
+        assert_eq!(
+            summary(parse_package_json(text)),
+            vec![(4, 5173, "dev".to_string()), (5, 3000, "start".to_string())]
+        );
+        assert!(parse_package_json("not json").is_empty());
+    }
+
+    #[test]
+    fn test_parse_dockerfile() {
+        let text = "FROM node:20\nEXPOSE 3000 9229/tcp\nexpose 53/udp\nRUN echo EXPOSE 1\n";
+        let found = parse_dockerfile(text);
+        let ports: Vec<(usize, u16, Protocol)> =
+            found.iter().map(|f| (f.line, f.port, f.protocol)).collect();
+        assert_eq!(
+            ports,
+            vec![
+                (2, 3000, Protocol::Tcp),
+                (2, 9229, Protocol::Tcp),
+                (3, 53, Protocol::Udp)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_and_check() {
+        let root =
+            std::env::temp_dir().join(format!("portdetective-project-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("api")).unwrap();
+        fs::create_dir_all(root.join("node_modules/dep")).unwrap();
+        fs::write(root.join(".env"), "PORT=3000\n").unwrap();
+        fs::write(root.join("api/Dockerfile"), "EXPOSE 8080\n").unwrap();
+        fs::write(root.join("node_modules/dep/.env"), "PORT=1\n").unwrap();
+
+        let declared = scan(&root).unwrap();
+        let files: Vec<(String, u16)> = declared
+            .iter()
+            .map(|d| (d.file.display().to_string(), d.port))
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                (".env".to_string(), 3000),
+                ("api/Dockerfile".to_string(), 8080)
+            ]
+        );
+
+        let owner = PortEntry {
+            port: 3000,
+            protocol: Protocol::Tcp,
+            pid: 42,
+            name: "node".to_string(),
+            user: "dev".to_string(),
+            command: "node server.js".to_string(),
+            address: "0.0.0.0".to_string(),
+        };
+        let findings = check(declared.clone(), std::slice::from_ref(&owner));
+        assert!(findings[0].taken);
+        assert_eq!(findings[0].owners[0].pid, 42);
+        assert!(!findings[1].taken);
+
+        // A socket whose owner can't be inspected still takes the port
+        let hidden = PortEntry {
+            port: 8080,
+            pid: 0,
+            name: "unknown".to_string(),
+            user: "unknown".to_string(),
+            command: String::new(),
+            ..owner
+        };
+        let findings = check(declared, &[hidden]);
+        assert!(findings[1].taken);
+        assert!(findings[1].owners[0].is_unknown());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
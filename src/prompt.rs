@@ -0,0 +1,183 @@
+//! Interactive confirmation for destructive actions
+
+use crate::cli::OnConflict;
+use crate::error::{PortDetectiveError, Result};
+use crate::model::KillTarget;
+use crate::output;
+use std::io::{self, IsTerminal, Write};
+
+/// What the user answered to "kill these processes?"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KillChoice {
+    All,
+    Select,
+    Abort,
+}
+
+/// Ask the user which of the targets to kill.
+///
+/// Returns the confirmed subset, or `Cancelled` if the user aborts.
+pub fn confirm_kill_targets(targets: Vec<KillTarget>) -> Result<Vec<KillTarget>> {
+    ensure_interactive()?;
+    for target in &targets {
+        output::print_kill_prompt(&target.ports, &target.process);
+    }
+
+    if let [target] = targets.as_slice() {
+        let answer = ask(&format!(
+            "Are you sure you want to kill PID {}? [y/N]: ",
+            target.process.pid
+        ))?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            output::print_kill_cancelled();
+            return Err(PortDetectiveError::Cancelled);
+        }
+        return Ok(targets);
+    }
+
+    let answer = ask(&format!(
+        "Kill all {} processes? [y]es / [s]elect / [N]o: ",
+        targets.len()
+    ))?;
+    match parse_choice(&answer) {
+        KillChoice::All => Ok(targets),
+        KillChoice::Abort => {
+            output::print_kill_cancelled();
+            Err(PortDetectiveError::Cancelled)
+        }
+        KillChoice::Select => {
+            output::print_kill_selection(&targets);
+            let answer = ask("Numbers to kill (e.g. 1 3), empty to abort: ")?;
+            match parse_selection(&answer, targets.len()) {
+                Some(picked) if !picked.is_empty() => Ok(targets
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| picked.contains(i))
+                    .map(|(_, t)| t)
+                    .collect()),
+                _ => {
+                    output::print_kill_cancelled();
+                    Err(PortDetectiveError::Cancelled)
+                }
+            }
+        }
+    }
+}
+
+/// Ask for a single yes/no confirmation before a bulk kill
+pub fn confirm_bulk_kill(count: usize) -> Result<()> {
+    ensure_interactive()?;
+    let answer = ask(&format!("Kill these {} process(es)? [y/N]: ", count))?;
+    if answer.trim().eq_ignore_ascii_case("y") {
+        Ok(())
+    } else {
+        output::print_kill_cancelled();
+        Err(PortDetectiveError::Cancelled)
+    }
+}
+
+/// Ask how to resolve a busy port before `run` launches its command
+pub fn choose_on_conflict() -> Result<OnConflict> {
+    ensure_interactive()?;
+    let answer = ask("[k]ill it / use the [n]ext free port / [A]bort: ")?;
+    match parse_on_conflict(&answer) {
+        OnConflict::Abort => {
+            output::print_kill_cancelled();
+            Err(PortDetectiveError::Cancelled)
+        }
+        choice => Ok(choice),
+    }
+}
+
+/// Fail instead of reading an answer from a pipe or closed stdin
+fn ensure_interactive() -> Result<()> {
+    if io::stdin().is_terminal() {
+        Ok(())
+    } else {
+        Err(PortDetectiveError::NotInteractive)
+    }
+}
+
+/// Print a question and read one line of input, treating end of input as a refusal
+fn ask(question: &str) -> Result<String> {
+    print!("{}", question);
+    let _ = io::stdout().flush();
+
+    let mut input = String::new();
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => Err(PortDetectiveError::Cancelled),
+        Ok(_) => Ok(input),
+    }
+}
+
+fn parse_choice(input: &str) -> KillChoice {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" | "a" | "all" => KillChoice::All,
+        "s" | "select" => KillChoice::Select,
+        _ => KillChoice::Abort,
+    }
+}
+
+fn parse_on_conflict(input: &str) -> OnConflict {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "k" | "kill" => OnConflict::Kill,
+        "n" | "next" => OnConflict::Next,
+        _ => OnConflict::Abort,
+    }
+}
+
+/// Parse 1-based, comma- or space-separated indices into 0-based ones.
+///
+/// Returns `None` if any entry is not a number in `1..=count`.
+fn parse_selection(input: &str, count: usize) -> Option<Vec<usize>> {
+    let mut picked = Vec::new();
+    for token in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|t| !t.is_empty())
+    {
+        let n: usize = token.parse().ok()?;
+        if n == 0 || n > count {
+            return None;
+        }
+        if !picked.contains(&(n - 1)) {
+            picked.push(n - 1);
+        }
+    }
+    Some(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_on_conflict() {
+        assert_eq!(parse_on_conflict("k\n"), OnConflict::Kill);
+        assert_eq!(parse_on_conflict("Next"), OnConflict::Next);
+        assert_eq!(parse_on_conflict(""), OnConflict::Abort);
+        assert_eq!(parse_on_conflict("x"), OnConflict::Abort);
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("y\n"), KillChoice::All);
+        assert_eq!(parse_choice("YES"), KillChoice::All);
+        assert_eq!(parse_choice("s"), KillChoice::Select);
+        assert_eq!(parse_choice("\n"), KillChoice::Abort);
+        assert_eq!(parse_choice("nope"), KillChoice::Abort);
+    }
+
+    #[test]
+    fn test_parse_selection_valid() {
+        assert_eq!(parse_selection("1 3", 3), Some(vec![0, 2]));
+        assert_eq!(parse_selection("2,1, 2\n", 3), Some(vec![1, 0]));
+        assert_eq!(parse_selection("\n", 3), Some(vec![]));
+    }
+
+    #[test]
+    fn test_parse_selection_rejects_out_of_range() {
+        assert_eq!(parse_selection("0", 3), None);
+        assert_eq!(parse_selection("4", 3), None);
+        assert_eq!(parse_selection("1 x", 3), None);
+    }
+}
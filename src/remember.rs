@@ -0,0 +1,304 @@
+//! Snapshots of killed processes, so `restart` can start them again as they were
+
+use crate::error::{PortDetectiveError, Result};
+use crate::model::ProcessInfo;
+use crate::state;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Environment variable names containing any of these are treated as secrets
+pub const SECRET_MARKERS: &[&str] = &[
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "PRIVATE_KEY",
+    "ACCESS_KEY",
+    "CREDENTIAL",
+    "COOKIE",
+];
+
+/// Markers too short to match inside a word: `AUTH` must not catch `XAUTHORITY`
+pub const SECRET_WORDS: &[&str] = &["AUTH", "SESSION"];
+
+/// Variables that look secret by name but only point at sockets or files
+/// a restarted GUI or dbus client needs
+pub const NOT_SECRET: &[&str] = &[
+    "DBUS_SESSION_BUS_ADDRESS",
+    "SSH_AUTH_SOCK",
+    "XDG_SESSION_ID",
+    "XDG_SESSION_TYPE",
+    "XDG_SESSION_CLASS",
+    "XDG_SESSION_DESKTOP",
+];
+
+/// Oldest snapshots are dropped beyond this many
+const MAX_REMEMBERED: usize = 50;
+
+/// Everything needed to start a killed process again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Remembered {
+    pub id: u32,
+    pub ports: Vec<u16>,
+    pub name: String,
+    pub user: String,
+    pub command: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    pub env: BTreeMap<String, String>,
+    /// Names of secret variables that were left out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub omitted_env: Vec<String>,
+    pub killed: DateTime<Local>,
+}
+
+/// The remembered processes on disk
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Store {
+    pub entries: Vec<Remembered>,
+}
+
+impl Store {
+    /// Default location of the store
+    pub fn path() -> PathBuf {
+        state::state_dir().join("remembered.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        state::load(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        state::save(path, self)
+    }
+
+    /// Add a snapshot under a fresh ID and return it
+    pub fn add(&mut self, mut entry: Remembered) -> u32 {
+        entry.id = self.entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        let id = entry.id;
+        self.entries.push(entry);
+        if self.entries.len() > MAX_REMEMBERED {
+            let excess = self.entries.len() - MAX_REMEMBERED;
+            self.entries.drain(..excess);
+        }
+        id
+    }
+
+    /// The most recent snapshot for a port, or else the snapshot with that ID
+    pub fn find(&self, target: u32) -> Option<&Remembered> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.ports.iter().any(|&p| u32::from(p) == target))
+            .or_else(|| self.entries.iter().find(|e| e.id == target))
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.entries.retain(|e| e.id != id);
+    }
+}
+
+/// Whether an environment variable name looks like it holds a secret
+pub fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    if NOT_SECRET.contains(&key.as_str()) {
+        return false;
+    }
+    let words: Vec<&str> = key.split(|c: char| !c.is_ascii_alphanumeric()).collect();
+    SECRET_MARKERS.iter().any(|m| key.contains(m)) || SECRET_WORDS.iter().any(|w| words.contains(w))
+}
+
+/// Snapshot a process about to be killed.
+///
+/// Secret variables are left out unless `keep_secrets` is set; their names
+/// are kept so the user knows what to supply again.
+pub fn snapshot(
+    info: &ProcessInfo,
+    ports: &[u16],
+    environ: HashMap<String, String>,
+    keep_secrets: bool,
+) -> Remembered {
+    let mut env = BTreeMap::new();
+    let mut omitted_env = Vec::new();
+    for (key, value) in environ {
+        if !keep_secrets && is_secret(&key) {
+            omitted_env.push(key);
+        } else {
+            env.insert(key, value);
+        }
+    }
+    omitted_env.sort();
+
+    Remembered {
+        id: 0,
+        ports: ports.to_vec(),
+        name: info.name.clone(),
+        user: info.user.clone(),
+        command: info.command.clone(),
+        cwd: info.cwd.clone(),
+        env,
+        omitted_env,
+        killed: Local::now(),
+    }
+}
+
+/// Start a remembered process again, detached in its own process group.
+///
+/// Output goes to a log file under the state directory. Returns the new PID
+/// and the log path.
+pub fn relaunch(entry: &Remembered) -> Result<(u32, PathBuf)> {
+    let failed = |reason: String| PortDetectiveError::RestartFailed {
+        name: entry.name.clone(),
+        reason,
+    };
+    let (program, args) = entry
+        .command
+        .split_first()
+        .ok_or_else(|| failed("no command line was recorded".to_string()))?;
+
+    let log_path = state::state_dir()
+        .join("logs")
+        .join(format!("{}-{}.log", entry.name, entry.id));
+    if let Some(dir) = log_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| failed(e.to_string()))?;
+    }
+    let log = fs::File::create(&log_path).map_err(|e| failed(e.to_string()))?;
+    let log_err = log.try_clone().map_err(|e| failed(e.to_string()))?;
+
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .env_clear()
+        .envs(&entry.env)
+        .stdin(Stdio::null())
+        .stdout(log)
+        .stderr(log_err);
+    if let Some(cwd) = &entry.cwd {
+        command.current_dir(cwd);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    // Not waited on: the child outlives us and is reparented when we exit
+    let child = command.spawn().map_err(|e| failed(e.to_string()))?;
+    Ok((child.id(), log_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Protocol;
+
+    fn info() -> ProcessInfo {
+        ProcessInfo {
+            pid: 42,
+            name: "node".to_string(),
+            user: "dev".to_string(),
+            uid: None,
+            command: vec!["node".to_string(), "server.js".to_string()],
+            cwd: Some(PathBuf::from("/srv/app")),
+            exe: None,
+            parent_pid: None,
+            parent_name: None,
+            started: None,
+            manager: None,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    fn environ() -> HashMap<String, String> {
+        HashMap::from([
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("NODE_ENV".to_string(), "development".to_string()),
+            ("GITHUB_TOKEN".to_string(), "ghp_xxx".to_string()),
+            ("db_password".to_string(), "hunter2".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_is_secret() {
+        assert!(is_secret("AWS_SECRET_ACCESS_KEY"));
+        assert!(is_secret("GITHUB_TOKEN"));
+        assert!(is_secret("db_password"));
+        assert!(is_secret("STRIPE_API_KEY"));
+        assert!(!is_secret("PATH"));
+        assert!(!is_secret("NODE_ENV"));
+    }
+
+    #[test]
+    fn test_short_markers_match_whole_words() {
+        assert!(is_secret("BASIC_AUTH"));
+        assert!(is_secret("auth"));
+        assert!(is_secret("RAILS_SESSION_KEY"));
+        assert!(!is_secret("XAUTHORITY"));
+        assert!(!is_secret("DBUS_SESSION_BUS_ADDRESS"));
+        assert!(!is_secret("SSH_AUTH_SOCK"));
+        assert!(!is_secret("XDG_SESSION_TYPE"));
+        assert!(!is_secret("AUTHOR_NAME"));
+    }
+
+    #[test]
+    fn test_snapshot_leaves_out_secrets() {
+        let entry = snapshot(&info(), &[3000], environ(), false);
+        assert_eq!(entry.command, vec!["node", "server.js"]);
+        assert_eq!(entry.cwd, Some(PathBuf::from("/srv/app")));
+        assert_eq!(entry.env.len(), 2);
+        assert!(entry.env.contains_key("PATH"));
+        assert_eq!(entry.omitted_env, vec!["GITHUB_TOKEN", "db_password"]);
+    }
+
+    #[test]
+    fn test_snapshot_can_keep_secrets() {
+        let entry = snapshot(&info(), &[3000], environ(), true);
+        assert_eq!(entry.env.len(), 4);
+        assert!(entry.omitted_env.is_empty());
+    }
+
+    #[test]
+    fn test_store_ids_and_lookup() {
+        let mut store = Store::default();
+        let first = store.add(snapshot(&info(), &[3000], HashMap::new(), false));
+        let second = store.add(snapshot(&info(), &[3000, 3001], HashMap::new(), false));
+        assert_eq!((first, second), (1, 2));
+
+        // Ports win over IDs, and the latest snapshot on a port wins
+        assert_eq!(store.find(3000).unwrap().id, 2);
+        assert_eq!(store.find(1).unwrap().id, 1);
+        assert!(store.find(9999).is_none());
+
+        store.remove(2);
+        assert_eq!(store.find(3000).unwrap().id, 1);
+        assert_eq!(
+            store.add(snapshot(&info(), &[8080], HashMap::new(), false)),
+            2
+        );
+    }
+
+    #[test]
+    fn test_store_is_capped() {
+        let mut store = Store::default();
+        for _ in 0..MAX_REMEMBERED + 5 {
+            store.add(snapshot(&info(), &[3000], HashMap::new(), false));
+        }
+        assert_eq!(store.entries.len(), MAX_REMEMBERED);
+        assert_eq!(store.entries[0].id, 6);
+    }
+
+    #[test]
+    fn test_relaunch_without_command_fails() {
+        let mut entry = snapshot(&info(), &[3000], HashMap::new(), false);
+        entry.command.clear();
+        assert!(matches!(
+            relaunch(&entry),
+            Err(PortDetectiveError::RestartFailed { .. })
+        ));
+    }
+}
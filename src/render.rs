@@ -0,0 +1,464 @@
+//! Renderers for the machine-readable output formats
+
+use crate::cli::OutputFormat;
+use crate::model::{KillResult, PortEntry, PortReport};
+use crate::sweep::Candidate;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::LazyLock;
+
+/// Plain scalars a YAML 1.1 reader would take for a bool, null or number
+static AMBIGUOUS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?xi)^(?:
+            y | n | yes | no | true | false | on | off | ~ | null
+          | [-+]? 0b [01_]+
+          | [-+]? 0x [0-9a-f_]+
+          | [-+]? [0-9][0-9_]* (?: :[0-5]?[0-9] )* (?: \.[0-9_]* )? (?: e[-+]?[0-9]+ )?
+          | [-+]? \.[0-9_]+ (?: e[-+]?[0-9]+ )?
+          | [-+]? \.inf | \.nan
+        )$",
+    )
+    .expect("valid regex")
+});
+
+/// A value that flattens into rows for the CSV, TSV and Markdown formats
+pub trait Rows {
+    const HEADERS: &'static [&'static str];
+
+    fn rows(&self) -> Vec<Vec<String>>;
+}
+
+impl<R: Rows> Rows for [R] {
+    const HEADERS: &'static [&'static str] = R::HEADERS;
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.iter().flat_map(Rows::rows).collect()
+    }
+}
+
+impl<R: Rows> Rows for Vec<R> {
+    const HEADERS: &'static [&'static str] = R::HEADERS;
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.as_slice().rows()
+    }
+}
+
+/// One row per process; a free port is a single row with empty process fields
+impl Rows for PortReport {
+    const HEADERS: &'static [&'static str] = &[
+        "port", "protocol", "status", "pid", "name", "user", "started", "command",
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let status = serde_json::to_value(self.status)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default();
+        if self.processes.is_empty() {
+            let mut row = vec![self.port.to_string(), self.protocol.to_string(), status];
+            row.resize(Self::HEADERS.len(), String::new());
+            return vec![row];
+        }
+        self.processes
+            .iter()
+            .map(|p| {
+                vec![
+                    self.port.to_string(),
+                    p.protocol.to_string(),
+                    status.clone(),
+                    p.pid.to_string(),
+                    p.name.clone(),
+                    p.user.clone(),
+                    p.started.map(|s| s.to_rfc3339()).unwrap_or_default(),
+                    p.command.join(" "),
+                ]
+            })
+            .collect()
+    }
+}
+
+impl Rows for PortEntry {
+    const HEADERS: &'static [&'static str] = &[
+        "port", "protocol", "pid", "name", "user", "address", "command",
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.port.to_string(),
+            self.protocol.to_string(),
+            self.pid.to_string(),
+            self.name.clone(),
+            self.user.clone(),
+            self.address.clone(),
+            self.command.clone(),
+        ]]
+    }
+}
+
+impl Rows for KillResult {
+    const HEADERS: &'static [&'static str] =
+        &["pid", "name", "ports", "signal", "via", "success", "error"];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let ports: Vec<String> = self.ports.iter().map(u16::to_string).collect();
+        vec![vec![
+            self.pid.to_string(),
+            self.name.clone(),
+            ports.join(" "),
+            self.signal.clone(),
+            self.via.clone().unwrap_or_default(),
+            self.success.to_string(),
+            self.error.clone().unwrap_or_default(),
+        ]]
+    }
+}
+
+impl Rows for Candidate {
+    const HEADERS: &'static [&'static str] = &[
+        "ports", "pid", "name", "user", "started", "reasons", "command",
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        let process = &self.target.process;
+        let ports: Vec<String> = self.target.ports.iter().map(u16::to_string).collect();
+        vec![vec![
+            ports.join(" "),
+            process.pid.to_string(),
+            process.name.clone(),
+            process.user.clone(),
+            process.started.map(|s| s.to_rfc3339()).unwrap_or_default(),
+            self.reasons.join("; "),
+            process.command.join(" "),
+        ]]
+    }
+}
+
+/// Render `value` in `format`. The table format belongs to `output`, so it
+/// renders as JSON here.
+pub fn render<T: Serialize + Rows + ?Sized>(value: &T, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Table | OutputFormat::Json => {
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| "null".to_string())
+        }
+        OutputFormat::Ndjson => ndjson(&to_value(value)),
+        OutputFormat::Yaml => yaml(&to_value(value)),
+        OutputFormat::Csv => delimited(T::HEADERS, &value.rows(), ','),
+        OutputFormat::Tsv => delimited(T::HEADERS, &value.rows(), '\t'),
+        OutputFormat::Markdown => markdown(T::HEADERS, &value.rows()),
+    }
+}
+
+fn to_value<T: Serialize + ?Sized>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// One compact JSON value per line; arrays yield one line per element
+fn ndjson(value: &Value) -> String {
+    let items = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    items
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// RFC 4180 style for commas; tabs and newlines are flattened for TSV
+fn delimited(headers: &[&str], rows: &[Vec<String>], separator: char) -> String {
+    let field = |s: &str| -> String {
+        if separator == '\t' {
+            s.replace(['\t', '\n', '\r'], " ")
+        } else if s.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+    let line = |cells: Vec<String>| cells.join(&separator.to_string());
+
+    let mut lines = vec![line(headers.iter().map(|h| field(h)).collect())];
+    lines.extend(
+        rows.iter()
+            .map(|row| line(row.iter().map(|c| field(c)).collect())),
+    );
+    lines.join("\n")
+}
+
+/// A GitHub-flavored Markdown table
+fn markdown(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let cell = |s: &str| s.replace('|', "\\|").replace(['\n', '\r'], " ");
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+
+    let mut lines = vec![
+        line(headers.iter().map(|h| cell(h)).collect()),
+        line(headers.iter().map(|_| "---".to_string()).collect()),
+    ];
+    lines.extend(
+        rows.iter()
+            .map(|row| line(row.iter().map(|c| cell(c)).collect())),
+    );
+    lines.join("\n")
+}
+
+/// Block-style YAML for a JSON value
+fn yaml(value: &Value) -> String {
+    match value {
+        Value::Object(map) if !map.is_empty() => yaml_block(value, 0),
+        Value::Array(items) if !items.is_empty() => yaml_block(value, 0),
+        scalar => yaml_scalar(scalar),
+    }
+}
+
+fn is_nested(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => !map.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => false,
+    }
+}
+
+fn yaml_block(value: &Value, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut lines = Vec::new();
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                if is_nested(v) {
+                    lines.push(format!("{}{}:", pad, yaml_string(key)));
+                    lines.push(yaml_block(v, indent + 2));
+                } else {
+                    lines.push(format!("{}{}: {}", pad, yaml_string(key), yaml_scalar(v)));
+                }
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                if is_nested(v) {
+                    // The item's first line moves up onto the dash
+                    let block = yaml_block(v, indent + 2);
+                    lines.push(format!("{}- {}", pad, &block[indent + 2..]));
+                } else {
+                    lines.push(format!("{}- {}", pad, yaml_scalar(v)));
+                }
+            }
+        }
+        scalar => lines.push(format!("{}{}", pad, yaml_scalar(scalar))),
+    }
+    lines.join("\n")
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => yaml_string(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+/// Plain when unambiguous, otherwise double-quoted (JSON escapes are valid YAML)
+fn yaml_string(s: &str) -> String {
+    let special = s.is_empty()
+        || s.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c) || c.is_whitespace())
+        || s.ends_with(|c: char| c == ':' || c.is_whitespace())
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.contains(|c: char| c.is_control());
+    if special || AMBIGUOUS.is_match(s) || s.parse::<f64>().is_ok() {
+        serde_json::to_string(s).unwrap_or_default()
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{KillTarget, ProcessInfo, Protocol};
+
+    fn entry(port: u16, name: &str, command: &str) -> PortEntry {
+        PortEntry {
+            port,
+            protocol: Protocol::Tcp,
+            pid: 4242,
+            name: name.to_string(),
+            user: "dev".to_string(),
+            command: command.to_string(),
+            address: "127.0.0.1".to_string(),
+        }
+    }
+
+    fn report() -> PortReport {
+        PortReport::in_use(
+            3000,
+            Protocol::Tcp,
+            vec![ProcessInfo {
+                pid: 4242,
+                name: "node".to_string(),
+                user: "dev".to_string(),
+                uid: None,
+                command: vec!["node".to_string(), "server.js".to_string()],
+                cwd: None,
+                exe: None,
+                parent_pid: None,
+                parent_name: None,
+                started: None,
+                manager: None,
+                protocol: Protocol::Tcp,
+            }],
+        )
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let entries = vec![entry(3000, "node", "node -e \"a, b\"")];
+        assert_eq!(
+            render(&entries, OutputFormat::Csv),
+            "port,protocol,pid,name,user,address,command\n\
+             3000,tcp,4242,node,dev,127.0.0.1,\"node -e \"\"a, b\"\"\""
+        );
+    }
+
+    #[test]
+    fn test_tsv_flattens_tabs() {
+        let entries = vec![entry(3000, "node", "node\tserver.js")];
+        let tsv = render(&entries, OutputFormat::Tsv);
+        assert_eq!(
+            tsv.lines().nth(1),
+            Some("3000\ttcp\t4242\tnode\tdev\t127.0.0.1\tnode server.js")
+        );
+    }
+
+    #[test]
+    fn test_markdown_table() {
+        let entries = vec![entry(3000, "node", "a | b")];
+        assert_eq!(
+            render(&entries, OutputFormat::Markdown),
+            "| port | protocol | pid | name | user | address | command |\n\
+             | --- | --- | --- | --- | --- | --- | --- |\n\
+             | 3000 | tcp | 4242 | node | dev | 127.0.0.1 | a \\| b |"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_one_line_per_item() {
+        let entries = vec![
+            entry(3000, "node", "node"),
+            entry(5432, "postgres", "postgres"),
+        ];
+        let ndjson = render(&entries, OutputFormat::Ndjson);
+        let lines: Vec<&str> = ndjson.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let back: PortEntry = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(back.port, 5432);
+
+        assert_eq!(render(&report(), OutputFormat::Ndjson).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_report_rows() {
+        let csv = render(&report(), OutputFormat::Csv);
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("3000,tcp,in_use,4242,node,dev,,node server.js")
+        );
+
+        let free = render(&PortReport::free(8080, Protocol::Tcp), OutputFormat::Csv);
+        assert_eq!(free.lines().nth(1), Some("8080,tcp,free,,,,,"));
+    }
+
+    #[test]
+    fn test_kill_result_rows() {
+        let results = vec![KillResult {
+            pid: 4242,
+            name: "node".to_string(),
+            ports: vec![3000, 3001],
+            signal: "SIGTERM".to_string(),
+            via: None,
+            open_connections: None,
+            success: false,
+            error: Some("Operation not permitted".to_string()),
+            respawns: Vec::new(),
+        }];
+        assert_eq!(
+            render(&results, OutputFormat::Tsv).lines().nth(1),
+            Some("4242\tnode\t3000 3001\tSIGTERM\t\tfalse\tOperation not permitted")
+        );
+    }
+
+    #[test]
+    fn test_sweep_candidate_rows() {
+        let candidates = vec![Candidate {
+            target: KillTarget {
+                process: report().processes[0].clone(),
+                ports: vec![3000, 9229],
+            },
+            reasons: vec!["idle 2h".to_string(), "no terminal".to_string()],
+        }];
+        assert_eq!(
+            render(&candidates, OutputFormat::Csv),
+            "ports,pid,name,user,started,reasons,command\n\
+             3000 9229,4242,node,dev,,idle 2h; no terminal,node server.js"
+        );
+    }
+
+    #[test]
+    fn test_yaml() {
+        assert_eq!(
+            render(&report(), OutputFormat::Yaml),
+            "port: 3000\n\
+             protocol: tcp\n\
+             status: in_use\n\
+             processes:\n  \
+               - pid: 4242\n    \
+                 name: node\n    \
+                 user: dev\n    \
+                 command:\n      \
+                   - node\n      \
+                   - server.js\n    \
+                 protocol: tcp"
+        );
+        assert_eq!(render(&Vec::<PortEntry>::new(), OutputFormat::Yaml), "[]");
+    }
+
+    #[test]
+    fn test_yaml_quotes_ambiguous_strings() {
+        assert_eq!(yaml_string("node"), "node");
+        assert_eq!(yaml_string("3000"), "\"3000\"");
+        assert_eq!(yaml_string("true"), "\"true\"");
+        assert_eq!(yaml_string("-e"), "\"-e\"");
+        assert_eq!(yaml_string("a: b"), "\"a: b\"");
+        assert_eq!(yaml_string(""), "\"\"");
+        assert_eq!(yaml_string("line\nbreak"), "\"line\\nbreak\"");
+    }
+
+    #[test]
+    fn test_yaml_quotes_trailing_colon_and_comments() {
+        assert_eq!(yaml_string("foo:"), "\"foo:\"");
+        assert_eq!(yaml_string("a # b"), "\"a # b\"");
+        assert_eq!(yaml_string("localhost:3000"), "localhost:3000");
+        assert_eq!(yaml_string("node#1"), "node#1");
+    }
+
+    #[test]
+    fn test_yaml_quotes_yaml_1_1_bools_nulls_and_numbers() {
+        for s in ["y", "Y", "n", "on", "Off", "NO", "~", "Null"] {
+            assert_eq!(yaml_string(s), format!("\"{}\"", s), "{}", s);
+        }
+        for s in [
+            "0x1F", "1e3", "1_000", "0b101", "017", "1:30", "-.5", ".inf", ".NaN",
+        ] {
+            assert_eq!(yaml_string(s), format!("\"{}\"", s), "{}", s);
+        }
+        for s in ["yarn", "node2", "v1.2", "0x", "e3"] {
+            assert_eq!(yaml_string(s), s, "{}", s);
+        }
+    }
+}
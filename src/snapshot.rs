@@ -0,0 +1,201 @@
+//! Saving the port list and comparing it with a later one
+
+use crate::error::{PortDetectiveError, Result};
+use crate::model::{PortEntry, Protocol};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// The listening ports at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken: DateTime<Local>,
+    pub host: String,
+    pub listeners: Vec<PortEntry>,
+}
+
+impl Snapshot {
+    pub fn new(listeners: Vec<PortEntry>, host: String) -> Self {
+        Snapshot {
+            taken: Local::now(),
+            host,
+            listeners,
+        }
+    }
+
+    /// Read a snapshot written by `portdetective snapshot`
+    pub fn read(path: &Path) -> Result<Self> {
+        let snapshot_error = |reason: String| PortDetectiveError::Snapshot {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let json = fs::read_to_string(path).map_err(|e| snapshot_error(e.to_string()))?;
+        serde_json::from_str(&json).map_err(|e| snapshot_error(e.to_string()))
+    }
+}
+
+/// A port held both before and after, by different processes or addresses
+#[derive(Debug, Clone, Serialize)]
+pub struct Changed {
+    pub port: u16,
+    pub protocol: Protocol,
+    /// Which of `pid`, `command` and `address` differ
+    pub fields: Vec<&'static str>,
+    pub before: Vec<PortEntry>,
+    pub after: Vec<PortEntry>,
+}
+
+/// Differences between two port lists
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Diff {
+    pub added: Vec<PortEntry>,
+    pub removed: Vec<PortEntry>,
+    pub changed: Vec<Changed>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare listeners port by port.
+///
+/// Listeners are matched on port and protocol, so a restarted server shows up
+/// as changed rather than as one removal and one addition.
+pub fn diff(before: &[PortEntry], after: &[PortEntry]) -> Diff {
+    let mut ports: BTreeMap<(u16, Protocol), (Vec<PortEntry>, Vec<PortEntry>)> = BTreeMap::new();
+    for entry in before {
+        ports.entry(entry.key()).or_default().0.push(entry.clone());
+    }
+    for entry in after {
+        ports.entry(entry.key()).or_default().1.push(entry.clone());
+    }
+
+    let mut diff = Diff::default();
+    for ((port, protocol), (before, after)) in ports {
+        if before.is_empty() {
+            diff.added.extend(after);
+            continue;
+        }
+        if after.is_empty() {
+            diff.removed.extend(before);
+            continue;
+        }
+
+        let mut fields = Vec::new();
+        if differs(&before, &after, |e| e.pid.to_string()) {
+            fields.push("pid");
+        }
+        if differs(&before, &after, |e| e.command.clone()) {
+            fields.push("command");
+        }
+        if differs(&before, &after, |e| e.address.clone()) {
+            fields.push("address");
+        }
+        if !fields.is_empty() {
+            diff.changed.push(Changed {
+                port,
+                protocol,
+                fields,
+                before,
+                after,
+            });
+        }
+    }
+    diff
+}
+
+fn differs(
+    before: &[PortEntry],
+    after: &[PortEntry],
+    field: impl Fn(&PortEntry) -> String,
+) -> bool {
+    let values = |entries: &[PortEntry]| {
+        let mut values: Vec<String> = entries.iter().map(&field).collect();
+        values.sort();
+        values.dedup();
+        values
+    };
+    values(before) != values(after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(port: u16, pid: u32, command: &str) -> PortEntry {
+        PortEntry {
+            port,
+            protocol: Protocol::Tcp,
+            pid,
+            name: command.split(' ').next().unwrap().to_string(),
+            user: "dev".to_string(),
+            command: command.to_string(),
+            address: "0.0.0.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_identical_lists() {
+        let list = vec![entry(22, 1, "sshd"), entry(5432, 2, "postgres")];
+        assert!(diff(&list, &list).is_empty());
+    }
+
+    #[test]
+    fn test_added_and_removed() {
+        let before = vec![entry(22, 1, "sshd"), entry(5432, 2, "postgres")];
+        let after = vec![entry(22, 1, "sshd"), entry(3000, 3, "node server.js")];
+
+        let diff = diff(&before, &after);
+        assert_eq!(diff.added, vec![entry(3000, 3, "node server.js")]);
+        assert_eq!(diff.removed, vec![entry(5432, 2, "postgres")]);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn test_changed_fields() {
+        let before = vec![
+            entry(3000, 1, "node server.js"),
+            entry(8080, 5, "java -jar a.jar"),
+        ];
+        let mut rebound = entry(8080, 5, "java -jar a.jar");
+        rebound.address = "127.0.0.1".to_string();
+        let after = vec![entry(3000, 2, "node server.js --inspect"), rebound];
+
+        let diff = diff(&before, &after);
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].port, 3000);
+        assert_eq!(diff.changed[0].fields, vec!["pid", "command"]);
+        assert_eq!(diff.changed[1].fields, vec!["address"]);
+    }
+
+    #[test]
+    fn test_worker_order_does_not_matter() {
+        let before = vec![entry(80, 10, "nginx"), entry(80, 11, "nginx")];
+        let after = vec![entry(80, 11, "nginx"), entry(80, 10, "nginx")];
+        assert!(diff(&before, &after).is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "portdetective-snapshot-{}.json",
+            std::process::id()
+        ));
+        let snapshot = Snapshot::new(vec![entry(22, 1, "sshd")], "build-01".to_string());
+        fs::write(&path, serde_json::to_string_pretty(&snapshot).unwrap()).unwrap();
+
+        let read = Snapshot::read(&path).unwrap();
+        assert_eq!(read.listeners, snapshot.listeners);
+        assert_eq!(read.host, "build-01");
+        let _ = fs::remove_file(&path);
+
+        assert!(matches!(
+            Snapshot::read(&path),
+            Err(PortDetectiveError::Snapshot { .. })
+        ));
+    }
+}
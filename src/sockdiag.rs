@@ -0,0 +1,217 @@
+//! Kernel-filtered socket queries over netlink sock_diag
+
+use crate::error::{PortDetectiveError, Result};
+use netstat2::{ProtocolSocketInfo, SocketInfo, TcpSocketInfo, TcpState, UdpSocketInfo};
+use nix::sys::socket::{
+    AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType, recv, sendto, socket,
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::AsRawFd;
+
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const INET_DIAG_REQ_BYTECODE: u16 = 1;
+const INET_DIAG_BC_S_GE: u8 = 2;
+const INET_DIAG_BC_S_LE: u8 = 3;
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+pub const IPPROTO_TCP: u8 = 6;
+pub const IPPROTO_UDP: u8 = 17;
+
+const NLMSG_HEADER_LEN: usize = 16;
+/// `struct inet_diag_msg`: family, state, timer, retrans, sockid, then five u32s
+const DIAG_MSG_LEN: usize = 72;
+
+/// Every socket of `protocol` whose local port is `port`, in any state and
+/// either address family. The kernel applies the port filter, so the cost
+/// does not grow with the number of other sockets on the machine.
+pub fn sockets_on_port(port: u16, protocol: u8) -> Result<Vec<SocketInfo>> {
+    let mut sockets = Vec::new();
+    for family in [AF_INET, AF_INET6] {
+        sockets.extend(query(family, protocol, port)?);
+    }
+    Ok(sockets)
+}
+
+fn query(family: u8, protocol: u8, port: u16) -> Result<Vec<SocketInfo>> {
+    let error = |e: nix::Error| PortDetectiveError::NetworkError(format!("sock_diag: {}", e));
+    let fd = socket(
+        AddressFamily::Netlink,
+        SockType::Datagram,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkSockDiag,
+    )
+    .map_err(error)?;
+    sendto(
+        fd.as_raw_fd(),
+        &request(family, protocol, port),
+        &NetlinkAddr::new(0, 0),
+        MsgFlags::empty(),
+    )
+    .map_err(error)?;
+
+    let mut sockets = Vec::new();
+    let mut buf = vec![0u8; 32 * 1024];
+    loop {
+        let len = recv(fd.as_raw_fd(), &mut buf, MsgFlags::empty()).map_err(error)?;
+        let mut messages = &buf[..len];
+        while messages.len() >= NLMSG_HEADER_LEN {
+            let msg_len = u32_at(messages, 0) as usize;
+            let kind = u16::from_ne_bytes([messages[4], messages[5]]);
+            if msg_len < NLMSG_HEADER_LEN || msg_len > messages.len() {
+                return Err(PortDetectiveError::NetworkError(
+                    "sock_diag: truncated reply".to_string(),
+                ));
+            }
+            match kind {
+                NLMSG_DONE => return Ok(sockets),
+                NLMSG_ERROR => {
+                    let errno = i32::from_ne_bytes(
+                        messages[NLMSG_HEADER_LEN..NLMSG_HEADER_LEN + 4]
+                            .try_into()
+                            .unwrap_or_default(),
+                    );
+                    return Err(error(nix::Error::from_raw(-errno)));
+                }
+                SOCK_DIAG_BY_FAMILY => {
+                    if let Some(socket) = parse(&messages[NLMSG_HEADER_LEN..msg_len], protocol) {
+                        sockets.push(socket);
+                    }
+                }
+                _ => {}
+            }
+            messages = &messages[align(msg_len).min(messages.len())..];
+        }
+    }
+}
+
+/// A dump request for one family and protocol, with a bytecode filter that
+/// keeps only sockets whose source port is exactly `port`
+fn request(family: u8, protocol: u8, port: u16) -> Vec<u8> {
+    // Each comparison is two ops: the test and its operand. A match jumps to
+    // the next test (or the end, which accepts); a miss jumps past the end.
+    let mut bytecode = Vec::with_capacity(16);
+    for (code, remaining) in [(INET_DIAG_BC_S_GE, 16u16), (INET_DIAG_BC_S_LE, 8)] {
+        bytecode.extend([code, 8]);
+        bytecode.extend((remaining + 4).to_ne_bytes());
+        bytecode.extend([0, 0]);
+        bytecode.extend(port.to_ne_bytes());
+    }
+
+    let mut req = Vec::with_capacity(56);
+    req.extend([family, protocol, 0, 0]);
+    req.extend(u32::MAX.to_ne_bytes()); // every state
+    req.extend([0u8; 48]); // inet_diag_sockid, unused for dumps
+
+    let attr_len = 4 + bytecode.len();
+    let total = NLMSG_HEADER_LEN + req.len() + attr_len;
+    let mut msg = Vec::with_capacity(total);
+    msg.extend((total as u32).to_ne_bytes());
+    msg.extend(SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    msg.extend((NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    msg.extend(1u32.to_ne_bytes()); // sequence
+    msg.extend(0u32.to_ne_bytes()); // port ID, filled in by the kernel
+    msg.extend(req);
+    msg.extend((attr_len as u16).to_ne_bytes());
+    msg.extend(INET_DIAG_REQ_BYTECODE.to_ne_bytes());
+    msg.extend(bytecode);
+    msg
+}
+
+/// Decode one `inet_diag_msg`
+fn parse(msg: &[u8], protocol: u8) -> Option<SocketInfo> {
+    if msg.len() < DIAG_MSG_LEN {
+        return None;
+    }
+    let family = msg[0];
+    let state = msg[1];
+    let local_port = u16::from_be_bytes([msg[4], msg[5]]);
+    let remote_port = u16::from_be_bytes([msg[6], msg[7]]);
+    let addr = |offset: usize| -> IpAddr {
+        let bytes: [u8; 16] = msg[offset..offset + 16].try_into().unwrap_or_default();
+        if family == AF_INET {
+            IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(bytes))
+        }
+    };
+    let (local_addr, remote_addr) = (addr(8), addr(24));
+
+    let protocol_socket_info = match protocol {
+        IPPROTO_TCP => ProtocolSocketInfo::Tcp(TcpSocketInfo {
+            local_addr,
+            local_port,
+            remote_addr,
+            remote_port,
+            state: TcpState::from(state),
+        }),
+        _ => ProtocolSocketInfo::Udp(UdpSocketInfo {
+            local_addr,
+            local_port,
+        }),
+    };
+    Some(SocketInfo {
+        protocol_socket_info,
+        associated_pids: Vec::new(),
+        inode: u32_at(msg, 68),
+        uid: u32_at(msg, 64),
+    })
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap_or_default())
+}
+
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, UdpSocket};
+
+    #[test]
+    fn test_request_filters_on_port_in_kernel() {
+        let msg = request(AF_INET, IPPROTO_TCP, 8080);
+        assert_eq!(msg.len(), 16 + 56 + 20);
+        assert_eq!(u32_at(&msg, 0) as usize, msg.len());
+        // The operand of both comparisons is the port
+        let bytecode = &msg[msg.len() - 16..];
+        assert_eq!(bytecode[0], INET_DIAG_BC_S_GE);
+        assert_eq!(u16::from_ne_bytes([bytecode[6], bytecode[7]]), 8080);
+        assert_eq!(bytecode[8], INET_DIAG_BC_S_LE);
+        assert_eq!(u16::from_ne_bytes([bytecode[14], bytecode[15]]), 8080);
+    }
+
+    #[test]
+    fn test_sockets_on_port_returns_only_that_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _other = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let sockets = sockets_on_port(port, IPPROTO_TCP).unwrap();
+        assert_eq!(sockets.len(), 1);
+        match &sockets[0].protocol_socket_info {
+            ProtocolSocketInfo::Tcp(tcp) => {
+                assert_eq!(tcp.local_port, port);
+                assert_eq!(tcp.state, TcpState::Listen);
+            }
+            other => panic!("expected TCP, got {:?}", other),
+        }
+        assert_ne!(sockets[0].inode, 0);
+    }
+
+    #[test]
+    fn test_sockets_on_port_sees_udp() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+
+        let sockets = sockets_on_port(port, IPPROTO_UDP).unwrap();
+        assert_eq!(sockets.len(), 1);
+        assert!(sockets_on_port(port, IPPROTO_TCP).unwrap().is_empty());
+    }
+}
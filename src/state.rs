@@ -0,0 +1,140 @@
+//! Local state files kept between runs
+
+use crate::error::{PortDetectiveError, Result};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory for portdetective's state: `$XDG_STATE_HOME/portdetective`,
+/// falling back to `~/.local/state/portdetective`
+pub fn state_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/state")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("portdetective")
+}
+
+/// Directory for user configuration: `$XDG_CONFIG_HOME/portdetective`,
+/// falling back to `~/.config/portdetective`
+pub fn config_dir() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("portdetective")
+}
+
+/// Directory for short-lived coordination files: `$XDG_RUNTIME_DIR/portdetective`,
+/// falling back to the state directory
+pub fn runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|v| !v.is_empty())
+        .map(|dir| PathBuf::from(dir).join("portdetective"))
+        .unwrap_or_else(state_dir)
+}
+
+/// Read a JSON state file, or the default value if it does not exist yet
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    read(path)
+}
+
+/// Read a JSON state file that must exist
+pub fn read<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let json = fs::read_to_string(path).map_err(|e| state_error(path, e))?;
+    serde_json::from_str(&json).map_err(|e| state_error(path, e))
+}
+
+/// Write a JSON state file readable only by the current user.
+///
+/// The file is written next to its final path and renamed into place, so a
+/// crash never leaves it half written.
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| state_error(path, e))?;
+    }
+    let json = serde_json::to_string_pretty(value).map_err(|e| state_error(path, e))?;
+
+    let tmp = path.with_extension("tmp");
+    write_private(&tmp, &(json + "\n")).map_err(|e| state_error(path, e))?;
+    fs::rename(&tmp, path).map_err(|e| state_error(path, e))
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    fs::write(path, contents)
+}
+
+fn state_error(path: &Path, e: impl std::fmt::Display) -> PortDetectiveError {
+    PortDetectiveError::State {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn scratch(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("portdetective-state-{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn test_load_missing_file_is_default() {
+        let value: BTreeMap<String, u32> = load(&scratch("missing.json")).unwrap();
+        assert!(value.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path = scratch("nested/round-trip.json");
+        let value = BTreeMap::from([("a".to_string(), 1u32)]);
+        save(&path, &value).unwrap();
+
+        let loaded: BTreeMap<String, u32> = load(&path).unwrap();
+        assert_eq!(loaded, value);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_load_corrupt_file_is_error() {
+        let path = scratch("corrupt.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+
+        let result: Result<BTreeMap<String, u32>> = load(&path);
+        assert!(matches!(result, Err(PortDetectiveError::State { .. })));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,79 @@
+//! Whether each named project port is being listened on
+
+use crate::model::PortEntry;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// A named port and whoever is listening on it
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub port: u16,
+    pub up: bool,
+    pub listeners: Vec<PortEntry>,
+}
+
+/// Match each named port against the port list, ordered by port
+pub fn status(ports: &BTreeMap<String, u16>, entries: &[PortEntry]) -> Vec<ServiceStatus> {
+    let mut services: Vec<ServiceStatus> = ports
+        .iter()
+        .map(|(name, &port)| {
+            let listeners: Vec<PortEntry> =
+                entries.iter().filter(|e| e.port == port).cloned().collect();
+            ServiceStatus {
+                name: name.clone(),
+                port,
+                up: !listeners.is_empty(),
+                listeners,
+            }
+        })
+        .collect();
+    services.sort_by(|a, b| (a.port, &a.name).cmp(&(b.port, &b.name)));
+    services
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Protocol;
+
+    fn entry(port: u16, pid: u32, name: &str) -> PortEntry {
+        PortEntry {
+            port,
+            protocol: Protocol::Tcp,
+            pid,
+            name: name.to_string(),
+            user: "dev".to_string(),
+            command: name.to_string(),
+            address: "127.0.0.1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_status() {
+        let ports = BTreeMap::from([
+            ("web".to_string(), 5173),
+            ("api".to_string(), 3000),
+            ("db".to_string(), 5432),
+        ]);
+        let entries = vec![
+            entry(22, 1, "sshd"),
+            entry(3000, 10, "node"),
+            entry(5173, 20, "vite"),
+        ];
+
+        let services = status(&ports, &entries);
+        let summary: Vec<(&str, bool)> = services.iter().map(|s| (s.name.as_str(), s.up)).collect();
+        assert_eq!(summary, vec![("api", true), ("web", true), ("db", false)]);
+        assert_eq!(services[0].listeners[0].pid, 10);
+        assert!(services[2].listeners.is_empty());
+    }
+
+    #[test]
+    fn test_port_with_unknown_owner_is_up() {
+        let ports = BTreeMap::from([("db".to_string(), 5432)]);
+        let services = status(&ports, &[entry(5432, 0, "unknown")]);
+        assert!(services[0].up);
+        assert!(services[0].listeners[0].is_unknown());
+    }
+}
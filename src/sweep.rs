@@ -0,0 +1,266 @@
+//! Finding stale and orphaned dev servers worth cleaning up
+
+use crate::error::Result;
+use crate::model::KillTarget;
+use crate::state;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Parents that adopt orphaned processes
+pub const REAPERS: &[&str] = &["init", "systemd", "launchd"];
+
+/// Thresholds for the time-based sweep rules
+#[derive(Debug, Clone, Copy)]
+pub struct Criteria {
+    /// No established connections for at least this long
+    pub idle: Duration,
+    /// Started at least this long ago
+    pub older_than: Duration,
+}
+
+/// What is known about a candidate beyond its `ProcessInfo`
+#[derive(Debug, Clone, Default)]
+pub struct Facts {
+    /// Whether it has a controlling terminal, if that can be told
+    pub has_tty: Option<bool>,
+    /// Established connections on its ports right now
+    pub open_connections: usize,
+    /// Last time a connection was seen on its ports
+    pub last_active: Option<DateTime<Local>>,
+}
+
+/// A listener that matched at least one sweep rule
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    #[serde(flatten)]
+    pub target: KillTarget,
+    pub reasons: Vec<String>,
+}
+
+/// Why a listener should be swept; empty if it should be left alone
+pub fn reasons(
+    target: &KillTarget,
+    facts: &Facts,
+    criteria: &Criteria,
+    now: DateTime<Local>,
+) -> Vec<String> {
+    let info = &target.process;
+    let mut reasons = Vec::new();
+
+    let reaped = info.parent_pid == Some(1)
+        || info
+            .parent_name
+            .as_deref()
+            .is_some_and(|name| REAPERS.contains(&name));
+    if reaped {
+        reasons.push(format!(
+            "orphaned (parent is {})",
+            info.parent_name.as_deref().unwrap_or("PID 1")
+        ));
+    }
+
+    if facts.has_tty == Some(false) {
+        reasons.push("no controlling terminal".to_string());
+    }
+
+    if facts.open_connections == 0 {
+        let since = facts.last_active.or(info.started);
+        if let Some(since) = since
+            && age(since, now) >= criteria.idle
+        {
+            reasons.push(format!("idle for {}", format_age(age(since, now))));
+        }
+    }
+
+    if let Some(started) = info.started
+        && age(started, now) >= criteria.older_than
+    {
+        reasons.push(format!("started {} ago", format_age(age(started, now))));
+    }
+
+    reasons
+}
+
+fn age(since: DateTime<Local>, now: DateTime<Local>) -> Duration {
+    (now - since).to_std().unwrap_or_default()
+}
+
+/// Render an age as its largest whole unit, e.g. `5h` or `3d`
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        s if s >= 86_400 => format!("{}d", s / 86_400),
+        s if s >= 3_600 => format!("{}h", s / 3_600),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// When connections were last seen on each listener, across sweeps.
+///
+/// Keyed by PID and start time so a recycled PID starts with a clean slate.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Activity {
+    pub last_active: BTreeMap<String, DateTime<Local>>,
+}
+
+impl Activity {
+    /// Default location of the activity record
+    pub fn path() -> PathBuf {
+        state::state_dir().join("activity.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        state::load(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        state::save(path, self)
+    }
+
+    pub fn key(target: &KillTarget) -> String {
+        match target.process.started {
+            Some(started) => format!("{}@{}", target.process.pid, started.timestamp()),
+            None => target.process.pid.to_string(),
+        }
+    }
+
+    pub fn get(&self, target: &KillTarget) -> Option<DateTime<Local>> {
+        self.last_active.get(&Self::key(target)).copied()
+    }
+
+    /// Record what was seen now, forgetting listeners that are gone
+    pub fn observe(&mut self, seen: &[(&KillTarget, usize)], now: DateTime<Local>) {
+        let live: Vec<String> = seen.iter().map(|(t, _)| Self::key(t)).collect();
+        self.last_active.retain(|key, _| live.contains(key));
+        for (target, open) in seen {
+            if *open > 0 {
+                self.last_active.insert(Self::key(target), now);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ProcessInfo, Protocol};
+    use chrono::Duration as ChronoDuration;
+
+    const HOUR: Duration = Duration::from_secs(3_600);
+
+    fn criteria() -> Criteria {
+        Criteria {
+            idle: 4 * HOUR,
+            older_than: 72 * HOUR,
+        }
+    }
+
+    fn target(parent: (u32, &str), started_hours_ago: i64, now: DateTime<Local>) -> KillTarget {
+        KillTarget {
+            ports: vec![5173],
+            process: ProcessInfo {
+                pid: 42,
+                name: "vite".to_string(),
+                user: "dev".to_string(),
+                uid: None,
+                command: vec!["vite".to_string()],
+                cwd: None,
+                exe: None,
+                parent_pid: Some(parent.0),
+                parent_name: Some(parent.1.to_string()),
+                started: Some(now - ChronoDuration::hours(started_hours_ago)),
+                manager: None,
+                protocol: Protocol::Tcp,
+            },
+        }
+    }
+
+    fn busy() -> Facts {
+        Facts {
+            has_tty: Some(true),
+            open_connections: 1,
+            last_active: None,
+        }
+    }
+
+    #[test]
+    fn test_fresh_attached_busy_server_is_kept() {
+        let now = Local::now();
+        let t = target((100, "zsh"), 1, now);
+        assert!(reasons(&t, &busy(), &criteria(), now).is_empty());
+    }
+
+    #[test]
+    fn test_orphan_and_no_tty() {
+        let now = Local::now();
+        let t = target((1, "systemd"), 1, now);
+        let facts = Facts {
+            has_tty: Some(false),
+            ..busy()
+        };
+        assert_eq!(
+            reasons(&t, &facts, &criteria(), now),
+            vec!["orphaned (parent is systemd)", "no controlling terminal"]
+        );
+
+        // A user systemd instance is a subreaper, not PID 1
+        let t = target((900, "systemd"), 1, now);
+        assert_eq!(reasons(&t, &busy(), &criteria(), now).len(), 1);
+    }
+
+    #[test]
+    fn test_idle_uses_last_activity_then_start_time() {
+        let now = Local::now();
+        let t = target((100, "zsh"), 10, now);
+        let idle = Facts {
+            open_connections: 0,
+            ..busy()
+        };
+        assert_eq!(reasons(&t, &idle, &criteria(), now), vec!["idle for 10h"]);
+
+        let recently_active = Facts {
+            last_active: Some(now - ChronoDuration::hours(1)),
+            ..idle
+        };
+        assert!(reasons(&t, &recently_active, &criteria(), now).is_empty());
+    }
+
+    #[test]
+    fn test_old_server() {
+        let now = Local::now();
+        let t = target((100, "zsh"), 24 * 5, now);
+        assert_eq!(
+            reasons(&t, &busy(), &criteria(), now),
+            vec!["started 5d ago"]
+        );
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(30)), "30s");
+        assert_eq!(format_age(Duration::from_secs(90)), "1m");
+        assert_eq!(format_age(5 * HOUR), "5h");
+        assert_eq!(format_age(50 * HOUR), "2d");
+    }
+
+    #[test]
+    fn test_activity_observe() {
+        let now = Local::now();
+        let a = target((100, "zsh"), 1, now);
+        let mut b = target((100, "zsh"), 2, now);
+        b.process.pid = 43;
+
+        let mut activity = Activity::default();
+        activity.observe(&[(&a, 2), (&b, 0)], now);
+        assert_eq!(activity.get(&a), Some(now));
+        assert_eq!(activity.get(&b), None);
+
+        // a is gone on the next sweep and is forgotten
+        activity.observe(&[(&b, 0)], now);
+        assert!(activity.last_active.is_empty());
+    }
+}
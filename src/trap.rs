@@ -0,0 +1,182 @@
+//! Catching short-lived processes that briefly bind a port
+
+use crate::error::Result;
+use crate::model::{ProcessInfo, Protocol};
+use crate::net::Binding;
+use crate::proc;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// One process in a binder's parent chain
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Ancestor {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// A process seen with a socket on the trapped port
+#[derive(Debug, Clone, Serialize)]
+pub struct Sighting {
+    pub seen: DateTime<Local>,
+    pub port: u16,
+    pub protocol: Protocol,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// Missing if the socket was gone before its owner could be found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Missing if the owner exited before it could be inspected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<ProcessInfo>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ancestry: Vec<Ancestor>,
+}
+
+/// Snapshot every owner of a new binding, argv and cwd first since the
+/// process may be about to exit
+pub fn capture(binding: &Binding) -> Vec<Sighting> {
+    let seen = Local::now();
+    let sighting = |pid: Option<u32>| Sighting {
+        seen,
+        port: binding.port,
+        protocol: binding.protocol,
+        state: binding.state.clone(),
+        pid,
+        process: None,
+        ancestry: Vec::new(),
+    };
+
+    if binding.pids.is_empty() {
+        return vec![sighting(None)];
+    }
+    binding
+        .pids
+        .iter()
+        .map(|&pid| Sighting {
+            process: proc::inspect(pid, binding.protocol).ok(),
+            ancestry: proc::ancestors(pid)
+                .into_iter()
+                .map(|(pid, name)| Ancestor { pid, name })
+                .collect(),
+            ..sighting(Some(pid))
+        })
+        .collect()
+}
+
+/// Poll `scan` every `interval` for `duration`, capturing and logging each
+/// socket the first time it shows up. Returns the number of sightings.
+pub fn trap(
+    duration: Duration,
+    interval: Duration,
+    mut scan: impl FnMut() -> Result<Vec<Binding>>,
+    mut capture: impl FnMut(&Binding) -> Vec<Sighting>,
+    mut log: impl FnMut(&Sighting) -> Result<()>,
+) -> Result<usize> {
+    let start = Instant::now();
+    let mut known: HashSet<u64> = HashSet::new();
+    let mut sightings = 0;
+
+    loop {
+        let bindings = scan()?;
+        // Forget sockets that closed so a reused inode counts as new
+        known.retain(|id| bindings.iter().any(|b| b.id == *id));
+        for binding in bindings {
+            if !known.insert(binding.id) {
+                continue;
+            }
+            for sighting in capture(&binding) {
+                log(&sighting)?;
+                sightings += 1;
+            }
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            return Ok(sightings);
+        }
+        thread::sleep(interval.min(duration - elapsed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(id: u64, pids: &[u32]) -> Binding {
+        Binding {
+            id,
+            port: 8080,
+            protocol: Protocol::Tcp,
+            address: "127.0.0.1".parse().unwrap(),
+            state: Some("LISTEN".to_string()),
+            listening: true,
+            pids: pids.to_vec(),
+        }
+    }
+
+    fn run(polls: Vec<Vec<Binding>>) -> Vec<Option<u32>> {
+        let mut polls = polls.into_iter();
+        let mut logged = Vec::new();
+        let count = trap(
+            Duration::from_millis(30),
+            Duration::from_millis(1),
+            || Ok(polls.next().unwrap_or_default()),
+            |b| {
+                let mut sightings = capture(&Binding {
+                    pids: Vec::new(),
+                    ..b.clone()
+                });
+                sightings[0].pid = b.pids.first().copied();
+                sightings
+            },
+            |s| {
+                logged.push(s.pid);
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(count, logged.len());
+        logged
+    }
+
+    #[test]
+    fn test_trap_logs_each_socket_once() {
+        let logged = run(vec![
+            vec![],
+            vec![binding(1, &[100])],
+            vec![binding(1, &[100])],
+            vec![binding(1, &[100]), binding(2, &[200])],
+            vec![],
+        ]);
+        assert_eq!(logged, vec![Some(100), Some(200)]);
+    }
+
+    #[test]
+    fn test_trap_counts_reused_socket_again() {
+        let logged = run(vec![
+            vec![binding(1, &[100])],
+            vec![],
+            vec![binding(1, &[300])],
+        ]);
+        assert_eq!(logged, vec![Some(100), Some(300)]);
+    }
+
+    #[test]
+    fn test_capture_without_owner() {
+        let sightings = capture(&binding(1, &[]));
+        assert_eq!(sightings.len(), 1);
+        assert!(sightings[0].pid.is_none());
+        assert!(sightings[0].process.is_none());
+    }
+
+    #[test]
+    fn test_capture_snapshots_owner() {
+        let sightings = capture(&binding(1, &[std::process::id()]));
+        let process = sightings[0].process.as_ref().unwrap();
+        assert_eq!(process.pid, std::process::id());
+        assert!(!process.command.is_empty());
+    }
+}
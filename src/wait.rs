@@ -0,0 +1,182 @@
+//! Blocking until a port reaches a state, for scripts
+
+use crate::cli::WaitUntil;
+use crate::error::Result;
+use crate::listing::Selector;
+use crate::model::ProcessInfo;
+use serde::Serialize;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Outcome of waiting on a port
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitReport {
+    pub port: u16,
+    pub until: WaitUntil,
+    /// Whether the state was reached before the timeout
+    pub reached: bool,
+    pub elapsed_ms: u64,
+    /// The matching owner once the port is listening
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<ProcessInfo>,
+}
+
+/// What was listening on the port at one poll
+#[derive(Debug, Clone, Default)]
+pub struct Listeners {
+    /// Listening sockets on the port, whether or not their owner is visible
+    pub sockets: usize,
+    /// The owners that could be inspected
+    pub owners: Vec<ProcessInfo>,
+}
+
+/// Poll `listeners` every `interval` until the port is in the wanted state or
+/// `timeout` expires.
+///
+/// Waiting for `listening` ends when an owner matching `selector` shows up,
+/// or any socket when the selector is empty. Waiting for `free` ends only
+/// when no socket at all is left, whoever owns it.
+pub fn wait_for(
+    port: u16,
+    until: WaitUntil,
+    selector: &Selector,
+    timeout: Duration,
+    interval: Duration,
+    mut listeners: impl FnMut() -> Result<Listeners>,
+) -> Result<WaitReport> {
+    let start = Instant::now();
+    loop {
+        let Listeners { sockets, owners } = listeners()?;
+        let owner = owners.into_iter().find(|p| selector.matches(p));
+        let reached = match until {
+            WaitUntil::Listening if selector.is_empty() => sockets > 0,
+            WaitUntil::Listening => owner.is_some(),
+            WaitUntil::Free => sockets == 0,
+        };
+
+        let elapsed = start.elapsed();
+        if reached || elapsed >= timeout {
+            return Ok(WaitReport {
+                port,
+                until,
+                reached,
+                elapsed_ms: elapsed.as_millis() as u64,
+                process: owner.filter(|_| until == WaitUntil::Listening),
+            });
+        }
+        thread::sleep(interval.min(timeout - elapsed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Protocol;
+    use regex::Regex;
+
+    fn process(name: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid: 42,
+            name: name.to_string(),
+            user: "dev".to_string(),
+            uid: None,
+            command: vec![name.to_string()],
+            cwd: None,
+            exe: None,
+            parent_pid: None,
+            parent_name: None,
+            started: None,
+            manager: None,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    fn owned(owners: Vec<ProcessInfo>) -> Listeners {
+        Listeners {
+            sockets: owners.len(),
+            owners,
+        }
+    }
+
+    fn wait(until: WaitUntil, selector: &Selector, polls: Vec<Listeners>) -> (WaitReport, usize) {
+        let mut polls = polls.into_iter();
+        let mut count = 0;
+        let report = wait_for(
+            5432,
+            until,
+            selector,
+            Duration::from_millis(50),
+            Duration::from_millis(1),
+            || {
+                count += 1;
+                Ok(polls.next().unwrap_or_default())
+            },
+        )
+        .unwrap();
+        (report, count)
+    }
+
+    #[test]
+    fn test_wait_until_listening() {
+        let (report, polls) = wait(
+            WaitUntil::Listening,
+            &Selector::default(),
+            vec![
+                owned(vec![]),
+                owned(vec![]),
+                owned(vec![process("postgres")]),
+            ],
+        );
+        assert!(report.reached);
+        assert_eq!(polls, 3);
+        assert_eq!(report.process.unwrap().name, "postgres");
+    }
+
+    #[test]
+    fn test_wait_until_free() {
+        let (report, polls) = wait(
+            WaitUntil::Free,
+            &Selector::default(),
+            vec![owned(vec![process("postgres")]), owned(vec![])],
+        );
+        assert!(report.reached);
+        assert_eq!(polls, 2);
+        assert!(report.process.is_none());
+    }
+
+    #[test]
+    fn test_wait_requires_matching_owner() {
+        let selector = Selector {
+            name: Some(Regex::new("^postgres$").unwrap()),
+            ..Selector::default()
+        };
+        let polls = vec![owned(vec![process("pgbouncer")]); 1000];
+
+        let (report, _) = wait(WaitUntil::Listening, &selector, polls.clone());
+        assert!(!report.reached);
+        assert!(report.elapsed_ms >= 50);
+
+        // A port held by anyone is not free, matching or not
+        let (report, _) = wait(WaitUntil::Free, &selector, polls);
+        assert!(!report.reached);
+    }
+
+    #[test]
+    fn test_wait_counts_sockets_without_visible_owner() {
+        let hidden = vec![
+            Listeners {
+                sockets: 1,
+                owners: Vec::new(),
+            };
+            1000
+        ];
+
+        let (report, polls) = wait(WaitUntil::Listening, &Selector::default(), hidden.clone());
+        assert!(report.reached);
+        assert_eq!(polls, 1);
+        assert!(report.process.is_none());
+
+        let (report, _) = wait(WaitUntil::Free, &Selector::default(), hidden);
+        assert!(!report.reached);
+    }
+}
@@ -0,0 +1,544 @@
+//! Live-updating port table for `watch`
+
+use crate::color::Colorize;
+use crate::error::{PortDetectiveError, Result};
+use crate::model::{PortEntry, Protocol};
+use crate::sweep;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, terminal};
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Column the table is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Port,
+    Pid,
+    Process,
+    User,
+}
+
+impl SortKey {
+    fn next(self) -> Self {
+        match self {
+            SortKey::Port => SortKey::Pid,
+            SortKey::Pid => SortKey::Process,
+            SortKey::Process => SortKey::User,
+            SortKey::User => SortKey::Port,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Port => "port",
+            SortKey::Pid => "pid",
+            SortKey::Process => "process",
+            SortKey::User => "user",
+        }
+    }
+}
+
+/// How a row changed since the previous refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Unchanged,
+}
+
+/// A row of the table
+#[derive(Debug, Clone)]
+pub struct Row {
+    pub entry: PortEntry,
+    pub change: Change,
+}
+
+/// What a key press asks the event loop to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    None,
+    Quit,
+    /// Kill the process of the selected row, and only that one
+    Kill {
+        port: u16,
+        pid: u32,
+    },
+}
+
+type RowKey = (u16, Protocol, u32);
+
+fn row_key(entry: &PortEntry) -> RowKey {
+    (entry.port, entry.protocol, entry.pid)
+}
+
+/// Table contents and view state, independent of the terminal
+#[derive(Debug, Default)]
+pub struct Dashboard {
+    current: Vec<PortEntry>,
+    added: HashSet<RowKey>,
+    removed: Vec<PortEntry>,
+    refreshes: usize,
+    pub sort: SortKey,
+    pub descending: bool,
+    pub filter: String,
+    pub editing_filter: bool,
+    pub selected: usize,
+}
+
+impl Dashboard {
+    /// Replace the table, remembering what appeared and disappeared
+    pub fn refresh(&mut self, entries: Vec<PortEntry>) {
+        let selected = self.selected_row().map(|r| row_key(&r.entry));
+        let old: HashSet<RowKey> = self.current.iter().map(row_key).collect();
+        let new: HashSet<RowKey> = entries.iter().map(row_key).collect();
+
+        if self.refreshes > 0 {
+            self.added = new.difference(&old).copied().collect();
+            self.removed = self
+                .current
+                .drain(..)
+                .filter(|e| !new.contains(&row_key(e)))
+                .collect();
+        }
+        self.current = entries;
+        self.refreshes += 1;
+
+        // Keep the cursor on the same row when others come and go
+        let rows = self.rows();
+        if let Some(i) = selected.and_then(|k| rows.iter().position(|r| row_key(&r.entry) == k)) {
+            self.selected = i;
+        }
+        self.selected = self.selected.min(rows.len().saturating_sub(1));
+    }
+
+    /// Rows that appeared since the previous refresh
+    pub fn added(&self) -> Vec<&PortEntry> {
+        self.current
+            .iter()
+            .filter(|e| self.added.contains(&row_key(e)))
+            .collect()
+    }
+
+    /// Rows that disappeared since the previous refresh
+    pub fn removed(&self) -> &[PortEntry] {
+        &self.removed
+    }
+
+    /// Visible rows, filtered and sorted, with departed rows kept for one refresh
+    pub fn rows(&self) -> Vec<Row> {
+        let needle = self.filter.to_lowercase();
+        let mut rows: Vec<Row> = self
+            .current
+            .iter()
+            .map(|entry| Row {
+                entry: entry.clone(),
+                change: if self.added.contains(&row_key(entry)) {
+                    Change::Added
+                } else {
+                    Change::Unchanged
+                },
+            })
+            .chain(self.removed.iter().map(|entry| Row {
+                entry: entry.clone(),
+                change: Change::Removed,
+            }))
+            .filter(|row| needle.is_empty() || matches_filter(&row.entry, &needle))
+            .collect();
+
+        match self.sort {
+            SortKey::Port => rows.sort_by_key(|r| (r.entry.port, r.entry.pid)),
+            SortKey::Pid => rows.sort_by_key(|r| (r.entry.pid, r.entry.port)),
+            SortKey::Process => rows.sort_by(|a, b| {
+                (a.entry.name.to_lowercase(), a.entry.port)
+                    .cmp(&(b.entry.name.to_lowercase(), b.entry.port))
+            }),
+            SortKey::User => rows
+                .sort_by(|a, b| (&a.entry.user, a.entry.port).cmp(&(&b.entry.user, b.entry.port))),
+        }
+        if self.descending {
+            rows.reverse();
+        }
+        rows
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.rows().into_iter().nth(self.selected)
+    }
+
+    /// Apply a key press to the view
+    pub fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Action::Quit;
+        }
+
+        if self.editing_filter {
+            match key.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.filter.clear();
+                    self.editing_filter = false;
+                }
+                _ => {}
+            }
+            self.selected = 0;
+            return Action::None;
+        }
+
+        let last = self.rows().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(last),
+            KeyCode::Home => self.selected = 0,
+            KeyCode::End => self.selected = last,
+            KeyCode::Char('s') => self.sort = self.sort.next(),
+            KeyCode::Char('r') => self.descending = !self.descending,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Char('k') => {
+                if let Some(row) = self.selected_row()
+                    && row.change != Change::Removed
+                {
+                    return Action::Kill {
+                        port: row.entry.port,
+                        pid: row.entry.pid,
+                    };
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+}
+
+fn matches_filter(entry: &PortEntry, needle: &str) -> bool {
+    entry.port.to_string().contains(needle)
+        || entry.name.to_lowercase().contains(needle)
+        || entry.user.to_lowercase().contains(needle)
+        || entry.command.to_lowercase().contains(needle)
+}
+
+/// Width of the columns before COMMAND, including the change marker
+const FIXED_WIDTH: usize = 2 + 7 + 1 + 6 + 1 + 8 + 1 + 12 + 1 + 10 + 1;
+
+fn format_row(marker: &str, entry: &PortEntry, width: usize) -> String {
+    let command: String = entry
+        .command
+        .chars()
+        .take(width.saturating_sub(FIXED_WIDTH))
+        .collect();
+    format!(
+        "{:<2}{:<7} {:<6} {:<8} {:<12} {:<10} {}",
+        marker,
+        entry.port,
+        entry.protocol.to_string(),
+        entry.pid,
+        entry.name.chars().take(12).collect::<String>(),
+        entry.user.chars().take(10).collect::<String>(),
+        command
+    )
+}
+
+/// Render the screen as lines that fit `width` x `height`
+pub fn render(dash: &Dashboard, interval: Duration, width: usize, height: usize) -> Vec<String> {
+    let rows = dash.rows();
+    let mut lines = Vec::with_capacity(height);
+
+    let direction = if dash.descending { "↓" } else { "↑" };
+    let mut title = format!(
+        "🔎 portdetective watch · every {} · {} port(s) · sorted by {} {}",
+        sweep::format_age(interval),
+        dash.current.len(),
+        dash.sort.label(),
+        direction
+    );
+    if !dash.added.is_empty() || !dash.removed.is_empty() {
+        title.push_str(&format!(
+            " · +{} new, -{} gone",
+            dash.added.len(),
+            dash.removed.len()
+        ));
+    }
+    lines.push(title.bold().to_string());
+
+    let header = format!(
+        "  {:<7} {:<6} {:<8} {:<12} {:<10} {}",
+        "PORT", "PROTO", "PID", "PROCESS", "USER", "COMMAND"
+    );
+    lines.push(header.bold().underline().to_string());
+
+    // Title, header, blank line and footer take four lines
+    let visible = height.saturating_sub(4).max(1);
+    let offset = dash.selected.saturating_sub(visible - 1);
+    for (i, row) in rows.iter().enumerate().skip(offset).take(visible) {
+        let marker = match row.change {
+            Change::Added => "+",
+            Change::Removed => "-",
+            Change::Unchanged => "",
+        };
+        let line = format_row(marker, &row.entry, width);
+        let line = if i == dash.selected {
+            line.reversed().to_string()
+        } else {
+            match row.change {
+                Change::Added => line.green().bold().to_string(),
+                Change::Removed => line.red().dimmed().to_string(),
+                Change::Unchanged => line,
+            }
+        };
+        lines.push(line);
+    }
+    if rows.is_empty() {
+        lines.push(format!("  {}", "No matching listening ports".dimmed()));
+    }
+
+    while lines.len() < height.saturating_sub(1) {
+        lines.push(String::new());
+    }
+    let footer = if dash.editing_filter {
+        format!("Filter: {}▏  (Enter to apply, Esc to clear)", dash.filter)
+    } else if dash.filter.is_empty() {
+        "↑/↓ select  k kill  s sort  r reverse  / filter  q quit".to_string()
+    } else {
+        format!(
+            "↑/↓ select  k kill  s sort  r reverse  / filter ({})  q quit",
+            dash.filter
+        )
+    };
+    lines.push(footer.dimmed().to_string());
+    lines
+}
+
+/// Alternate screen in raw mode, restored when dropped
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode().map_err(terminal_error)?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)
+            .map_err(terminal_error)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn terminal_error(e: io::Error) -> PortDetectiveError {
+    PortDetectiveError::Terminal(e.to_string())
+}
+
+fn draw(dash: &Dashboard, interval: Duration) -> Result<()> {
+    let (width, height) = terminal::size().map_err(terminal_error)?;
+    let mut stdout = io::stdout();
+    for (y, line) in render(dash, interval, width as usize, height as usize)
+        .iter()
+        .enumerate()
+    {
+        queue!(
+            stdout,
+            cursor::MoveTo(0, y as u16),
+            terminal::Clear(terminal::ClearType::UntilNewLine)
+        )
+        .map_err(terminal_error)?;
+        write!(stdout, "{}", line).map_err(terminal_error)?;
+    }
+    stdout.flush().map_err(terminal_error)
+}
+
+/// Run the full-screen dashboard until the user quits.
+///
+/// `kill` runs on the normal screen so its prompt works as usual; the
+/// dashboard comes back once the user presses Enter.
+pub fn run(
+    interval: Duration,
+    mut collect: impl FnMut() -> Result<Vec<PortEntry>>,
+    mut kill: impl FnMut(u16, u32),
+) -> Result<()> {
+    let mut dash = Dashboard::default();
+    let mut screen = Some(Screen::enter()?);
+    let mut last_refresh: Option<Instant> = None;
+
+    loop {
+        if last_refresh.is_none_or(|t| t.elapsed() >= interval) {
+            dash.refresh(collect()?);
+            last_refresh = Some(Instant::now());
+        }
+        draw(&dash, interval)?;
+
+        let remaining = last_refresh
+            .map(|t| interval.saturating_sub(t.elapsed()))
+            .unwrap_or_default();
+        if !event::poll(remaining).map_err(terminal_error)? {
+            continue;
+        }
+        let Event::Key(key) = event::read().map_err(terminal_error)? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match dash.handle_key(key) {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Kill { port, pid } => {
+                drop(screen.take());
+                kill(port, pid);
+                print!("\nPress Enter to return to the dashboard...");
+                let _ = io::stdout().flush();
+                let _ = io::stdin().read_line(&mut String::new());
+                screen = Some(Screen::enter()?);
+                last_refresh = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(port: u16, pid: u32, name: &str) -> PortEntry {
+        PortEntry {
+            port,
+            protocol: Protocol::Tcp,
+            pid,
+            name: name.to_string(),
+            user: "dev".to_string(),
+            command: format!("{} --port {}", name, port),
+            address: "127.0.0.1".to_string(),
+        }
+    }
+
+    fn press(dash: &mut Dashboard, code: KeyCode) -> Action {
+        dash.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn ports(dash: &Dashboard) -> Vec<(u16, Change)> {
+        dash.rows()
+            .iter()
+            .map(|r| (r.entry.port, r.change))
+            .collect()
+    }
+
+    #[test]
+    fn test_refresh_marks_added_and_removed() {
+        let mut dash = Dashboard::default();
+        dash.refresh(vec![entry(3000, 1, "node"), entry(5432, 2, "postgres")]);
+        // Nothing is new on the first refresh
+        assert!(dash.added().is_empty());
+
+        dash.refresh(vec![entry(5432, 2, "postgres"), entry(8080, 3, "java")]);
+        assert_eq!(
+            ports(&dash),
+            vec![
+                (3000, Change::Removed),
+                (5432, Change::Unchanged),
+                (8080, Change::Added)
+            ]
+        );
+
+        // Departed rows are shown for one refresh only
+        dash.refresh(vec![entry(5432, 2, "postgres"), entry(8080, 3, "java")]);
+        assert_eq!(
+            ports(&dash),
+            vec![(5432, Change::Unchanged), (8080, Change::Unchanged)]
+        );
+    }
+
+    #[test]
+    fn test_sort_and_reverse() {
+        let mut dash = Dashboard::default();
+        dash.refresh(vec![
+            entry(3000, 30, "node"),
+            entry(5432, 10, "postgres"),
+            entry(8080, 20, "java"),
+        ]);
+
+        press(&mut dash, KeyCode::Char('s'));
+        assert_eq!(dash.sort, SortKey::Pid);
+        let order: Vec<u16> = dash.rows().iter().map(|r| r.entry.port).collect();
+        assert_eq!(order, vec![5432, 8080, 3000]);
+
+        press(&mut dash, KeyCode::Char('s'));
+        press(&mut dash, KeyCode::Char('r'));
+        let order: Vec<u16> = dash.rows().iter().map(|r| r.entry.port).collect();
+        assert_eq!(order, vec![5432, 3000, 8080]);
+    }
+
+    #[test]
+    fn test_filter_editing() {
+        let mut dash = Dashboard::default();
+        dash.refresh(vec![entry(3000, 1, "node"), entry(5432, 2, "postgres")]);
+
+        press(&mut dash, KeyCode::Char('/'));
+        for c in "POST".chars() {
+            press(&mut dash, KeyCode::Char(c));
+        }
+        // Typing a filter does not trigger commands
+        assert_eq!(press(&mut dash, KeyCode::Enter), Action::None);
+        assert_eq!(ports(&dash), vec![(5432, Change::Unchanged)]);
+
+        press(&mut dash, KeyCode::Char('/'));
+        press(&mut dash, KeyCode::Esc);
+        assert_eq!(dash.rows().len(), 2);
+    }
+
+    #[test]
+    fn test_kill_selected_row() {
+        let mut dash = Dashboard::default();
+        dash.refresh(vec![entry(3000, 1, "node"), entry(5432, 2, "postgres")]);
+
+        press(&mut dash, KeyCode::Down);
+        press(&mut dash, KeyCode::Down);
+        assert_eq!(
+            press(&mut dash, KeyCode::Char('k')),
+            Action::Kill { port: 5432, pid: 2 }
+        );
+
+        // Only the highlighted process, not everything else on the port
+        dash.refresh(vec![entry(5432, 2, "postgres"), entry(5432, 3, "postgres")]);
+        press(&mut dash, KeyCode::End);
+        assert_eq!(
+            press(&mut dash, KeyCode::Char('k')),
+            Action::Kill { port: 5432, pid: 3 }
+        );
+
+        // A row that is already gone cannot be killed
+        dash.refresh(vec![entry(3000, 1, "node")]);
+        press(&mut dash, KeyCode::End);
+        assert_eq!(press(&mut dash, KeyCode::Char('k')), Action::None);
+        assert_eq!(press(&mut dash, KeyCode::Char('q')), Action::Quit);
+    }
+
+    #[test]
+    fn test_selection_follows_row() {
+        let mut dash = Dashboard::default();
+        dash.refresh(vec![entry(5432, 2, "postgres")]);
+        dash.refresh(vec![entry(3000, 1, "node"), entry(5432, 2, "postgres")]);
+        assert_eq!(dash.selected, 1);
+    }
+
+    #[test]
+    fn test_render_fits_screen() {
+        let mut dash = Dashboard::default();
+        dash.refresh((0..50).map(|i| entry(3000 + i, i as u32, "node")).collect());
+        dash.selected = 40;
+
+        let lines = render(&dash, Duration::from_secs(2), 80, 20);
+        assert_eq!(lines.len(), 20);
+        // The selected row is scrolled into view
+        assert!(lines.iter().any(|l| l.contains("3040")));
+        assert!(!lines.iter().any(|l| l.contains("3000 ")));
+    }
+}